@@ -0,0 +1,279 @@
+//! Command line arguments.
+
+use crate::rate_limit::{DEFAULT_BURST, DEFAULT_RATE};
+use crate::scrape_web::{DEFAULT_CONCURRENCY, DEFAULT_RETRIES};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use server_lib::validation::duplicates::{DEFAULT_MAX_DISTANCE, DEFAULT_MIN_SIMILARITY};
+use server_lib::validation::DEFAULT_DUPLICATE_DISTANCE;
+use std::path::PathBuf;
+
+/// Default filepath to the KML file from flightlog.org.
+const DEFAULT_KML: &str = "crates/scraper/resources/country_160.kml";
+
+/// Scrape paragliding takeoffs.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Command to run.
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Directory to write log files to.
+    #[arg(long, global = true, default_value = "logs", value_name = "DIR")]
+    pub log_dir: PathBuf,
+
+    /// Don't write a log file.
+    #[arg(long, global = true)]
+    pub no_log_file: bool,
+
+    /// Log more (`-v` for debug, `-vv` for trace).
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Only log warnings and errors.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Don't write anything, only log what would be written.
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+}
+
+/// Subcommands.
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print the URLs of takeoffs in sources.
+    Discover {
+        /// Sources.
+        #[command(flatten)]
+        sources: SourceArgs,
+
+        /// Only print takeoffs that are not in the database.
+        #[arg(long)]
+        missing: bool,
+    },
+    /// Scrape takeoffs.
+    Scrape {
+        /// Sources.
+        #[command(flatten)]
+        sources: SourceArgs,
+
+        /// Takeoffs to scrape.
+        #[command(flatten)]
+        input: InputArgs,
+
+        /// Concurrency and retries.
+        #[command(flatten)]
+        queue: QueueArgs,
+
+        /// Where to write takeoffs.
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Re-scrape takeoffs and apply upstream changes to the database.
+    Refresh {
+        /// Sources.
+        #[command(flatten)]
+        sources: SourceArgs,
+
+        /// Concurrency and retries.
+        #[command(flatten)]
+        queue: QueueArgs,
+    },
+    /// Import takeoffs from a file into the database.
+    Import {
+        /// File to import.
+        path: PathBuf,
+
+        /// File format (guessed from the extension by default).
+        #[arg(long, value_enum)]
+        format: Option<FileFormat>,
+    },
+    /// Validate takeoffs and print a JSON report.
+    Validate {
+        /// File to validate instead of the database.
+        #[arg(conflicts_with = "flag")]
+        path: Option<PathBuf>,
+
+        /// File format (guessed from the extension by default).
+        #[arg(long, value_enum)]
+        format: Option<FileFormat>,
+
+        /// Meters takeoffs must be apart to not be duplicates.
+        #[arg(long, default_value_t = DEFAULT_DUPLICATE_DISTANCE, value_parser = parse_positive)]
+        distance: f64,
+
+        /// Save issues in the database, replacing earlier issues.
+        #[arg(long)]
+        flag: bool,
+
+        /// Output file for the report (stdout by default).
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Review and merge duplicate takeoffs in the database.
+    Duplicates {
+        /// Duplicates command to run.
+        #[command(subcommand)]
+        command: DuplicatesCommand,
+    },
+}
+
+/// Duplicates subcommands.
+#[derive(Debug, Subcommand)]
+pub enum DuplicatesCommand {
+    /// Print candidate duplicates as JSON, best first.
+    List {
+        /// Meters candidates can be apart.
+        #[arg(long, default_value_t = DEFAULT_MAX_DISTANCE, value_parser = parse_positive)]
+        max_distance: f64,
+
+        /// Minimum name similarity of candidates, from 0 to 1.
+        #[arg(long, default_value_t = DEFAULT_MIN_SIMILARITY)]
+        min_similarity: f64,
+    },
+    /// Merge a takeoff into another and delete it.
+    Merge {
+        /// Id of the takeoff to keep.
+        keep: i32,
+
+        /// Id of the takeoff to merge and delete.
+        remove: i32,
+    },
+    /// Mark a pair of takeoffs as not duplicates.
+    Dismiss {
+        /// Id of a takeoff.
+        a: i32,
+
+        /// Id of the other takeoff.
+        b: i32,
+    },
+}
+
+/// Arguments selecting sources.
+#[derive(Debug, Args)]
+pub struct SourceArgs {
+    /// Source to use, can be repeated (all sources by default).
+    #[arg(long = "source", value_name = "NAME")]
+    pub sources: Vec<String>,
+
+    /// KML file from flightlog.org.
+    #[arg(long, default_value = DEFAULT_KML, value_name = "PATH")]
+    pub kml: String,
+
+    /// Fetch pages with a Chrome driver (requires the `selenium` feature).
+    #[arg(long)]
+    pub selenium: bool,
+
+    /// Requests per second to each host.
+    #[arg(long, default_value_t = DEFAULT_RATE, value_parser = parse_positive)]
+    pub rate: f64,
+
+    /// Requests that can be made at once to each host.
+    #[arg(long, default_value_t = DEFAULT_BURST)]
+    pub burst: u32,
+}
+
+/// Arguments selecting takeoffs to scrape.
+///
+/// Takeoffs missing from the database are scraped by default.
+/// Unless it's a dry run, progress is saved in the database so the run can be resumed.
+#[derive(Debug, Args)]
+#[group(multiple = false)]
+pub struct InputArgs {
+    /// File with one takeoff URL per line.
+    #[arg(long, value_name = "PATH")]
+    pub urls: Option<PathBuf>,
+
+    /// A single takeoff id, from the one `--source`.
+    #[arg(long, requires = "sources")]
+    pub id: Option<String>,
+
+    /// All discovered takeoffs, including those in the database.
+    #[arg(long)]
+    pub all: bool,
+
+    /// Takeoffs that were not scraped in the last run.
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Takeoffs that failed in earlier runs.
+    #[arg(long)]
+    pub retry_failed: bool,
+}
+
+/// Arguments for scraping many takeoffs.
+#[derive(Debug, Args)]
+pub struct QueueArgs {
+    /// Takeoffs to scrape at once (always 1 with `--selenium`).
+    #[arg(long, default_value_t = DEFAULT_CONCURRENCY)]
+    pub concurrency: usize,
+
+    /// Retries after transient errors, with exponential backoff.
+    #[arg(long, default_value_t = DEFAULT_RETRIES)]
+    pub retries: u32,
+}
+
+/// Arguments selecting where to write takeoffs.
+#[derive(Debug, Args)]
+pub struct OutputArgs {
+    /// Output target.
+    #[arg(long, value_enum, default_value_t = OutputTarget::Db)]
+    pub output: OutputTarget,
+
+    /// Output file for `ndjson` and `geojson` (stdout by default).
+    #[arg(long, value_name = "PATH")]
+    pub out: Option<PathBuf>,
+}
+
+/// Output targets.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum OutputTarget {
+    /// Insert into the database.
+    Db,
+    /// One JSON takeoff per line.
+    Ndjson,
+    /// A GeoJSON feature collection.
+    Geojson,
+}
+
+/// Parse a positive number.
+fn parse_positive(value: &str) -> Result<f64, String> {
+    match value.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(rate),
+        _ => Err(format!("{value} is not a positive number")),
+    }
+}
+
+/// Formats of files to import or validate.
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum FileFormat {
+    /// One JSON takeoff per line.
+    Ndjson,
+    /// A GeoJSON feature collection.
+    Geojson,
+    /// A KML file from flightlog.org, only placemarks missing from the database are imported.
+    ///
+    /// All placemarks are validated.
+    Kml,
+}
+
+impl FileFormat {
+    /// Guess the format from a file extension.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A filepath.
+    ///
+    /// # Returns
+    ///
+    /// The format, or `None` if the extension is unknown.
+    pub fn from_path(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            "geojson" | "json" => Some(Self::Geojson),
+            "kml" => Some(Self::Kml),
+            _ => None,
+        }
+    }
+}
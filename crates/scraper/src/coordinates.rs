@@ -0,0 +1,573 @@
+//! Parse coordinates.
+//!
+//! Supports degrees, minutes and seconds (DMS, e.g. `N 60° 38' 44''  E 6° 24' 28''`),
+//! degrees and decimal minutes (DDM, e.g. `60° 38.733' N, 6° 24.467' E`)
+//! and decimal degrees (e.g. `-33.9249, 18.4241`).
+//!
+//! Hemispheres (`N`, `S`, `E`, `W`, or Norwegian `NORD`, `ØST`, etc.) can be written before
+//! or after each coordinate, and take precedence over the order of the coordinates. Without
+//! hemispheres, the latitude comes first and negative values are south or west.
+//!
+//! Text with the same position written several times, on separate lines or in brackets
+//! (e.g. `N 60° 38' 44''  E 6° 24' 28''` followed by `60.64556, 6.40778`), gives the first one.
+
+use std::fmt::Display;
+
+/// Degrees that positions written several times can differ by (about 100 m).
+const AGREEMENT_DEGREES: f64 = 0.001;
+
+/// A position in decimal degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    /// Latitude, positive north.
+    pub latitude: f64,
+    /// Longitude, positive east.
+    pub longitude: f64,
+}
+
+/// Latitude or longitude.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Axis {
+    /// North-south.
+    Latitude,
+    /// East-west.
+    Longitude,
+}
+
+/// Error from parsing coordinates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoordinateError {
+    /// There are no numbers.
+    Empty,
+    /// There are not exactly two coordinates.
+    Count(usize),
+    /// A number can't be parsed.
+    InvalidNumber(String),
+    /// A coordinate has more than degrees, minutes and seconds.
+    TooManyComponents(usize),
+    /// Minutes or seconds are negative, not below 60, or follow fractional degrees or minutes.
+    InvalidComponent(&'static str, f64),
+    /// A hemisphere conflicts with the sign, or both coordinates are on the same axis.
+    Hemisphere(String),
+    /// A coordinate is out of range for its axis.
+    OutOfRange(Axis, f64),
+}
+
+impl Display for CoordinateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "no coordinates"),
+            Self::Count(count) => write!(f, "expected 2 coordinates, found {count}"),
+            Self::InvalidNumber(number) => write!(f, "invalid number {number}"),
+            Self::TooManyComponents(count) => {
+                write!(f, "coordinate has {count} components, expected at most 3")
+            }
+            Self::InvalidComponent(component, value) => write!(f, "invalid {component} {value}"),
+            Self::Hemisphere(reason) => write!(f, "invalid hemisphere: {reason}"),
+            Self::OutOfRange(Axis::Latitude, value) => write!(f, "latitude {value} out of range"),
+            Self::OutOfRange(Axis::Longitude, value) => write!(f, "longitude {value} out of range"),
+        }
+    }
+}
+
+impl std::error::Error for CoordinateError {}
+
+/// Unit marker after a number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Unit {
+    /// `°`
+    Degrees,
+    /// `'`
+    Minutes,
+    /// `''` or `"`
+    Seconds,
+}
+
+/// Token of a coordinate string.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A number, with an optional unit.
+    Number(f64, Option<Unit>),
+    /// `N`, `S`, `E` or `W`.
+    Hemisphere(char),
+    /// `,`, `;` or `/`.
+    Separator,
+}
+
+/// A single coordinate, before it's converted to decimal degrees.
+#[derive(Debug, Default)]
+struct Group {
+    numbers: Vec<f64>,
+    hemisphere: Option<char>,
+}
+
+/// Parse a latitude and longitude.
+///
+/// # Arguments
+///
+/// * `text` - Coordinates in DMS, DDM or decimal degrees. Other words (e.g. `DMS:`) are ignored.
+///
+/// # Errors
+///
+/// This function will return an error if there aren't exactly two valid coordinates, or
+/// positions written several times differ.
+///
+/// # Returns
+///
+/// [`Coordinates`] in decimal degrees.
+pub fn parse(text: &str) -> Result<Coordinates, CoordinateError> {
+    match parse_pair(text) {
+        Err(CoordinateError::Count(count)) => {
+            parse_alternatives(text).ok_or(CoordinateError::Count(count))
+        }
+        out => out,
+    }
+}
+
+/// Parse a position written several times, on separate lines or in brackets.
+///
+/// Returns the first position, or `None` if a part is invalid or the positions differ.
+fn parse_alternatives(text: &str) -> Option<Coordinates> {
+    let positions = text
+        .split(['\n', '(', ')', '[', ']', '|'])
+        .filter(|part| part.chars().any(|c| c.is_ascii_digit()))
+        .map(|part| parse_pair(part).ok())
+        .collect::<Option<Vec<Coordinates>>>()?;
+    let first = *positions.first()?;
+
+    positions
+        .iter()
+        .all(|position| {
+            (position.latitude - first.latitude).abs() < AGREEMENT_DEGREES
+                && (position.longitude - first.longitude).abs() < AGREEMENT_DEGREES
+        })
+        .then_some(first)
+}
+
+/// Parse a single latitude and longitude.
+///
+/// # Errors
+///
+/// This function will return an error if there aren't exactly two valid coordinates.
+fn parse_pair(text: &str) -> Result<Coordinates, CoordinateError> {
+    let tokens = tokenize(text)?;
+    let groups = group(&tokens);
+
+    match groups.len() {
+        0 => return Err(CoordinateError::Empty),
+        2 => {}
+        count => return Err(CoordinateError::Count(count)),
+    }
+
+    // Hemispheres decide the axis, otherwise latitude comes first
+    let axes = match (
+        groups[0].hemisphere.map(axis),
+        groups[1].hemisphere.map(axis),
+    ) {
+        (Some(a), Some(b)) if a == b => {
+            return Err(CoordinateError::Hemisphere(
+                "both coordinates on the same axis".to_owned(),
+            ))
+        }
+        (Some(Axis::Longitude), _) | (_, Some(Axis::Latitude)) => [Axis::Longitude, Axis::Latitude],
+        _ => [Axis::Latitude, Axis::Longitude],
+    };
+
+    let mut out = Coordinates {
+        latitude: 0.0,
+        longitude: 0.0,
+    };
+    for (group, axis) in groups.iter().zip(axes) {
+        let value = to_decimal(group)?;
+        match axis {
+            Axis::Latitude if (-90.0..=90.0).contains(&value) => out.latitude = value,
+            Axis::Longitude if (-180.0..=180.0).contains(&value) => out.longitude = value,
+            _ => return Err(CoordinateError::OutOfRange(axis, value)),
+        }
+    }
+
+    Ok(out)
+}
+
+/// Split text into tokens.
+///
+/// # Errors
+///
+/// This function will return an error if a number can't be parsed.
+fn tokenize(text: &str) -> Result<Vec<Token>, CoordinateError> {
+    let mut out = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '0'..='9' | '.' | '-' | '+' => {
+                let mut number = String::from(c);
+                while let Some(&next) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                    number.push(next);
+                    chars.next();
+                }
+
+                // A decimal comma, if minutes or seconds follow (e.g. `36,34"`)
+                if !number.contains('.') && chars.peek() == Some(&',') {
+                    let mut ahead = chars.clone();
+                    ahead.next();
+                    let decimals: String =
+                        ahead.clone().take_while(|c| c.is_ascii_digit()).collect();
+                    let unit = ahead.skip(decimals.len()).find(|c| *c != ' ');
+                    if !decimals.is_empty()
+                        && matches!(unit, Some('\'' | '′' | '’' | '"' | '″' | '”'))
+                    {
+                        number.push('.');
+                        number.push_str(&decimals);
+                        chars.nth(decimals.len());
+                    }
+                }
+
+                // A lone sign or dot isn't a number (e.g. `N - E`)
+                if !number.chars().any(|c| c.is_ascii_digit()) {
+                    continue;
+                }
+                let value = number
+                    .parse::<f64>()
+                    .map_err(|_| CoordinateError::InvalidNumber(number.clone()))?;
+
+                while chars.peek().is_some_and(|c| *c == ' ') {
+                    chars.next();
+                }
+                let unit = match chars.peek() {
+                    Some('°' | 'º' | '˚') => Some(Unit::Degrees),
+                    Some('\'' | '′' | '’') => Some(Unit::Minutes),
+                    Some('"' | '″' | '”') => Some(Unit::Seconds),
+                    _ => None,
+                };
+                if unit.is_some() {
+                    chars.next();
+                }
+                // Two apostrophes are seconds
+                let unit = match (unit, chars.peek()) {
+                    (Some(Unit::Minutes), Some('\'' | '′' | '’')) => {
+                        chars.next();
+                        Some(Unit::Seconds)
+                    }
+                    (unit, _) => unit,
+                };
+
+                out.push(Token::Number(value, unit));
+            }
+            ',' | ';' | '/' => out.push(Token::Separator),
+            c if c.is_alphabetic() => {
+                let mut word = String::from(c);
+                while let Some(&next) = chars.peek().filter(|c| c.is_alphabetic()) {
+                    word.push(next);
+                    chars.next();
+                }
+
+                match word.to_lowercase().as_str() {
+                    "n" | "north" | "nord" => out.push(Token::Hemisphere('N')),
+                    "s" | "south" | "sør" | "syd" => out.push(Token::Hemisphere('S')),
+                    "e" | "east" | "ø" | "øst" => out.push(Token::Hemisphere('E')),
+                    "w" | "west" | "vest" => out.push(Token::Hemisphere('W')),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(out)
+}
+
+/// Group tokens into coordinates.
+///
+/// Coordinates are split at separators, hemispheres, degrees following other numbers, and
+/// after seconds. If that gives a single group of 2, 4 or 6 numbers, it's split in half.
+fn group(tokens: &[Token]) -> Vec<Group> {
+    // Hemispheres are prefixes if the first coordinate starts with one
+    let prefix = matches!(
+        tokens.iter().find(|token| **token != Token::Separator),
+        Some(Token::Hemisphere(_))
+    );
+
+    let mut out: Vec<Group> = Vec::new();
+    let mut current = Group::default();
+    let mut explicit = false;
+
+    for token in tokens {
+        match token {
+            Token::Separator => {
+                explicit = true;
+                out.push(std::mem::take(&mut current));
+            }
+            Token::Hemisphere(hemisphere) if prefix => {
+                explicit = true;
+                out.push(std::mem::take(&mut current));
+                current.hemisphere = Some(*hemisphere);
+            }
+            Token::Hemisphere(hemisphere) => {
+                explicit = true;
+                current.hemisphere = Some(*hemisphere);
+                out.push(std::mem::take(&mut current));
+            }
+            Token::Number(value, unit) => {
+                if *unit == Some(Unit::Degrees) && !current.numbers.is_empty() {
+                    explicit = true;
+                    out.push(std::mem::take(&mut current));
+                }
+                current.numbers.push(*value);
+                if *unit == Some(Unit::Seconds) && !prefix {
+                    explicit = true;
+                    out.push(std::mem::take(&mut current));
+                }
+            }
+        }
+    }
+    out.push(current);
+
+    // Hemispheres after a coordinate that was split on seconds
+    let mut merged: Vec<Group> = Vec::new();
+    for group in out {
+        match merged.last_mut() {
+            Some(last) if group.numbers.is_empty() && last.hemisphere.is_none() => {
+                last.hemisphere = group.hemisphere;
+            }
+            _ if group.numbers.is_empty() && group.hemisphere.is_none() => {}
+            _ => merged.push(group),
+        }
+    }
+
+    match &merged[..] {
+        [single] if !explicit && [2, 4, 6].contains(&single.numbers.len()) => {
+            let (a, b) = single.numbers.split_at(single.numbers.len() / 2);
+            vec![
+                Group {
+                    numbers: a.to_vec(),
+                    hemisphere: None,
+                },
+                Group {
+                    numbers: b.to_vec(),
+                    hemisphere: None,
+                },
+            ]
+        }
+        _ => merged,
+    }
+}
+
+/// Get the axis of a hemisphere.
+fn axis(hemisphere: char) -> Axis {
+    match hemisphere {
+        'N' | 'S' => Axis::Latitude,
+        _ => Axis::Longitude,
+    }
+}
+
+/// Convert a coordinate to decimal degrees.
+///
+/// # Errors
+///
+/// This function will return an error if the components or hemisphere are invalid.
+fn to_decimal(group: &Group) -> Result<f64, CoordinateError> {
+    let (degrees, rest) = match group.numbers.split_first() {
+        Some(split) if group.numbers.len() <= 3 => split,
+        Some(_) => return Err(CoordinateError::TooManyComponents(group.numbers.len())),
+        None => return Err(CoordinateError::Empty),
+    };
+
+    // Only the last component can have decimals
+    let mut value = degrees.abs();
+    let mut previous = *degrees;
+    for (component, (number, divisor)) in ["minutes", "seconds"]
+        .into_iter()
+        .zip(rest.iter().zip([60.0, 3600.0]))
+    {
+        if !(0.0..60.0).contains(number) || previous.fract() != 0.0 {
+            return Err(CoordinateError::InvalidComponent(component, *number));
+        }
+        value += number / divisor;
+        previous = *number;
+    }
+
+    let negative = degrees.is_sign_negative();
+    match group.hemisphere {
+        Some('S' | 'W') if negative => Err(CoordinateError::Hemisphere(
+            "negative coordinate with S or W".to_owned(),
+        )),
+        Some('N' | 'E') if negative => Err(CoordinateError::Hemisphere(
+            "negative coordinate with N or E".to_owned(),
+        )),
+        Some('S' | 'W') => Ok(-value),
+        _ if negative => Ok(-value),
+        _ => Ok(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// Degrees, minutes and seconds of a coordinate, with seconds rounded down to hundredths.
+    fn dms(value: f64) -> (u32, u32, f64) {
+        let value = value.abs();
+        let degrees = value.floor();
+        let minutes = ((value - degrees) * 60.0).floor();
+        let seconds = ((value - degrees) * 3600.0 - minutes * 60.0).max(0.0);
+
+        (
+            degrees as u32,
+            minutes as u32,
+            (seconds * 100.0).floor() / 100.0,
+        )
+    }
+
+    /// Degrees and minutes of a coordinate, with minutes rounded down to 4 decimals.
+    fn ddm(value: f64) -> (u32, f64) {
+        let value = value.abs();
+        let degrees = value.floor();
+        let minutes = (value - degrees) * 60.0;
+
+        (degrees as u32, (minutes * 10_000.0).floor() / 10_000.0)
+    }
+
+    /// Hemisphere of a coordinate.
+    fn hemisphere(value: f64, [positive, negative]: [char; 2]) -> char {
+        if value < 0.0 {
+            negative
+        } else {
+            positive
+        }
+    }
+
+    /// Assert that coordinates are within `epsilon` degrees.
+    fn assert_near(parsed: Coordinates, latitude: f64, longitude: f64, epsilon: f64) {
+        assert!(
+            (parsed.latitude - latitude).abs() < epsilon
+                && (parsed.longitude - longitude).abs() < epsilon,
+            "{parsed:?} is not at {latitude}, {longitude}"
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn decimal_round_trip(latitude in -90.0..=90.0, longitude in -180.0..=180.0) {
+            let parsed = parse(&format!("{latitude:.6}, {longitude:.6}")).unwrap();
+
+            assert_near(parsed, latitude, longitude, 1e-6);
+        }
+
+        #[test]
+        fn dms_round_trip(latitude in -90.0..=90.0, longitude in -180.0..=180.0, prefix: bool) {
+            let coordinate = |value: f64, hemispheres: [char; 2]| {
+                let (degrees, minutes, seconds) = dms(value);
+                let hemisphere = hemisphere(value, hemispheres);
+                match prefix {
+                    true => format!("{hemisphere} {degrees}° {minutes}' {seconds:.2}''"),
+                    false => format!("{degrees}°{minutes}'{seconds:.2}\"{hemisphere}"),
+                }
+            };
+            let text = format!(
+                "{}  {}",
+                coordinate(latitude, ['N', 'S']),
+                coordinate(longitude, ['E', 'W'])
+            );
+
+            assert_near(parse(&text).unwrap(), latitude, longitude, 1e-5);
+        }
+
+        #[test]
+        fn ddm_round_trip(latitude in -90.0..=90.0, longitude in -180.0..=180.0) {
+            let coordinate = |value: f64, hemispheres: [char; 2]| {
+                let (degrees, minutes) = ddm(value);
+                format!("{degrees}° {minutes:.4}' {}", hemisphere(value, hemispheres))
+            };
+            // Hemispheres take precedence over the order
+            let text = format!(
+                "{}, {}",
+                coordinate(longitude, ['E', 'W']),
+                coordinate(latitude, ['N', 'S'])
+            );
+
+            assert_near(parse(&text).unwrap(), latitude, longitude, 1e-5);
+        }
+
+        #[test]
+        fn hemisphere_sign(latitude in 0.0..=90.0_f64, longitude in 0.0..=180.0_f64) {
+            let south_west = parse(&format!("S {latitude:.6} W {longitude:.6}")).unwrap();
+            assert_near(south_west, -latitude, -longitude, 1e-6);
+
+            let negative = format!("-{latitude:.6} S, {longitude:.6} E");
+            prop_assert!(matches!(parse(&negative), Err(CoordinateError::Hemisphere(_))));
+        }
+
+        #[test]
+        fn out_of_range(latitude in 90.001..1000.0_f64, longitude in 180.001..1000.0_f64) {
+            prop_assert_eq!(
+                parse(&format!("{latitude}, 10")),
+                Err(CoordinateError::OutOfRange(Axis::Latitude, latitude))
+            );
+            prop_assert_eq!(
+                parse(&format!("10, -{longitude}")),
+                Err(CoordinateError::OutOfRange(Axis::Longitude, -longitude))
+            );
+        }
+    }
+
+    /// Coordinate cells and descriptions from flightlog.org, with their position.
+    const FLIGHTLOG: [(&str, f64, f64); 8] = [
+        (
+            "DMS: N 60° 38' 44''  E 6° 24' 28''\nDecimal: 60.64556, 6.40778",
+            60.645_555_6,
+            6.407_777_8,
+        ),
+        (
+            "N 60° 38' 44''  E 6° 24' 28'' (60.64556, 6.40778)",
+            60.645_555_6,
+            6.407_777_8,
+        ),
+        (
+            "Koordinater: N 59° 30' 19''  E 9° 50' 35''",
+            59.505_277_8,
+            9.843_055_6,
+        ),
+        (
+            "N 61° 11' 18\"\nE 8° 13' 36,34\"",
+            61.188_333_3,
+            8.226_761_1,
+        ),
+        ("59 32' 35,65\"/9 45' 29,11\"", 59.543_236_1, 9.758_086_1),
+        ("N59 35 17.6 E9 48 14.9", 59.588_222_2, 9.804_138_9),
+        ("N59.4702632       Ø9.8253198", 59.470_263_2, 9.825_319_8),
+        ("NORD 59.5056833 ØST 9.8437065", 59.505_683_3, 9.843_706_5),
+    ];
+
+    #[test]
+    fn flightlog() {
+        for (text, latitude, longitude) in FLIGHTLOG {
+            let parsed = parse(text).unwrap_or_else(|err| panic!("{text}: {err}"));
+            assert_near(parsed, latitude, longitude, 1e-6);
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        assert_eq!(
+            parse("Koordinater kommer snart."),
+            Err(CoordinateError::Empty)
+        );
+        assert_eq!(parse("60.1"), Err(CoordinateError::Count(1)));
+        assert_eq!(parse("60.1, 6.2, 7.3"), Err(CoordinateError::Count(3)));
+        // Written twice, but far apart
+        assert_eq!(
+            parse("N 60° 38' 44''  E 6° 24' 28''\n61.64556, 6.40778"),
+            Err(CoordinateError::Count(3))
+        );
+        assert_eq!(
+            parse("N 60° 70' E 6°"),
+            Err(CoordinateError::InvalidComponent("minutes", 70.0))
+        );
+        assert!(matches!(
+            parse("N 60° N 6°"),
+            Err(CoordinateError::Hemisphere(_))
+        ));
+    }
+}
@@ -0,0 +1,378 @@
+//! Extract takeoffs from flightlog.org HTML pages.
+
+use crate::coordinates::{self, Coordinates};
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use regex::Regex;
+use scraper::{ElementRef, Html, Node, Selector};
+use server_lib::models::NewTakeoff;
+
+/// A takeoff extracted from a takeoff page.
+#[derive(Debug)]
+pub struct ExtractedTakeoff {
+    /// The takeoff, without an image.
+    pub takeoff: NewTakeoff,
+    /// Optional absolute URL of the full size image.
+    pub image_url: Option<String>,
+}
+
+/// Extract a takeoff from a takeoff page.
+///
+/// # Arguments
+///
+/// * `html` - The page source.
+/// * `url` - The page URL, used as source URL and for resolving relative links.
+///
+/// # Errors
+///
+/// This function will return an error if a required field is missing or can't be parsed.
+///
+/// # Returns
+///
+/// An [`ExtractedTakeoff`].
+#[rustfmt::skip]
+pub fn extract_takeoff(html: &str, url: &str) -> Result<ExtractedTakeoff, anyhow::Error> {
+    let document = Html::parse_document(html);
+    let base_url = reqwest::Url::parse(url)?;
+
+    let name = inner_text(select_first(&document, "body > div > table:nth-child(2) > tbody > tr:nth-child(2) > td > table > tbody > tr:nth-child(3) > td > span")?);
+    let description = value_of(&document, "Description")?;
+    let image_url = description.select(&selector("a > img")?).next().and_then(|e| parent_element(e)?.value().attr("href")).map(|href| base_url.join(href)).transpose()?.map(String::from);
+    let region = inner_text(value_of(&document, "region")?);
+    let (altitude_m, height_diff_m) = parse_altitudes(&inner_text(value_of(&document, "Altitude")?))?;
+    let Coordinates { latitude, longitude } = coordinates::parse(&inner_text(value_of(&document, "Coordinates")?))?;
+    let wind_dirs = description.select(&selector("img")?).next().and_then(|e| e.value().attr("alt")).unwrap_or_default().split(' ').filter(|e| !e.is_empty()).map(|e| e.to_owned()).collect();
+    let info_url = value_of(&document, "Link to more info").ok().and_then(|e| e.select(&selector("a").ok()?).next()).and_then(|e| e.value().attr("href")).map(|href| href.to_owned());
+    let (created_at, created_by) = parse_dated(&inner_text(value_of(&document, "created")?))?;
+    let (updated_at, updated_by) = parse_dated(&inner_text(value_of(&document, "Updated")?))?;
+    let description = inner_text(description);
+    let source_url = Some(url.to_owned());
+
+    let takeoff = NewTakeoff {
+        name,
+        description,
+        image: None,
+        region,
+        altitude_m,
+        height_diff_m,
+        latitude,
+        longitude,
+        wind_dirs,
+        info_url,
+        source: None,
+        source_url,
+        created_at,
+        created_by,
+        updated_at,
+        updated_by,
+    };
+
+    Ok(ExtractedTakeoff { takeoff, image_url })
+}
+
+/// Parse a CSS selector.
+///
+/// # Errors
+///
+/// This function will return an error if the selector is invalid.
+fn selector(selectors: &str) -> Result<Selector, anyhow::Error> {
+    Selector::parse(selectors).map_err(|err| anyhow!("invalid selector {selectors}: {err}"))
+}
+
+/// Select the first element matching a CSS selector.
+///
+/// # Errors
+///
+/// This function will return an error if the selector is invalid or nothing matches.
+fn select_first<'a>(document: &'a Html, selectors: &str) -> Result<ElementRef<'a>, anyhow::Error> {
+    document
+        .select(&selector(selectors)?)
+        .next()
+        .ok_or(anyhow!("no element matching {selectors}"))
+}
+
+/// Find the value cell of a labelled table row.
+///
+/// Same as the XPath `//td[contains(.,'label')]/following-sibling::td`, i.e. the first cell
+/// in document order that follows a sibling cell containing `label`.
+///
+/// # Errors
+///
+/// This function will return an error if there is no such cell.
+fn value_of<'a>(document: &'a Html, label: &str) -> Result<ElementRef<'a>, anyhow::Error> {
+    let cells = selector("td")?;
+
+    document
+        .select(&cells)
+        .find(|cell| {
+            cell.prev_siblings()
+                .filter_map(ElementRef::wrap)
+                .any(|sibling| {
+                    sibling.value().name() == "td"
+                        && sibling.text().any(|text| text.contains(label))
+                })
+        })
+        .ok_or(anyhow!("missing value for {label}"))
+}
+
+/// Get the parent element of an element.
+fn parent_element(element: ElementRef) -> Option<ElementRef> {
+    element.parent().and_then(ElementRef::wrap)
+}
+
+/// Get the text of an element, similar to how a browser renders it.
+///
+/// `br` elements are line breaks, whitespace is collapsed and the result is trimmed.
+fn inner_text(element: ElementRef) -> String {
+    let mut out = String::new();
+
+    for node in element.descendants() {
+        match node.value() {
+            Node::Element(element) if element.name() == "br" => out.push('\n'),
+            Node::Text(text) => {
+                for c in text.chars() {
+                    if !c.is_whitespace() {
+                        out.push(c);
+                    } else if !out.ends_with([' ', '\n']) {
+                        out.push(' ');
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out.lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_owned()
+}
+
+/// Parse altitudes.
+///
+/// The altitude is the number before `asl` or `moh`, and the height difference the number after
+/// `top to bottom` or `height difference`. Without these labels, the first number is the altitude
+/// and the second the height difference.
+///
+/// # Arguments
+///
+/// * `text` - Altitude text (e.g. `790 meters asl Top to bottom 740 meters`).
+///
+/// # Errors
+///
+/// This function will return an error if a number is too large.
+///
+/// # Returns
+///
+/// A tuple of the altitude and height difference in meters.
+fn parse_altitudes(text: &str) -> Result<(Option<i32>, Option<i32>), anyhow::Error> {
+    let altitude_re =
+        Regex::new(r"(?i)(\d+)\s*(?:m|meters?|metres?)?\.?\s*(?:asl|a\.s\.l\.|moh|m\.o\.h\.)")?;
+    let height_diff_re =
+        Regex::new(r"(?i)(?:top to bottom|height difference|høydeforskjell)\D*(\d+)")?;
+    let number = |re: &Regex| -> Result<Option<i32>, anyhow::Error> {
+        Ok(re
+            .captures(text)
+            .map(|caps| caps[1].parse::<i32>())
+            .transpose()?)
+    };
+
+    let (altitude, height_diff) = (number(&altitude_re)?, number(&height_diff_re)?);
+    if altitude.is_some() || height_diff.is_some() {
+        return Ok((altitude, height_diff));
+    }
+
+    let number_re = Regex::new(r"\d+")?;
+    let mut numbers = number_re
+        .find_iter(text)
+        .map(|number| number.as_str().parse::<i32>());
+    let altitude = numbers.next().transpose()?;
+    let height_diff = numbers.next().transpose()?;
+
+    Ok((altitude, height_diff))
+}
+
+/// Parse a date followed by a name.
+///
+/// Dates are `YYYY-MM-DD` or `DD.MM.YYYY`, optionally with a time, and are in UTC.
+/// A `by` before the name is left out.
+///
+/// # Arguments
+///
+/// * `text` - Date and name (e.g. `2008-05-11 Ola Nordmann`).
+///
+/// # Errors
+///
+/// This function will return an error if Regex fails.
+///
+/// # Returns
+///
+/// A tuple of the date and name, either of which can be missing.
+fn parse_dated(text: &str) -> Result<(Option<DateTime<Utc>>, Option<String>), anyhow::Error> {
+    let text = text.trim();
+    let date_re = Regex::new(
+        r"^(\d{4}-\d{1,2}-\d{1,2}|\d{1,2}\.\d{1,2}\.\d{4})(?:[ T](\d{1,2}:\d{2}(?::\d{2})?))?",
+    )?;
+
+    let (date, rest) = match date_re.captures(text) {
+        Some(caps) => {
+            let date = NaiveDate::parse_from_str(&caps[1], "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(&caps[1], "%d.%m.%Y"))
+                .ok();
+            let time = caps.get(2).map_or(Some(NaiveTime::MIN), |time| {
+                NaiveTime::parse_from_str(time.as_str(), "%H:%M:%S")
+                    .or_else(|_| NaiveTime::parse_from_str(time.as_str(), "%H:%M"))
+                    .ok()
+            });
+            let date = date
+                .zip(time)
+                .map(|(date, time)| date.and_time(time).and_utc());
+
+            (date, &text[caps[0].len()..])
+        }
+        None => (None, text),
+    };
+
+    let rest = rest.trim();
+    let name = match rest.get(..3) {
+        Some(by) if by.eq_ignore_ascii_case("by ") => rest[3..].trim(),
+        _ => rest,
+    };
+
+    Ok((date, Some(name.to_owned()).filter(|name| !name.is_empty())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// URL of a takeoff page.
+    const URL: &str = "https://flightlog.org/fl.html?l=1&a=22&country_id=160&start_id=7";
+
+    /// Read a saved page from `tests/fixtures/flightlog`.
+    fn fixture(name: &str) -> String {
+        let path = format!(
+            "{}/tests/fixtures/flightlog/{name}",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn takeoff() {
+        let extracted = extract_takeoff(&fixture("takeoff.html"), URL).unwrap();
+        let takeoff = extracted.takeoff;
+
+        assert_eq!(takeoff.name, "Hanguren");
+        assert_eq!(
+            takeoff.description,
+            "Starten ligger ved toppstasjonen til Hangursbanen. Fin start i østlig vind & termikk.\n\nLanding ved Prestegardsmoen."
+        );
+        assert_eq!(
+            extracted.image_url.as_deref(),
+            Some("https://flightlog.org/fl/images/start_7.jpg")
+        );
+        assert_eq!(takeoff.region, "Hordaland");
+        assert_eq!(
+            (takeoff.altitude_m, takeoff.height_diff_m),
+            (Some(660), Some(600))
+        );
+        assert!((takeoff.latitude - (60.0 + 38.0 / 60.0 + 44.0 / 3600.0)).abs() < 1e-9);
+        assert!((takeoff.longitude - (6.0 + 24.0 / 60.0 + 28.0 / 3600.0)).abs() < 1e-9);
+        assert_eq!(takeoff.wind_dirs, ["NE", "E", "SE"]);
+        assert_eq!(takeoff.info_url.as_deref(), Some("https://www.vossxc.no/"));
+        assert_eq!(takeoff.source_url.as_deref(), Some(URL));
+        assert_eq!(
+            takeoff.created_at,
+            Some(Utc.with_ymd_and_hms(2008, 5, 11, 0, 0, 0).unwrap())
+        );
+        assert_eq!(takeoff.created_by.as_deref(), Some("Ola Nordmann"));
+        assert_eq!(
+            takeoff.updated_at,
+            Some(Utc.with_ymd_and_hms(2019, 6, 12, 14, 5, 0).unwrap())
+        );
+        assert_eq!(takeoff.updated_by.as_deref(), Some("Kari Nordmann"));
+    }
+
+    #[test]
+    fn missing_optional_fields() {
+        let extracted = extract_takeoff(&fixture("takeoff_sparse.html"), URL).unwrap();
+        let takeoff = extracted.takeoff;
+
+        assert_eq!(takeoff.name, "Ålesund Aksla");
+        assert_eq!(takeoff.description, "");
+        assert_eq!(extracted.image_url, None);
+        assert_eq!(takeoff.region, "");
+        assert_eq!((takeoff.altitude_m, takeoff.height_diff_m), (None, None));
+        assert_eq!((takeoff.latitude, takeoff.longitude), (62.4722, 6.1549));
+        assert!(takeoff.wind_dirs.is_empty());
+        assert_eq!(takeoff.info_url, None);
+        assert_eq!((takeoff.created_at, takeoff.created_by), (None, None));
+        assert_eq!(takeoff.updated_at, None);
+        assert_eq!(takeoff.updated_by.as_deref(), Some("Kari"));
+    }
+
+    #[test]
+    fn missing_required_fields() {
+        let err = extract_takeoff(&fixture("takeoff_no_coordinates.html"), URL).unwrap_err();
+        assert_eq!(err.to_string(), "missing value for Coordinates");
+
+        let err = extract_takeoff("<html><body></body></html>", URL).unwrap_err();
+        assert!(err.to_string().starts_with("no element matching"), "{err}");
+    }
+
+    #[test]
+    fn altitudes() {
+        let cases = [
+            (
+                "790 meters asl Top to bottom 740 meters",
+                (Some(790), Some(740)),
+            ),
+            ("1200 moh, høydeforskjell 900 m", (Some(1200), Some(900))),
+            ("Height difference: 300", (None, Some(300))),
+            ("450 / 400", (Some(450), Some(400))),
+            ("ukjent", (None, None)),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(parse_altitudes(text).unwrap(), expected, "{text}");
+        }
+        assert!(parse_altitudes("99999999999 meters asl").is_err());
+    }
+
+    #[test]
+    fn dated() {
+        let date = |y, m, d, h, min| Some(Utc.with_ymd_and_hms(y, m, d, h, min, 0).unwrap());
+        let cases = [
+            (
+                "2008-05-11 Ola Nordmann",
+                (date(2008, 5, 11, 0, 0), Some("Ola Nordmann")),
+            ),
+            (
+                "12.06.2019 14:05 by Kari",
+                (date(2019, 6, 12, 14, 5), Some("Kari")),
+            ),
+            (
+                "2019-06-12T14:05:30",
+                (
+                    Some(Utc.with_ymd_and_hms(2019, 6, 12, 14, 5, 30).unwrap()),
+                    None,
+                ),
+            ),
+            ("By Kari", (None, Some("Kari"))),
+            ("", (None, None)),
+        ];
+
+        for (text, (date, name)) in cases {
+            let (parsed_date, parsed_name) = parse_dated(text).unwrap();
+            assert_eq!(
+                (parsed_date, parsed_name.as_deref()),
+                (date, name),
+                "{text}"
+            );
+        }
+    }
+}
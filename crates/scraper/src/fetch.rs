@@ -0,0 +1,123 @@
+//! Fetch web pages and images.
+
+use std::time::Duration;
+
+/// Seconds before a request times out.
+const REQUEST_TIMEOUT: u64 = 30;
+
+/// Check if an error is transient, so the request can be retried.
+///
+/// Timeouts, connection errors, `429 Too Many Requests` and server errors are transient.
+///
+/// # Arguments
+///
+/// * `err` - An error from fetching.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .any(|err| {
+            err.is_timeout()
+                || err.is_connect()
+                || err.status().is_some_and(|status| {
+                    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+                })
+        })
+}
+
+/// Backend used for fetching pages.
+pub enum Backend {
+    /// Plain HTTP requests.
+    Http(reqwest::Client),
+    /// A Chrome driver.
+    #[cfg(feature = "selenium")]
+    Selenium(thirtyfour::WebDriver),
+}
+
+impl Backend {
+    /// Create a plain HTTP backend.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the HTTP client can't be built.
+    ///
+    /// # Returns
+    ///
+    /// A [`Backend::Http`].
+    pub fn http() -> Result<Self, anyhow::Error> {
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("parastart-scraper/", env!("CARGO_PKG_VERSION")))
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT))
+            .build()?;
+
+        Ok(Self::Http(client))
+    }
+
+    /// Create a Selenium backend.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if initializing the chrome driver fails.
+    ///
+    /// # Returns
+    ///
+    /// A [`Backend::Selenium`].
+    #[cfg(feature = "selenium")]
+    pub async fn selenium() -> Result<Self, anyhow::Error> {
+        Ok(Self::Selenium(crate::selenium::init_driver().await?))
+    }
+
+    /// Fetch the source of a page.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - A URL to fetch.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails.
+    ///
+    /// # Returns
+    ///
+    /// The page source.
+    pub async fn fetch_page(&self, url: &str) -> Result<String, anyhow::Error> {
+        match self {
+            Self::Http(client) => Ok(client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?),
+            #[cfg(feature = "selenium")]
+            Self::Selenium(driver) => crate::selenium::fetch_page(driver, url).await,
+        }
+    }
+
+    /// Fetch an image.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - A URL to fetch.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails.
+    ///
+    /// # Returns
+    ///
+    /// The image in bytes.
+    pub async fn fetch_image(&self, url: &str) -> Result<Vec<u8>, anyhow::Error> {
+        match self {
+            Self::Http(client) => Ok(client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?
+                .to_vec()),
+            #[cfg(feature = "selenium")]
+            Self::Selenium(driver) => crate::selenium::fetch_image(driver, url).await,
+        }
+    }
+}
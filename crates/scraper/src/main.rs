@@ -1,39 +1,517 @@
+mod cli;
+mod coordinates;
+mod extract_html;
+mod fetch;
+mod output;
 mod parse_kml;
+mod rate_limit;
+mod refresh;
 mod scrape_web;
+#[cfg(feature = "selenium")]
+mod selenium;
+mod sources;
 
 use anyhow::anyhow;
-use server_lib::connection;
+use clap::Parser;
+use cli::{
+    Cli, Command, DuplicatesCommand, FileFormat, InputArgs, OutputArgs, OutputTarget, QueueArgs,
+    SourceArgs,
+};
+use output::Output;
+use rate_limit::RateLimiter;
+use scrape_web::Queue;
+use server_lib::models::{ChangeNote, GetTakeoff, NewTakeoff, ScrapeJobStatus};
+use server_lib::validation::{self, duplicates};
+use server_lib::{connection, geojson, helpers};
+use sources::TakeoffSource;
+use sqlx::PgPool;
+use std::fs::{self, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::info;
+use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
-    // Set up log files
-    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-    let log_file_name = format!("scraper_{timestamp}.log");
-    let file_appender = tracing_appender::rolling::never("logs", log_file_name);
-    let (appender, _guard) = tracing_appender::non_blocking(file_appender);
-
-    // Set up logging
-    const DEFAULT_FILTER: &str = "scraper=debug";
+    let cli = Cli::parse();
+    let _guard = init_logging(&cli)?;
+
+    match cli.command {
+        Command::Discover { sources, missing } => discover(&sources, missing).await?,
+        #[rustfmt::skip]
+        Command::Scrape { sources, input, queue, output } => scrape(&sources, &input, &queue, &output, cli.dry_run).await?,
+        Command::Refresh { sources, queue } => refresh(&sources, &queue, cli.dry_run).await?,
+        Command::Import { path, format } => import(&path, format, cli.dry_run).await?,
+        #[rustfmt::skip]
+        Command::Validate { path, format, distance, flag, out } => validate(path.as_deref(), format, distance, flag, out.as_deref(), cli.dry_run).await?,
+        Command::Duplicates { command } => duplicates(command, cli.dry_run).await?,
+    }
+    info!("Exiting.");
+
+    Ok(())
+}
+
+/// Set up logging to stderr and optionally a log file.
+///
+/// `RUST_LOG` takes precedence over the verbosity flags.
+///
+/// # Errors
+///
+/// This function will return an error if the system time is before the Unix epoch.
+///
+/// # Returns
+///
+/// A guard that flushes the log file when dropped.
+fn init_logging(cli: &Cli) -> Result<Option<WorkerGuard>, anyhow::Error> {
+    let default_filter = match (cli.quiet, cli.verbose) {
+        (true, _) => "scraper=warn",
+        (false, 0) => "scraper=info",
+        (false, 1) => "scraper=debug",
+        (false, _) => "scraper=trace",
+    };
+
+    // Set up log file
+    let (file_layer, guard) = if cli.no_log_file {
+        (None, None)
+    } else {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let log_file_name = format!("scraper_{timestamp}.log");
+        let file_appender = tracing_appender::rolling::never(&cli.log_dir, log_file_name);
+        let (appender, guard) = tracing_appender::non_blocking(file_appender);
+
+        (
+            Some(fmt::layer().with_ansi(false).with_writer(appender)),
+            Some(guard),
+        )
+    };
+
+    // Log to stderr, so stdout can be used for output
     tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| DEFAULT_FILTER.into()))
-        .with(tracing_subscriber::fmt::layer())
-        .with(fmt::layer().pretty().with_writer(std::io::stdout))
-        .with(fmt::layer().with_ansi(false).with_writer(appender))
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| default_filter.into()))
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
         .init();
 
-    // Connect to database
-    let mut connection = connection::single().await.map_err(|e| anyhow!(e))?;
+    Ok(guard)
+}
 
-    // Gather URLs
-    let path = "crates/scraper/resources/country_160.kml";
-    let urls = parse_kml::get_missing_urls(path, &mut connection).await?;
+/// Connect to the database.
+///
+/// # Errors
+///
+/// This function will return an error if connecting or migrating fails.
+async fn connect() -> Result<PgPool, anyhow::Error> {
+    connection::pool().await.map_err(|e| anyhow!(e))
+}
 
-    // Scrape URLs and insert into database
-    scrape_web::try_scrape_all(urls, &mut connection).await?;
-    info!("Exiting.");
+/// Create the selected sources.
+///
+/// # Errors
+///
+/// This function will return an error if a source is unknown or can't be created.
+async fn create_sources(args: &SourceArgs) -> Result<Vec<Box<dyn TakeoffSource>>, anyhow::Error> {
+    let config = sources::Config {
+        kml_path: args.kml.clone(),
+        selenium: args.selenium,
+        limiter: Arc::new(RateLimiter::new(args.rate, args.burst)),
+    };
+    let names = if args.sources.is_empty() {
+        sources::NAMES.iter().map(|name| name.to_string()).collect()
+    } else {
+        args.sources.clone()
+    };
+
+    let mut out = Vec::new();
+    for name in names {
+        out.push(sources::create(&name, &config).await?);
+    }
+
+    Ok(out)
+}
+
+/// Get queue options from arguments.
+///
+/// A Chrome driver can only fetch one page at a time, so concurrency is 1 with `--selenium`.
+fn queue(sources: &SourceArgs, args: &QueueArgs) -> Queue {
+    Queue {
+        concurrency: if sources.selenium {
+            1
+        } else {
+            args.concurrency
+        },
+        retries: args.retries,
+    }
+}
+
+/// Print the URLs of discovered takeoffs, one per line.
+///
+/// # Errors
+///
+/// This function will return an error if discovery fails.
+async fn discover(args: &SourceArgs, missing: bool) -> Result<(), anyhow::Error> {
+    let pool = if missing {
+        Some(connect().await?)
+    } else {
+        None
+    };
+    let mut stdout = std::io::stdout().lock();
+
+    for source in create_sources(args).await? {
+        let ids = match &pool {
+            Some(pool) => sources::discover_missing(source.as_ref(), pool).await?,
+            None => source.discover().await?,
+        };
+
+        for id in ids {
+            writeln!(stdout, "{}", source.source_url(&id))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Scrape takeoffs and write them to the selected output.
+///
+/// Unless it's a dry run, the takeoffs are saved as scrape jobs, and the status of each is
+/// updated as it's scraped. `--resume` and `--retry-failed` then select takeoffs from the jobs.
+///
+/// # Errors
+///
+/// This function will return an error if creating sources, discovery or opening the output fails.
+/// Scraping errors are logged.
+async fn scrape(
+    sources: &SourceArgs,
+    input: &InputArgs,
+    queue_args: &QueueArgs,
+    output: &OutputArgs,
+    dry_run: bool,
+) -> Result<(), anyhow::Error> {
+    // Ids are only unique within a source
+    if input.id.is_some() && sources.sources.len() != 1 {
+        return Err(anyhow!("--id needs exactly one --source"));
+    }
+
+    let from_jobs = input.resume || input.retry_failed;
+    let discover_missing = input.urls.is_none() && input.id.is_none() && !input.all && !from_jobs;
+    let needs_db = !dry_run || discover_missing || from_jobs;
+    let pool = if needs_db {
+        Some(connect().await?)
+    } else {
+        None
+    };
+    let checkpoint = pool.as_ref().filter(|_| !dry_run);
+
+    // Gather takeoffs
+    let urls = input.urls.as_ref().map(fs::read_to_string).transpose()?;
+    let mut jobs = Vec::new();
+    for source in create_sources(sources).await? {
+        let ids = if let Some(urls) = &urls {
+            urls.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .filter_map(|url| source.id_from_url(url))
+                .collect()
+        } else if let Some(id) = &input.id {
+            vec![id.clone()]
+        } else if let (true, Some(pool)) = (from_jobs, &pool) {
+            let status = match input.resume {
+                true => ScrapeJobStatus::Pending,
+                false => ScrapeJobStatus::Failed,
+            };
+            helpers::get_scrape_jobs(pool, source.name(), status).await?
+        } else if let (true, Some(pool)) = (discover_missing, &pool) {
+            sources::discover_missing(source.as_ref(), pool).await?
+        } else {
+            source.discover().await?
+        };
+
+        if let (Some(pool), false) = (checkpoint, from_jobs) {
+            helpers::enqueue_scrape_jobs(pool, source.name(), &ids).await?;
+        }
+
+        info!("Scraping {} takeoffs from {}.", ids.len(), source.name());
+        jobs.push((source, ids));
+    }
+
+    // Open output, appending to files when resuming
+    let writer = || -> Result<Box<dyn Write + Send>, anyhow::Error> {
+        Ok(match &output.out {
+            Some(path) => Box::new(BufWriter::new(
+                OpenOptions::new()
+                    .create(true)
+                    .write(true)
+                    .append(from_jobs)
+                    .truncate(!from_jobs)
+                    .open(path)?,
+            )),
+            None => Box::new(std::io::stdout()),
+        })
+    };
+    let mut out = match (dry_run, output.output) {
+        (true, _) => Output::DryRun,
+        (false, OutputTarget::Db) => Output::Database(pool.clone().ok_or(anyhow!("no database"))?),
+        (false, OutputTarget::Ndjson) => Output::Ndjson(writer()?),
+        (false, OutputTarget::Geojson) => Output::GeoJson(writer()?, Vec::new()),
+    };
+
+    // Scrape takeoffs and write to output
+    let queue = queue(sources, queue_args);
+    for (source, ids) in jobs {
+        scrape_web::try_scrape_all(source.as_ref(), ids, queue, &mut out, checkpoint).await;
+    }
+
+    out.finish()
+}
+
+/// Refresh takeoffs and print a JSON report for each source.
+///
+/// # Errors
+///
+/// This function will return an error if creating sources or refreshing fails.
+async fn refresh(
+    sources: &SourceArgs,
+    queue_args: &QueueArgs,
+    dry_run: bool,
+) -> Result<(), anyhow::Error> {
+    let pool = connect().await?;
+    let queue = queue(sources, queue_args);
+
+    for source in create_sources(sources).await? {
+        let report = refresh::refresh(source.as_ref(), &pool, queue, dry_run).await?;
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    Ok(())
+}
+
+/// Import takeoffs from a file into the database.
+///
+/// Takeoffs are inserted in a single transaction, so nothing is imported if any of them fails.
+/// A dry run only reads the file, without connecting to the database.
+///
+/// # Errors
+///
+/// This function will return an error if reading, parsing or inserting fails.
+async fn import(
+    path: &Path,
+    format: Option<FileFormat>,
+    dry_run: bool,
+) -> Result<(), anyhow::Error> {
+    let pool = match dry_run {
+        true => None,
+        false => Some(connect().await?),
+    };
+    let takeoffs = read_takeoffs(path, format, pool.as_ref()).await?;
+
+    let Some(pool) = pool else {
+        info!("Dry run, not importing {} takeoffs.", takeoffs.len());
+        return Ok(());
+    };
+
+    let note = ChangeNote::comment(format!("Imported from {}", path.display()));
+    let mut tx = pool.begin().await?;
+    for takeoff in &takeoffs {
+        helpers::upsert_takeoff(&mut *tx, takeoff, &note).await?;
+    }
+    tx.commit().await?;
+    info!("Imported {} takeoffs.", takeoffs.len());
 
     Ok(())
 }
+
+/// Validate takeoffs from the database or a file, and write a JSON report.
+///
+/// With `flag`, the issues replace earlier issues in the database.
+///
+/// # Errors
+///
+/// This function will return an error if reading takeoffs, writing the report or saving issues fails.
+async fn validate(
+    path: Option<&Path>,
+    format: Option<FileFormat>,
+    distance: f64,
+    flag: bool,
+    out: Option<&Path>,
+    dry_run: bool,
+) -> Result<(), anyhow::Error> {
+    let pool = match path {
+        Some(_) => None,
+        None => Some(connect().await?),
+    };
+    let takeoffs: Vec<GetTakeoff> = match (path, &pool) {
+        (Some(path), _) => read_takeoffs(path, format, None)
+            .await?
+            .into_iter()
+            .map(GetTakeoff::from)
+            .collect(),
+        (None, Some(pool)) => helpers::get_takeoffs_without_images(pool).await?,
+        (None, None) => return Err(anyhow!("no database")),
+    };
+
+    let config = validation::Config {
+        duplicate_distance: distance,
+    };
+    let report = validation::validate(&takeoffs, &config);
+    info!(
+        "Validated {} takeoffs, found {} errors and {} warnings.",
+        report.checked, report.errors, report.warnings
+    );
+
+    let mut writer: Box<dyn Write> = match out {
+        Some(path) => Box::new(BufWriter::new(fs::File::create(path)?)),
+        None => Box::new(std::io::stdout()),
+    };
+    serde_json::to_writer_pretty(&mut writer, &report)?;
+    writeln!(writer)?;
+    writer.flush()?;
+
+    if let (true, Some(pool)) = (flag, &pool) {
+        if dry_run {
+            info!("Dry run, not flagging {} issues.", report.issues.len());
+            return Ok(());
+        }
+
+        let mut tx = pool.begin().await?;
+        helpers::clear_quality_issues(&mut *tx).await?;
+        helpers::insert_quality_issues(&mut *tx, &report.issues).await?;
+        tx.commit().await?;
+        info!("Flagged {} issues.", report.issues.len());
+    }
+
+    Ok(())
+}
+
+/// Run a duplicates command.
+///
+/// # Errors
+///
+/// This function will return an error if a takeoff doesn't exist, or a query fails.
+async fn duplicates(command: DuplicatesCommand, dry_run: bool) -> Result<(), anyhow::Error> {
+    let pool = connect().await?;
+
+    match command {
+        DuplicatesCommand::List {
+            max_distance,
+            min_similarity,
+        } => {
+            let config = duplicates::Config {
+                max_distance,
+                min_similarity,
+            };
+            let takeoffs = helpers::get_takeoffs_without_images(&pool).await?;
+            let dismissed = helpers::get_dismissed_duplicates(&pool).await?;
+            let candidates = duplicates::find_candidates(&takeoffs, &config, &dismissed);
+
+            info!("Found {} candidate duplicates.", candidates.len());
+            println!("{}", serde_json::to_string_pretty(&candidates)?);
+        }
+        DuplicatesCommand::Merge { keep, remove } => {
+            if keep == remove {
+                return Err(anyhow!("can't merge a takeoff with itself"));
+            }
+
+            let mut tx = pool.begin().await?;
+            let keep = helpers::get_takeoff(&mut *tx, keep)
+                .await?
+                .ok_or(anyhow!("no takeoff {keep}"))?;
+            let remove = helpers::get_takeoff(&mut *tx, remove)
+                .await?
+                .ok_or(anyhow!("no takeoff {remove}"))?;
+            let (keep_id, remove_id) = (keep.id, remove.id);
+
+            if dry_run {
+                let merged = duplicates::merge(keep, remove);
+                info!(
+                    "Dry run, not merging {remove_id} into {keep_id} as {} with merged source URLs {:?}.",
+                    merged.name, merged.merged_source_urls
+                );
+                return Ok(());
+            }
+
+            let note = ChangeNote::comment(format!("Merged {remove_id} into {keep_id}"));
+            helpers::merge_takeoffs(&mut tx, keep, remove, &note).await?;
+            tx.commit().await?;
+            info!("Merged {remove_id} into {keep_id}.");
+        }
+        DuplicatesCommand::Dismiss { a, b } => {
+            if a == b {
+                return Err(anyhow!("can't dismiss a takeoff with itself"));
+            }
+            if dry_run {
+                info!("Dry run, not dismissing {a} and {b}.");
+                return Ok(());
+            }
+
+            helpers::dismiss_duplicate(&pool, a, b).await?;
+            info!("Dismissed {a} and {b}.");
+        }
+    }
+
+    Ok(())
+}
+
+/// Read takeoffs from a file.
+///
+/// # Arguments
+///
+/// * `path` - A filepath.
+/// * `format` - File format, guessed from the extension if `None`.
+/// * `pool` - Optional database, to only read KML placemarks that are missing from it.
+///
+/// # Errors
+///
+/// This function will return an error if the format is unknown, or reading or parsing fails.
+async fn read_takeoffs(
+    path: &Path,
+    format: Option<FileFormat>,
+    pool: Option<&PgPool>,
+) -> Result<Vec<NewTakeoff>, anyhow::Error> {
+    let format = format
+        .or_else(|| FileFormat::from_path(path))
+        .ok_or(anyhow!(
+            "unknown format of {}, use --format",
+            path.display()
+        ))?;
+
+    let out = match format {
+        FileFormat::Ndjson => fs::read_to_string(path)?
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| {
+                serde_json::from_str::<NewTakeoff>(line)
+                    .map_err(|err| anyhow!("line {}: {err}", i + 1))
+            })
+            .collect::<Result<Vec<NewTakeoff>, anyhow::Error>>()?,
+        FileFormat::Geojson => {
+            let collection = serde_json::from_str(&fs::read_to_string(path)?)?;
+            // Takeoffs are upserted by source URL, so others would be duplicated
+            geojson::to_new_takeoffs(collection)
+                .map_err(|e| anyhow!(e))?
+                .into_iter()
+                .map(|(id, takeoff)| match (id, &takeoff.source_url) {
+                    (Some(id), None) => Err(anyhow!(
+                        "takeoff {id} has no source URL, import it through the API"
+                    )),
+                    _ => Ok(takeoff),
+                })
+                .collect::<Result<Vec<NewTakeoff>, anyhow::Error>>()?
+        }
+        FileFormat::Kml => {
+            let path = path.to_str().ok_or(anyhow!("invalid path"))?;
+            let placemarks = match pool {
+                Some(pool) => parse_kml::get_missing_placemarks(path, pool).await?,
+                None => parse_kml::get_placemarks(path)?,
+            };
+            placemarks
+                .iter()
+                .map(parse_kml::Placemark::to_new_takeoff)
+                .collect()
+        }
+    };
+
+    Ok(out)
+}
@@ -0,0 +1,70 @@
+//! Write scraped takeoffs to the database or a file.
+
+use server_lib::models::{ChangeNote, GetTakeoff, NewTakeoff};
+use server_lib::{geojson, helpers};
+use sqlx::PgPool;
+use std::io::Write;
+use tracing::info;
+
+/// Where to write takeoffs.
+pub enum Output {
+    /// Insert into the database.
+    Database(PgPool),
+    /// Write one JSON takeoff per line.
+    Ndjson(Box<dyn Write + Send>),
+    /// Write a GeoJSON feature collection when finished.
+    GeoJson(Box<dyn Write + Send>, Vec<GetTakeoff>),
+    /// Only log takeoffs.
+    DryRun,
+}
+
+impl Output {
+    /// Write a takeoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `takeoff` - A takeoff.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if inserting or writing fails.
+    pub async fn write(&mut self, takeoff: NewTakeoff) -> Result<(), anyhow::Error> {
+        match self {
+            Output::Database(pool) => {
+                let note = ChangeNote::comment("Scraped");
+                helpers::upsert_takeoff(&*pool, &takeoff, &note).await?
+            }
+            Output::Ndjson(writer) => {
+                serde_json::to_writer(&mut *writer, &takeoff)?;
+                writeln!(writer)?;
+            }
+            Output::GeoJson(_, takeoffs) => takeoffs.push(GetTakeoff::from(takeoff)),
+            Output::DryRun => info!(
+                "Would write {} ({}).",
+                takeoff.name,
+                takeoff.source_url.as_deref().unwrap_or_default()
+            ),
+        }
+
+        Ok(())
+    }
+
+    /// Finish writing, e.g. write the GeoJSON feature collection.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if writing fails.
+    pub fn finish(self) -> Result<(), anyhow::Error> {
+        match self {
+            Output::Ndjson(mut writer) => writer.flush()?,
+            Output::GeoJson(mut writer, takeoffs) => {
+                serde_json::to_writer(&mut writer, &geojson::to_feature_collection(takeoffs))?;
+                writeln!(writer)?;
+                writer.flush()?;
+            }
+            Output::Database(_) | Output::DryRun => {}
+        }
+
+        Ok(())
+    }
+}
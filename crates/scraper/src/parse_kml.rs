@@ -2,14 +2,61 @@
 
 //! Parse KML files from flightlog.org.
 
+use crate::sources::flightlog;
 use anyhow::anyhow;
-use regex::Regex;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use scraper::{Html, Node, Selector};
 use server_lib::helpers;
-use sqlx::PgConnection;
+use server_lib::models::NewTakeoff;
+use sqlx::PgPool;
 use std::fs;
 use tracing::info;
 
-/// Get Flightlog URLs from KML file.
+/// A placemark from a KML file.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Placemark {
+    /// Name.
+    pub name: String,
+    /// Latitude coordinate.
+    pub latitude: f64,
+    /// Longitude coordinate.
+    pub longitude: f64,
+    /// Optional meters over sea level.
+    pub altitude: Option<f64>,
+    /// Description as HTML.
+    pub description: String,
+    /// Optional link to the takeoff on flightlog.org.
+    pub link: Option<String>,
+}
+
+impl Placemark {
+    /// Convert to a [`NewTakeoff`].
+    ///
+    /// Fields that aren't in the KML file (region, wind directions, dates, etc.) are left empty.
+    pub fn to_new_takeoff(&self) -> NewTakeoff {
+        NewTakeoff {
+            name: self.name.clone(),
+            description: html_to_text(&self.description, self.link.as_deref()),
+            image: None,
+            region: String::new(),
+            altitude_m: self.altitude.map(|altitude| altitude.round() as i32),
+            height_diff_m: None,
+            latitude: self.latitude,
+            longitude: self.longitude,
+            wind_dirs: Vec::new(),
+            info_url: None,
+            source: Some(flightlog::NAME.to_owned()),
+            source_url: self.link.clone(),
+            created_at: None,
+            created_by: None,
+            updated_at: None,
+            updated_by: None,
+        }
+    }
+}
+
+/// Get placemarks from KML file.
 ///
 /// # Arguments
 ///
@@ -17,59 +64,300 @@ use tracing::info;
 ///
 /// # Errors
 ///
-/// This function will return an error if Regex or parsing fails.
+/// This function will return an error if reading or parsing fails.
 ///
 /// # Returns
 ///
-/// A list of URLs.
-pub fn get_urls(path: &str) -> Result<Vec<String>, anyhow::Error> {
-    let mut out = Vec::new();
+/// A list of placemarks.
+pub fn get_placemarks(path: &str) -> Result<Vec<Placemark>, anyhow::Error> {
     let contents = fs::read_to_string(path)?;
-    let re = Regex::new(r"https:\/\/flightlog\.org\/fl\.html\?.+start_id=\d+&quot;&gt;")?;
-    let caps = re.captures_iter(&contents);
-
-    for cap in caps {
-        let url = cap
-            .get(0)
-            .ok_or(anyhow!("regex failed"))?
-            .as_str()
-            .replace("&amp;amp;", "&")
-            .replace("&quot;&gt;", "");
-
-        out.push(url);
-    }
+    let out = parse_placemarks(&contents)?;
+
+    info!("Found {} placemarks from KML file.", out.len());
+
+    Ok(out)
+}
+
+/// Parse placemarks from KML.
+///
+/// # Arguments
+///
+/// * `contents` - KML document.
+///
+/// # Errors
+///
+/// This function will return an error if the XML is malformed, or a placemark has invalid coordinates.
+///
+/// # Returns
+///
+/// A list of placemarks, in document order.
+pub fn parse_placemarks(contents: &str) -> Result<Vec<Placemark>, anyhow::Error> {
+    let mut out = Vec::new();
+    let mut reader = Reader::from_str(contents);
+    reader.config_mut().trim_text(true);
 
-    info!("Found {} URLs from KML file.", out.len());
+    // Path of open elements, and the placemark being parsed (if any)
+    let mut path: Vec<String> = Vec::new();
+    let mut placemark: Option<Placemark> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(element) => {
+                let name = String::from_utf8(element.local_name().as_ref().to_vec())?;
+                if name == "Placemark" {
+                    placemark = Some(Placemark::default());
+                }
+                path.push(name);
+            }
+            Event::End(_) => {
+                let closed = path.pop();
+                if closed.as_deref() == Some("Placemark") {
+                    let mut done = placemark
+                        .take()
+                        .ok_or(anyhow!("unexpected end of placemark"))?;
+                    done.link = find_link(&done.description);
+                    out.push(done);
+                }
+            }
+            Event::Text(text) => {
+                if let Some(placemark) = placemark.as_mut() {
+                    set_field(placemark, &path, &repair_encoding(&text.unescape()?))?;
+                }
+            }
+            Event::CData(text) => {
+                if let Some(placemark) = placemark.as_mut() {
+                    set_field(
+                        placemark,
+                        &path,
+                        &repair_encoding(&String::from_utf8_lossy(&text)),
+                    )?;
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
 
     Ok(out)
 }
 
-/// Get Flightlog URLs from KML file that are not present in the database.
+/// Set the placemark field for the text at `path`.
+///
+/// # Errors
+///
+/// This function will return an error if parsing coordinates fails.
+fn set_field(placemark: &mut Placemark, path: &[String], text: &str) -> Result<(), anyhow::Error> {
+    match path {
+        [.., parent, field] if parent == "Placemark" && field == "name" => {
+            placemark.name.push_str(text);
+        }
+        [.., parent, field] if parent == "Placemark" && field == "description" => {
+            placemark.description.push_str(text);
+        }
+        [.., parent, field] if parent == "Point" && field == "coordinates" => {
+            let mut values = text
+                .trim()
+                .split(',')
+                .map(|value| value.trim().parse::<f64>());
+
+            placemark.longitude = values.next().ok_or(anyhow!("missing longitude"))??;
+            placemark.latitude = values.next().ok_or(anyhow!("missing latitude"))??;
+            placemark.altitude = values.next().transpose()?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Find the flightlog.org takeoff link in a description.
+///
+/// # Arguments
+///
+/// * `description` - Description as HTML.
+///
+/// # Returns
+///
+/// The first link containing a `start_id`, if any.
+fn find_link(description: &str) -> Option<String> {
+    let fragment = Html::parse_fragment(description);
+    let selector = Selector::parse("a[href]").ok()?;
+
+    fragment
+        .select(&selector)
+        .filter_map(|element| element.value().attr("href"))
+        .find(|href| href.contains("flightlog.org") && href.contains("start_id="))
+        .map(|href| href.to_owned())
+}
+
+/// Convert an HTML description to plain text.
+///
+/// # Arguments
+///
+/// * `html` - Description as HTML.
+/// * `link` - A link whose anchor text is left out.
+///
+/// # Returns
+///
+/// Text where `br` elements are line breaks.
+fn html_to_text(html: &str, link: Option<&str>) -> String {
+    let fragment = Html::parse_fragment(html);
+    let mut out = String::new();
+
+    for node in fragment.root_element().descendants() {
+        match node.value() {
+            Node::Element(element) if element.name() == "br" => out.push('\n'),
+            Node::Text(text) => {
+                let in_link = node.ancestors().any(|ancestor| {
+                    ancestor.value().as_element().is_some_and(|element| {
+                        element.name() == "a" && element.attr("href") == link
+                    })
+                });
+
+                if !in_link {
+                    out.push_str(text);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out.trim().to_owned()
+}
+
+/// Repair text that has been UTF-8 encoded twice (e.g. `LillestrÃ¸m`).
+///
+/// # Arguments
+///
+/// * `text` - Possibly double encoded text.
+///
+/// # Returns
+///
+/// The repaired text, or the original text if it isn't double encoded.
+fn repair_encoding(text: &str) -> String {
+    let bytes = text
+        .chars()
+        .map(|c| u8::try_from(u32::from(c)).ok())
+        .collect::<Option<Vec<u8>>>();
+
+    bytes
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .unwrap_or_else(|| text.to_owned())
+}
+
+/// Get placemarks from KML file that are not present in the database.
 ///
 /// # Arguments
 ///
 /// * `path` - A filepath to a KML file from flightlog.org.
-/// * `conn` - A connection to the Postgres database.
+/// * `pool` - A Postgres connection pool.
 ///
 /// # Errors
 ///
-/// This function will return an error if Regex or parsing fails.
+/// This function will return an error if reading or parsing fails.
 ///
 /// # Returns
 ///
-/// A list of URLs.
-pub async fn get_missing_urls(
+/// A list of placemarks with links.
+pub async fn get_missing_placemarks(
     path: &str,
-    conn: &mut PgConnection,
-) -> Result<Vec<String>, anyhow::Error> {
-    let parsed_urls = get_urls(path)?;
-    let existing_urls = helpers::get_source_urls(&mut *conn).await?;
-    let out = parsed_urls
+    pool: &PgPool,
+) -> Result<Vec<Placemark>, anyhow::Error> {
+    let existing_urls = helpers::get_source_urls(pool).await?;
+    let out = get_placemarks(path)?
         .into_iter()
-        .filter(|url| !existing_urls.contains(url))
-        .collect::<Vec<String>>();
+        .filter(|placemark| {
+            placemark
+                .link
+                .as_ref()
+                .is_some_and(|url| !existing_urls.contains(url))
+        })
+        .collect::<Vec<Placemark>>();
 
-    info!("Found {} missing URLs from KML file.", out.len());
+    info!("Found {} missing placemarks from KML file.", out.len());
 
     Ok(out)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Link to a takeoff on flightlog.org.
+    fn link(id: u32) -> Option<String> {
+        Some(format!(
+            "https://flightlog.org/fl.html?l=1&a=22&country_id=160&start_id={id}"
+        ))
+    }
+
+    #[test]
+    fn country_160() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/country_160.kml");
+        let placemarks = get_placemarks(path).unwrap();
+
+        assert_eq!(placemarks.len(), 3241);
+        assert!(placemarks.iter().all(|placemark| placemark.link.is_some()));
+
+        let first = &placemarks[0];
+        assert_eq!(first.name, "Akershus EnergiPark - Lillestrøm - PPG");
+        assert_eq!(first.link, link(5842));
+        assert_eq!(
+            (first.latitude, first.longitude, first.altitude),
+            (59.97222222, 11.07694444, Some(110.0))
+        );
+        assert!(first
+            .description
+            .starts_with("Innenfor meldepunkter til Kjeller"));
+
+        assert_eq!(
+            placemarks[1].name,
+            "Asker - Kråkholmen, Blakstadbukta (OTP)"
+        );
+        assert_eq!(placemarks[1].link, link(4122));
+        assert_eq!(placemarks[999].name, "Rundenakkjen");
+        assert_eq!(placemarks[999].link, link(7034));
+        assert_eq!(placemarks[3240].name, "Trøgstad (Oppslep)");
+        assert_eq!(placemarks[3240].link, link(266));
+    }
+
+    #[test]
+    fn to_new_takeoff() {
+        let kml = r#"<kml><Placemark>
+            <name>Hanguren</name>
+            <description>Nice start.&lt;br/&gt;&lt;a href="https://flightlog.org/fl.html?l=1&amp;amp;a=22&amp;amp;start_id=7"&gt;link&lt;/a&gt;</description>
+            <Point><coordinates>6.41,60.64</coordinates></Point>
+        </Placemark></kml>"#;
+        let placemarks = parse_placemarks(kml).unwrap();
+        let takeoff = placemarks[0].to_new_takeoff();
+
+        assert_eq!(takeoff.name, "Hanguren");
+        assert_eq!(takeoff.description, "Nice start.");
+        assert_eq!(takeoff.altitude_m, None);
+        assert_eq!((takeoff.latitude, takeoff.longitude), (60.64, 6.41));
+        assert_eq!(
+            takeoff.source_url.as_deref(),
+            Some("https://flightlog.org/fl.html?l=1&a=22&start_id=7")
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        let unclosed = "<kml><Placemark><name>A</Placemark></kml>";
+        let coordinates = "<kml><Placemark><Point><coordinates>east,north</coordinates></Point></Placemark></kml>";
+        let missing =
+            "<kml><Placemark><Point><coordinates>6.41</coordinates></Point></Placemark></kml>";
+
+        assert!(parse_placemarks(unclosed).is_err());
+        assert!(parse_placemarks(coordinates).is_err());
+        assert!(parse_placemarks(missing).is_err());
+        assert!(parse_placemarks("").unwrap().is_empty());
+        assert!(get_placemarks("resources/missing.kml").is_err());
+    }
+
+    #[test]
+    fn repairs_encoding() {
+        assert_eq!(repair_encoding("LillestrÃ¸m"), "Lillestrøm");
+        assert_eq!(repair_encoding("Lillestrøm"), "Lillestrøm");
+        assert_eq!(repair_encoding("Ålesund"), "Ålesund");
+    }
+}
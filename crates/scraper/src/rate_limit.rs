@@ -0,0 +1,152 @@
+//! Per-host rate limiting.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Default requests per second to each host.
+pub const DEFAULT_RATE: f64 = 0.5;
+/// Default number of requests that can be made at once to each host.
+pub const DEFAULT_BURST: u32 = 1;
+
+/// A token bucket rate limiter with one bucket per host.
+///
+/// Buckets hold up to `burst` tokens and are refilled with `rate` tokens per second.
+/// A request takes a token, or waits until one is available.
+#[derive(Debug)]
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+/// Tokens of a host.
+#[derive(Debug)]
+struct Bucket {
+    /// Available tokens, negative if requests are waiting.
+    tokens: f64,
+    /// Last time the tokens were refilled.
+    refilled: Instant,
+}
+
+impl RateLimiter {
+    /// Create a rate limiter.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate` - Requests per second to each host, must be positive.
+    /// * `burst` - Number of requests that can be made at once to each host.
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst: f64::from(burst.max(1)),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait until a request to the host of `url` is allowed.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - The URL to request.
+    pub async fn acquire(&self, url: &str) {
+        let host = reqwest::Url::parse(url)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_owned))
+            .unwrap_or_default();
+
+        let wait = {
+            let mut buckets = self.buckets.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let bucket = buckets.entry(host).or_insert(Bucket {
+                tokens: self.burst,
+                refilled: now,
+            });
+
+            let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst) - 1.0;
+            bucket.refilled = now;
+
+            // Reserve the token, and wait until it would have been refilled
+            match bucket.tokens < 0.0 {
+                true => Duration::from_secs_f64(-bucket.tokens / self.rate),
+                false => Duration::ZERO,
+            }
+        };
+
+        tokio::time::sleep(wait).await;
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new(DEFAULT_RATE, DEFAULT_BURST)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::join_all;
+
+    /// Seconds since `start` after acquiring a token for each of `urls` concurrently.
+    async fn acquire_all(limiter: &RateLimiter, start: Instant, urls: &[&str]) -> Vec<f64> {
+        join_all(urls.iter().map(|url| async move {
+            limiter.acquire(url).await;
+            (Instant::now() - start).as_secs_f64()
+        }))
+        .await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn bursts() {
+        let limiter = RateLimiter::new(1.0, 3);
+        let start = Instant::now();
+        let url = "https://flightlog.org/fl.html";
+
+        let times = acquire_all(&limiter, start, &[url, url, url, url, url]).await;
+
+        assert_eq!(times, [0.0, 0.0, 0.0, 1.0, 2.0]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn refills() {
+        let limiter = RateLimiter::new(0.5, 1);
+        let start = Instant::now();
+        let url = "https://flightlog.org/fl.html";
+
+        assert_eq!(acquire_all(&limiter, start, &[url, url]).await, [0.0, 2.0]);
+
+        // Half a token was refilled a second after the last wait, so it waits for the other half
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(acquire_all(&limiter, start, &[url]).await, [4.0]);
+
+        // Idle buckets fill up to the burst only
+        tokio::time::advance(Duration::from_secs(60)).await;
+        assert_eq!(
+            acquire_all(&limiter, start, &[url, url]).await,
+            [64.0, 66.0]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn buckets_per_host() {
+        let limiter = RateLimiter::new(0.5, 1);
+        let start = Instant::now();
+
+        let times = acquire_all(
+            &limiter,
+            start,
+            &[
+                "https://flightlog.org/a",
+                "https://paraglidingearth.com/a",
+                "https://flightlog.org/b",
+                "http://paraglidingearth.com:8080/b",
+            ],
+        )
+        .await;
+
+        assert_eq!(times, [0.0, 0.0, 2.0, 2.0]);
+    }
+}
@@ -0,0 +1,278 @@
+//! Refresh takeoffs that have changed in their source.
+
+use crate::scrape_web::{self, Queue, Scraped};
+use crate::sources::TakeoffSource;
+use futures::StreamExt;
+use serde::Serialize;
+use server_lib::helpers;
+use server_lib::models::{ChangeNote, NewTakeoff, SourceVersion};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use tracing::{error, info};
+
+/// Report of the changes found by [`refresh`], as source URLs.
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    /// Source name.
+    pub source: String,
+    /// Takeoffs that were inserted.
+    pub new: Vec<String>,
+    /// Takeoffs that were updated.
+    pub updated: Vec<String>,
+    /// Takeoffs that changed in the source, but were edited by a user since they were last
+    /// scraped. These are not updated, so the edits aren't lost.
+    pub conflicts: Vec<String>,
+    /// Takeoffs that are no longer in the source. These are not deleted.
+    pub removed: Vec<String>,
+    /// Takeoffs that were merged into other takeoffs. These are not scraped.
+    pub merged: Vec<String>,
+    /// Takeoffs that have not changed.
+    pub unchanged: Vec<String>,
+    /// Takeoffs that could not be scraped.
+    pub failed: Vec<String>,
+}
+
+/// A change to apply to the database.
+enum Change {
+    /// Insert or update a takeoff.
+    Upsert(Box<NewTakeoff>),
+    /// Set the content hash of an unchanged takeoff that has none.
+    Hash(i32, String),
+}
+
+/// How a scraped takeoff compares to its stored version.
+#[derive(Debug, PartialEq)]
+enum Comparison {
+    Changed,
+    /// Changed, but the stored takeoff was edited by a user.
+    Conflict,
+    Unchanged,
+}
+
+/// Compare a scraped takeoff with content hash `hash` to its stored version.
+fn compare(version: &SourceVersion, takeoff: &NewTakeoff, hash: &str) -> Comparison {
+    let updated_changed =
+        version.updated_at != takeoff.updated_at || version.updated_by != takeoff.updated_by;
+    let hash_changed = version
+        .content_hash
+        .as_ref()
+        .is_some_and(|stored_hash| stored_hash != hash);
+
+    // Edits set the update date and updater, so only the content hash can tell
+    match version.edited {
+        true if hash_changed || version.content_hash.is_none() => Comparison::Conflict,
+        false if updated_changed || hash_changed => Comparison::Changed,
+        _ => Comparison::Unchanged,
+    }
+}
+
+/// Re-scrape all takeoffs in a source and apply the changes to the database.
+///
+/// A stored takeoff has changed if its update date, updater or content hash differs from the scraped takeoff.
+/// Takeoffs stored without a content hash are compared by update date and updater only.
+/// Takeoffs that were merged into other takeoffs are skipped, so they aren't inserted again.
+/// Changed takeoffs that were edited by a user since they were last scraped are reported as
+/// conflicts instead of updated.
+///
+/// # Arguments
+///
+/// * `source` - The source to refresh.
+/// * `pool` - A Postgres connection pool.
+/// * `queue` - Concurrency and retries.
+/// * `dry_run` - Only report changes, without applying them.
+///
+/// # Errors
+///
+/// This function will return an error if discovery or the database fails. The changes are applied
+/// in a single transaction, so nothing is applied if any of them fails. Scraping errors are logged and reported.
+///
+/// # Returns
+///
+/// A [`Report`].
+pub async fn refresh(
+    source: &dyn TakeoffSource,
+    pool: &PgPool,
+    queue: Queue,
+    dry_run: bool,
+) -> Result<Report, anyhow::Error> {
+    let stored = helpers::get_source_versions(pool, source.name())
+        .await?
+        .into_iter()
+        .map(|version| (version.source_url.clone(), version))
+        .collect::<HashMap<String, SourceVersion>>();
+    let merged = helpers::get_merged_source_urls(pool).await?;
+    let (ids, merged_ids): (Vec<String>, Vec<String>) = source
+        .discover()
+        .await?
+        .into_iter()
+        .partition(|id| !merged.contains(&source.source_url(id)));
+    let discovered = ids
+        .iter()
+        .map(|id| source.source_url(id))
+        .collect::<HashSet<String>>();
+
+    let mut report = Report {
+        source: source.name().to_owned(),
+        merged: merged_ids.iter().map(|id| source.source_url(id)).collect(),
+        ..Default::default()
+    };
+    let mut changes = Vec::new();
+
+    let total = ids.len();
+    let mut scraped = std::pin::pin!(scrape_web::scrape_all(source, ids, queue));
+    let mut i = 0;
+
+    while let Some(Scraped { id, result, .. }) = scraped.next().await {
+        i += 1;
+        info!("Refreshed {i} / {total} from {}", source.name());
+        let url = source.source_url(&id);
+
+        let takeoff = match result {
+            Ok(takeoff) => takeoff,
+            Err(err) => {
+                error!("{url}: {err}");
+                report.failed.push(url);
+                continue;
+            }
+        };
+
+        let Some(version) = stored.get(&url) else {
+            report.new.push(url);
+            changes.push(Change::Upsert(Box::new(takeoff)));
+            continue;
+        };
+
+        let hash = helpers::content_hash(&takeoff);
+        match compare(version, &takeoff, &hash) {
+            Comparison::Changed => {
+                report.updated.push(url);
+                changes.push(Change::Upsert(Box::new(takeoff)));
+            }
+            Comparison::Conflict => report.conflicts.push(url),
+            Comparison::Unchanged => {
+                if version.content_hash.is_none() {
+                    changes.push(Change::Hash(version.id, hash));
+                }
+                report.unchanged.push(url);
+            }
+        }
+    }
+
+    report.removed = stored
+        .into_keys()
+        .filter(|url| !discovered.contains(url))
+        .collect();
+
+    // Takeoffs are scraped concurrently, so sort for a stable report
+    for urls in [
+        &mut report.new,
+        &mut report.updated,
+        &mut report.conflicts,
+        &mut report.removed,
+        &mut report.merged,
+        &mut report.unchanged,
+        &mut report.failed,
+    ] {
+        urls.sort();
+    }
+
+    // Apply changes
+    if dry_run {
+        info!("Dry run, not applying {} changes.", changes.len());
+    } else {
+        let note = ChangeNote::comment(format!("Refreshed from {}", report.source));
+        let mut tx = pool.begin().await?;
+        for change in &changes {
+            match change {
+                Change::Upsert(takeoff) => {
+                    helpers::upsert_takeoff(&mut *tx, takeoff, &note).await?
+                }
+                Change::Hash(id, hash) => helpers::set_content_hash(&mut *tx, *id, hash).await?,
+            }
+        }
+        tx.commit().await?;
+    }
+
+    info!(
+        "Refreshed {}: {} new, {} updated, {} conflicts, {} removed, {} merged, {} unchanged, {} failed.",
+        report.source,
+        report.new.len(),
+        report.updated.len(),
+        report.conflicts.len(),
+        report.removed.len(),
+        report.merged.len(),
+        report.unchanged.len(),
+        report.failed.len()
+    );
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn takeoff() -> NewTakeoff {
+        NewTakeoff {
+            name: "Hanguren".to_owned(),
+            description: String::new(),
+            image: None,
+            region: "Vestland".to_owned(),
+            altitude_m: Some(650),
+            height_diff_m: Some(600),
+            latitude: 60.645,
+            longitude: 6.401,
+            wind_dirs: vec!["S".to_owned()],
+            info_url: None,
+            source: Some("flightlog".to_owned()),
+            source_url: Some("https://flightlog.org/?id=1".to_owned()),
+            created_at: None,
+            created_by: None,
+            updated_at: Some(Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap()),
+            updated_by: Some("Kari".to_owned()),
+        }
+    }
+
+    /// Stored version of [`takeoff`].
+    fn version(edited: bool) -> SourceVersion {
+        let takeoff = takeoff();
+        SourceVersion {
+            id: 1,
+            source_url: takeoff.source_url.clone().unwrap(),
+            updated_at: takeoff.updated_at,
+            updated_by: takeoff.updated_by.clone(),
+            content_hash: Some(helpers::content_hash(&takeoff)),
+            edited,
+        }
+    }
+
+    #[test]
+    fn compares_takeoffs() {
+        let unchanged = takeoff();
+        let mut changed = takeoff();
+        changed.altitude_m = Some(700);
+
+        let compare = |version: &SourceVersion, takeoff: &NewTakeoff| {
+            compare(version, takeoff, &helpers::content_hash(takeoff))
+        };
+        assert_eq!(compare(&version(false), &unchanged), Comparison::Unchanged);
+        assert_eq!(compare(&version(false), &changed), Comparison::Changed);
+
+        // Edits set the update date and updater
+        let mut edited = version(true);
+        edited.updated_at = Some(Utc::now());
+        edited.updated_by = Some("editor".to_owned());
+        assert_eq!(compare(&edited, &unchanged), Comparison::Unchanged);
+        assert_eq!(compare(&edited, &changed), Comparison::Conflict);
+
+        // Without a hash, only the update date and updater tell
+        let mut unhashed = version(false);
+        unhashed.content_hash = None;
+        assert_eq!(compare(&unhashed, &changed), Comparison::Unchanged);
+        unhashed.updated_by = None;
+        assert_eq!(compare(&unhashed, &changed), Comparison::Changed);
+        unhashed.edited = true;
+        assert_eq!(compare(&unhashed, &unchanged), Comparison::Conflict);
+    }
+}
@@ -1,221 +1,186 @@
 #![deny(missing_docs)]
 
-//! Scrape takeoffs from flightlog.org.
+//! Scrape takeoffs from a source.
 
-use anyhow::anyhow;
-use futures::future::OptionFuture;
-use regex::Regex;
+use crate::fetch;
+use crate::output::Output;
+use crate::sources::TakeoffSource;
+use futures::{Stream, StreamExt};
 use server_lib::helpers;
 use server_lib::models::NewTakeoff;
-use sqlx::PgConnection;
-use thirtyfour::{error::WebDriverError, DesiredCapabilities, WebDriver};
-use thirtyfour::{By, ChromiumLikeCapabilities, WebElement};
-use tracing::{error, info};
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Default number of takeoffs scraped at once.
+pub const DEFAULT_CONCURRENCY: usize = 4;
+/// Default number of retries after transient errors.
+pub const DEFAULT_RETRIES: u32 = 3;
+/// Delay before the first retry, doubled for each following retry.
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+/// Maximum delay before a retry.
+const BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// Options for scraping many takeoffs.
+#[derive(Debug, Clone, Copy)]
+pub struct Queue {
+    /// Number of takeoffs scraped at once.
+    pub concurrency: usize,
+    /// Number of retries after transient errors.
+    pub retries: u32,
+}
 
-/// Seconds to wait before redirecting to a new URL.
-const PAGE_BEFORE_DELAY: u64 = 2;
-/// Seconds to wait before starting scraping after redirecting.
-const PAGE_SCRAPE_DELAY: u64 = 2;
+/// A scraped takeoff.
+pub struct Scraped {
+    /// The takeoff id in the source.
+    pub id: String,
+    /// Number of attempts, including retries.
+    pub attempts: u32,
+    /// The takeoff, or the error of the last attempt.
+    pub result: Result<NewTakeoff, anyhow::Error>,
+}
 
-/// Scrape takeoffs and save them to the database.
+/// Scrape takeoffs and write them to an output.
+///
+/// With a `checkpoint`, the status of each takeoff is saved in the `scrape_jobs` table,
+/// so an interrupted run can be resumed and failed takeoffs retried.
 ///
 /// # Arguments
-/// 
-/// * `urls` - A list of URLs to scrape.
-/// * `conn` - A connection to the Postgres database.
-/// 
-/// # Errors
 ///
-/// This function will return an error if initializing the chrome driver fails.
-/// All other errors are logged.
-#[rustfmt::skip]
-pub async fn try_scrape_all(urls: Vec<String>, conn: &mut PgConnection) -> Result<(), anyhow::Error> {
-    let driver = init_driver().await?;
-
-    for (i, url) in urls.iter().enumerate() {
-        info!("Scraping {} / {}", i + 1, urls.len());
-        try_scrape_and_insert(&url, conn, &driver).await.map_err(|err| error!("{url}: {err}")).ok();
-    }
-
-    Ok(())
-}
-
-/// Try scraping a takeoff and save to the database.
+/// * `source` - The source to scrape.
+/// * `ids` - A list of takeoff ids in the source.
+/// * `queue` - Concurrency and retries.
+/// * `output` - Where to write takeoffs.
+/// * `checkpoint` - Optional database to save the status of each takeoff in.
 ///
-/// # Arguments
-/// 
-/// * `url` - A URL to scrape.
-/// * `conn` - A connection to the Postgres database.
-/// * `driver` - A Chrome driver.
-/// 
 /// # Errors
 ///
-/// This function will return an error if scraping or inserting fails.
-#[rustfmt::skip]
-async fn try_scrape_and_insert(url: &str, conn: &mut PgConnection, driver: &WebDriver) -> Result<(), anyhow::Error> {
-    let takeoff = scrape_takeoff(url, &driver).await?;
-    helpers::insert_takeoff(&mut *conn, &takeoff).await?;
+/// All errors are logged.
+pub async fn try_scrape_all(
+    source: &dyn TakeoffSource,
+    ids: Vec<String>,
+    queue: Queue,
+    output: &mut Output,
+    checkpoint: Option<&PgPool>,
+) {
+    let total = ids.len();
+    let (mut done, mut failed) = (0, 0);
+    let mut scraped = std::pin::pin!(scrape_all(source, ids, queue));
+
+    while let Some(Scraped {
+        id,
+        attempts,
+        result,
+    }) = scraped.next().await
+    {
+        let url = source.source_url(&id);
+        let result = match result {
+            Ok(takeoff) => output.write(takeoff).await,
+            Err(err) => Err(err),
+        };
+
+        if let Err(err) = &result {
+            error!("{url}: {err}");
+            failed += 1;
+        } else {
+            done += 1;
+        }
+        info!("Scraped {} / {total} from {}", done + failed, source.name());
+
+        if let Some(pool) = checkpoint {
+            let error = result.err().map(|err| err.to_string());
+            let attempts = i32::try_from(attempts).unwrap_or(i32::MAX);
+            helpers::finish_scrape_job(pool, source.name(), &id, attempts, error.as_deref())
+                .await
+                .map_err(|err| error!("{url}: failed to save checkpoint: {err}"))
+                .ok();
+        }
+    }
 
-    Ok(())
+    info!(
+        "Scraped {done} takeoffs from {}, {failed} failed.",
+        source.name()
+    );
 }
 
-/// Scrape a takeoff.
+/// Scrape takeoffs concurrently, retrying transient errors.
 ///
 /// # Arguments
-/// 
-/// * `url` - A URL to scrape.
-/// * `driver` - A Chrome driver.
-/// 
-/// # Errors
 ///
-/// This function will return an error if any part of the scraping fails.
-/// 
-/// # Returns
-/// 
-/// A [`NewTakeoff`].
-#[rustfmt::skip]
-async fn scrape_takeoff(url: &str, driver: &WebDriver) -> Result<NewTakeoff, anyhow::Error> {
-    sleep(PAGE_BEFORE_DELAY);
-    driver.goto(url).await?;
-    sleep(PAGE_SCRAPE_DELAY);
-    
-    let name = driver.find(By::Css("body > div > table:nth-child(2) > tbody > tr:nth-child(2) > td > table > tbody > tr:nth-child(3) > td > span")).await?.text().await?;
-    let description = driver.find(By::XPath("//td[contains(.,'Description')]/following-sibling::td")).await?;
-    let image = OptionFuture::from(description.find(By::Css("a > img")).await.ok().map(|element| as_png(element, driver))).await.transpose().ok().flatten();
-    let region = driver.find(By::XPath("//td[contains(.,'region')]/following-sibling::td")).await?.text().await?;
-    let (altitude, altitude_diff) = extract_altitude_info(&driver.find(By::XPath("//td[contains(.,'Altitude')]/following-sibling::td")).await?.text().await?)?;
-    let (latitude, longitude) = dms_to_dec(&driver.find(By::XPath("//td[contains(.,'Coordinates')]/following-sibling::td")).await?.text().await?)?;
-    let wind_dirs = description.find(By::Css("img")).await.ok();
-    let wind_dirs = if let Some(e) = wind_dirs { e.attr("alt").await?.unwrap_or_default().split(' ').filter(|e| e != &" " && e != &"").map(|e| e.to_owned()).collect() } else { Vec::new() };
-    let info_url = driver.find(By::XPath("//td[contains(.,'Link to more info')]/following-sibling::td/a")).await.ok();
-    let info_url = if let Some(e) = info_url { e.attr("href").await? } else { None };
-    let created = driver.find(By::XPath("//td[contains(.,'created')]/following-sibling::td")).await?.text().await?;
-    let updated = driver.find(By::XPath("//td[contains(.,'Updated')]/following-sibling::td")).await?.text().await?;
-    let description = description.text().await?;
-    let source_url = Some(url.to_owned());
-
-    Ok(NewTakeoff {
-        name,
-        description,
-        image,
-        region,
-        altitude,
-        altitude_diff,
-        latitude,
-        longitude,
-        wind_dirs,
-        info_url,
-        source_url,
-        created,
-        updated,
-    })
-}
-
-/// Sleep in the current thread.
-/// 
-/// # Arguments
-/// 
-/// * `secs` - Amount of seconds to sleep.
-#[rustfmt::skip]
-fn sleep(secs: u64) {
-    std::thread::sleep(std::time::Duration::from_secs(secs));
-}
-
-/// Opens image in a new window and takes a screenshot.
-/// 
-/// # Arguments
-/// 
-/// * `element` - The `img` element, where the parent element is an element with the `href` attribute to the source.
-/// * `driver` - A Chrome driver.
-/// 
-/// # Errors
+/// * `source` - The source to scrape.
+/// * `ids` - A list of takeoff ids in the source.
+/// * `queue` - Concurrency and retries.
 ///
-/// This function will return an error if it can't find the image source or there's a driver issue.
-/// 
 /// # Returns
-/// 
-/// A PNG image in bytes.
-#[rustfmt::skip]
-async fn as_png(element: WebElement, driver: &WebDriver) -> Result<Vec<u8>, anyhow::Error> {
-    let source = element.parent().await?.attr("href").await?.ok_or(anyhow!("missing image source"))?;
-
-    driver.in_new_tab(|| async {
-        driver.goto(source).await?;
-        driver.find(By::Css("img")).await?.screenshot_as_png().await
-    }).await.map_err(|err| anyhow!("{err}"))
+///
+/// A stream of [`Scraped`] takeoffs, in the order they finish.
+pub fn scrape_all(
+    source: &dyn TakeoffSource,
+    ids: Vec<String>,
+    queue: Queue,
+) -> impl Stream<Item = Scraped> + '_ {
+    futures::stream::iter(ids)
+        .map(move |id| async move {
+            let (attempts, result) = scrape_with_retries(source, &id, queue.retries).await;
+
+            Scraped {
+                id,
+                attempts,
+                result,
+            }
+        })
+        .buffer_unordered(queue.concurrency.max(1))
 }
 
-/// Convert a string of DMS coordinates to latitude and longitude.
+/// Scrape a takeoff, retrying transient errors with exponential backoff.
 ///
-/// # Arguments
-/// 
-/// `text` - DMS coordinates (e.g. `DMS: N 60° 38' 44''  E 6° 24' 28''`).
-/// 
-/// # Errors
-///
-/// This function will return an error if Regex or parsing fails.
-/// 
 /// # Returns
-/// 
-/// A tuple where the first value is the latitude and the second value is the longitude.
-#[rustfmt::skip]
-fn dms_to_dec(text: &str) -> Result<(f64, f64), anyhow::Error> {
-    let re = Regex::new(r"(\d+)")?;
-    let mut caps = re.captures_iter(text);
-
-    let nd = caps.next().unwrap().get(1).unwrap().as_str().parse::<f64>()?;
-    let nm = caps.next().unwrap().get(1).unwrap().as_str().parse::<f64>()?;
-    let ns = caps.next().unwrap().get(1).unwrap().as_str().parse::<f64>()?;
-    let ed = caps.next().unwrap().get(1).unwrap().as_str().parse::<f64>()?;
-    let em = caps.next().unwrap().get(1).unwrap().as_str().parse::<f64>()?;
-    let es = caps.next().unwrap().get(1).unwrap().as_str().parse::<f64>()?;
-
-    let latitude = nd + (nm / 60_f64) + (ns / 3600_f64);
-    let longitude = ed + (em / 60_f64) + (es / 3600_f64);
-
-    Ok((latitude, longitude))
+///
+/// The number of attempts and the result of the last attempt.
+async fn scrape_with_retries(
+    source: &dyn TakeoffSource,
+    id: &str,
+    retries: u32,
+) -> (u32, Result<NewTakeoff, anyhow::Error>) {
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+        match scrape_takeoff(source, id).await {
+            Err(err) if attempts <= retries && fetch::is_transient(&err) => {
+                let delay = BACKOFF_BASE
+                    .saturating_mul(2_u32.saturating_pow(attempts - 1))
+                    .min(BACKOFF_MAX);
+                warn!("{}: {err}, retrying in {delay:?}", source.source_url(id));
+                tokio::time::sleep(delay).await;
+            }
+            result => return (attempts, result),
+        }
+    }
 }
 
-/// Extract altitude info from a string.
-/// 
+/// Scrape a takeoff.
+///
 /// # Arguments
-/// 
-/// * `text` - Latitude string (e.g. `	790 meters asl Top to bottom 740 meters`).
-/// 
-/// # Errors
 ///
-/// This function will return an error if Regex or parsing fails.
-/// 
-/// # Returns
-/// 
-/// A tuple where the first value is the altitude and the second value is the altitude difference.
-#[rustfmt::skip]
-fn extract_altitude_info(text: &str) -> Result<(Option<i32>, Option<i32>), anyhow::Error> {
-    let re = Regex::new(r"(\d+)")?;
-    let mut caps = re.captures_iter(text);
-
-    let altitude = caps.next().map(|e| e.get(1).unwrap().as_str().parse::<i32>()).transpose()?;
-    let altitude_diff = caps.next().map(|e| e.get(1).unwrap().as_str().parse::<i32>()).transpose()?;
-
-    Ok((altitude, altitude_diff))
-}
-
-/// Initialize and configure the web driver.
+/// * `source` - The source to scrape.
+/// * `id` - A takeoff id in the source.
 ///
 /// # Errors
 ///
-///  This function will return an error if initialization fails.
+/// This function will return an error if fetching or parsing fails.
 ///
 /// # Returns
 ///
-/// A Chrome driver.
-async fn init_driver() -> Result<WebDriver, WebDriverError> {
-    let mut caps = DesiredCapabilities::chrome();
-    caps.set_no_sandbox()?;
-    caps.set_disable_dev_shm_usage()?;
-
-    let driver = WebDriver::new("http://localhost:4444", caps).await?;
-    driver.maximize_window().await?;
-
-    Ok(driver)
+/// A [`NewTakeoff`] with `source` and `source_url` set.
+pub async fn scrape_takeoff(
+    source: &dyn TakeoffSource,
+    id: &str,
+) -> Result<NewTakeoff, anyhow::Error> {
+    let raw = source.fetch(id).await?;
+    let mut takeoff = source.parse(id, &raw).await?;
+    takeoff.source = Some(source.name().to_owned());
+    takeoff.source_url = Some(source.source_url(id));
+
+    Ok(takeoff)
 }
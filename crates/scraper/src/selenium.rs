@@ -0,0 +1,72 @@
+//! Fetch pages with a Chrome driver, as a fallback for pages that need a browser.
+
+use anyhow::anyhow;
+use std::time::Duration;
+use thirtyfour::{error::WebDriverError, DesiredCapabilities, WebDriver};
+use thirtyfour::{By, ChromiumLikeCapabilities};
+
+/// Seconds to wait before reading the page after redirecting.
+const PAGE_SCRAPE_DELAY: u64 = 2;
+
+/// Initialize and configure the web driver.
+///
+/// # Errors
+///
+/// This function will return an error if initialization fails.
+///
+/// # Returns
+///
+/// A Chrome driver.
+pub async fn init_driver() -> Result<WebDriver, WebDriverError> {
+    let mut caps = DesiredCapabilities::chrome();
+    caps.set_no_sandbox()?;
+    caps.set_disable_dev_shm_usage()?;
+
+    let driver = WebDriver::new("http://localhost:4444", caps).await?;
+    driver.maximize_window().await?;
+
+    Ok(driver)
+}
+
+/// Fetch the source of a page.
+///
+/// # Arguments
+///
+/// * `driver` - A Chrome driver.
+/// * `url` - A URL to fetch.
+///
+/// # Errors
+///
+/// This function will return an error if there's a driver issue.
+///
+/// # Returns
+///
+/// The page source, after scripts have run.
+pub async fn fetch_page(driver: &WebDriver, url: &str) -> Result<String, anyhow::Error> {
+    driver.goto(url).await?;
+    tokio::time::sleep(Duration::from_secs(PAGE_SCRAPE_DELAY)).await;
+
+    Ok(driver.source().await?)
+}
+
+/// Opens image in a new window and takes a screenshot.
+///
+/// # Arguments
+///
+/// * `driver` - A Chrome driver.
+/// * `url` - The image source.
+///
+/// # Errors
+///
+/// This function will return an error if there's a driver issue.
+///
+/// # Returns
+///
+/// A PNG image in bytes.
+#[rustfmt::skip]
+pub async fn fetch_image(driver: &WebDriver, url: &str) -> Result<Vec<u8>, anyhow::Error> {
+    driver.in_new_tab(|| async {
+        driver.goto(url).await?;
+        driver.find(By::Css("img")).await?.screenshot_as_png().await
+    }).await.map_err(|err| anyhow!("{err}"))
+}
@@ -0,0 +1,128 @@
+//! Takeoffs from flightlog.org.
+
+use super::{Config, TakeoffSource};
+use crate::fetch::Backend;
+use crate::rate_limit::RateLimiter;
+use crate::{extract_html, parse_kml};
+use async_trait::async_trait;
+use server_lib::models::NewTakeoff;
+use std::sync::Arc;
+use tracing::error;
+
+/// Source name.
+pub const NAME: &str = "flightlog";
+/// Takeoff page URL, without query.
+const BASE_URL: &str = "https://flightlog.org/fl.html";
+/// Country of the takeoffs in the KML file (Norway).
+const COUNTRY_ID: u32 = 160;
+
+/// Flightlog source.
+///
+/// Takeoffs are discovered from a KML file, and the id is the `start_id` of the takeoff page.
+pub struct Flightlog {
+    kml_path: String,
+    backend: Backend,
+    limiter: Arc<RateLimiter>,
+}
+
+impl Flightlog {
+    /// Create a flightlog source.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration for the source.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if creating the backend fails.
+    ///
+    /// # Returns
+    ///
+    /// A [`Flightlog`].
+    pub async fn new(config: &Config) -> Result<Self, anyhow::Error> {
+        #[cfg(feature = "selenium")]
+        let backend = if config.selenium {
+            Backend::selenium().await?
+        } else {
+            Backend::http()?
+        };
+        #[cfg(not(feature = "selenium"))]
+        let backend = match config.selenium {
+            true => return Err(anyhow::anyhow!("built without the selenium feature")),
+            false => Backend::http()?,
+        };
+
+        Ok(Self {
+            kml_path: config.kml_path.clone(),
+            backend,
+            limiter: config.limiter.clone(),
+        })
+    }
+}
+
+#[async_trait]
+impl TakeoffSource for Flightlog {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    fn source_url(&self, id: &str) -> String {
+        format!("{BASE_URL}?l=1&a=22&country_id={COUNTRY_ID}&start_id={id}")
+    }
+
+    fn id_from_url(&self, url: &str) -> Option<String> {
+        url.contains("flightlog.org")
+            .then(|| start_id(url))
+            .flatten()
+    }
+
+    async fn discover(&self) -> Result<Vec<String>, anyhow::Error> {
+        let out = parse_kml::get_placemarks(&self.kml_path)?
+            .into_iter()
+            .filter_map(|placemark| start_id(&placemark.link?))
+            .collect();
+
+        Ok(out)
+    }
+
+    async fn fetch(&self, id: &str) -> Result<String, anyhow::Error> {
+        let url = self.source_url(id);
+        self.limiter.acquire(&url).await;
+
+        self.backend.fetch_page(&url).await
+    }
+
+    async fn parse(&self, id: &str, raw: &str) -> Result<NewTakeoff, anyhow::Error> {
+        let extracted = extract_html::extract_takeoff(raw, &self.source_url(id))?;
+
+        let mut takeoff = extracted.takeoff;
+        if let Some(image_url) = extracted.image_url {
+            self.limiter.acquire(&image_url).await;
+            takeoff.image = self
+                .backend
+                .fetch_image(&image_url)
+                .await
+                .map_err(|err| error!("{image_url}: {err}"))
+                .ok();
+        }
+
+        Ok(takeoff)
+    }
+}
+
+/// Get the `start_id` query parameter of a takeoff page URL.
+///
+/// # Arguments
+///
+/// * `url` - A takeoff page URL.
+///
+/// # Returns
+///
+/// The takeoff id, if any.
+pub fn start_id(url: &str) -> Option<String> {
+    reqwest::Url::parse(url)
+        .ok()?
+        .query_pairs()
+        .find(|(key, _)| key == "start_id")
+        .map(|(_, value)| value.into_owned())
+}
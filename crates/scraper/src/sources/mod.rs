@@ -0,0 +1,154 @@
+//! Sources of takeoffs.
+//!
+//! Each source implements [`TakeoffSource`], and is registered in [`NAMES`] and [`create`].
+
+pub mod flightlog;
+
+use crate::rate_limit::RateLimiter;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use server_lib::helpers;
+use server_lib::models::NewTakeoff;
+use sqlx::PgPool;
+use std::sync::Arc;
+use tracing::info;
+
+/// Names of all sources.
+pub const NAMES: [&str; 1] = [flightlog::NAME];
+
+/// A database of takeoffs that can be scraped.
+#[async_trait]
+pub trait TakeoffSource: Send + Sync {
+    /// Unique name, saved as the takeoff source.
+    fn name(&self) -> &'static str;
+
+    /// Get the URL of a takeoff, used to deduplicate takeoffs.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A takeoff id in the source.
+    fn source_url(&self, id: &str) -> String;
+
+    /// Get the id of a takeoff from its URL.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - A takeoff URL.
+    ///
+    /// # Returns
+    ///
+    /// The takeoff id, or `None` if the URL isn't a takeoff in this source.
+    fn id_from_url(&self, url: &str) -> Option<String>;
+
+    /// Discover takeoffs.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if discovery fails.
+    ///
+    /// # Returns
+    ///
+    /// A list of takeoff ids in the source.
+    async fn discover(&self) -> Result<Vec<String>, anyhow::Error>;
+
+    /// Fetch a takeoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A takeoff id in the source.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if fetching fails.
+    ///
+    /// # Returns
+    ///
+    /// The raw takeoff (e.g. a HTML page).
+    async fn fetch(&self, id: &str) -> Result<String, anyhow::Error>;
+
+    /// Parse a fetched takeoff.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - A takeoff id in the source.
+    /// * `raw` - The raw takeoff from [`TakeoffSource::fetch`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if parsing fails.
+    ///
+    /// # Returns
+    ///
+    /// A [`NewTakeoff`].
+    async fn parse(&self, id: &str, raw: &str) -> Result<NewTakeoff, anyhow::Error>;
+}
+
+/// Configuration shared by sources.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Filepath to a KML file from flightlog.org.
+    pub kml_path: String,
+    /// Fetch pages with a Chrome driver instead of plain HTTP.
+    pub selenium: bool,
+    /// Rate limiter shared by sources, so sources on the same host share a limit.
+    pub limiter: Arc<RateLimiter>,
+}
+
+/// Create a source.
+///
+/// # Arguments
+///
+/// * `name` - One of [`NAMES`].
+/// * `config` - Configuration for the source.
+///
+/// # Errors
+///
+/// This function will return an error if the source is unknown or can't be created.
+///
+/// # Returns
+///
+/// A source.
+pub async fn create(name: &str, config: &Config) -> Result<Box<dyn TakeoffSource>, anyhow::Error> {
+    match name {
+        flightlog::NAME => Ok(Box::new(flightlog::Flightlog::new(config).await?)),
+        _ => Err(anyhow!(
+            "unknown source {name}, expected one of: {}",
+            NAMES.join(", ")
+        )),
+    }
+}
+
+/// Discover takeoffs that are not present in the database.
+///
+/// # Arguments
+///
+/// * `source` - The source to discover takeoffs from.
+/// * `pool` - A Postgres connection pool.
+///
+/// # Errors
+///
+/// This function will return an error if discovery or the database query fails.
+///
+/// # Returns
+///
+/// A list of takeoff ids in the source.
+pub async fn discover_missing(
+    source: &dyn TakeoffSource,
+    pool: &PgPool,
+) -> Result<Vec<String>, anyhow::Error> {
+    let existing_urls = helpers::get_source_urls(pool).await?;
+    let out = source
+        .discover()
+        .await?
+        .into_iter()
+        .filter(|id| !existing_urls.contains(&source.source_url(id)))
+        .collect::<Vec<String>>();
+
+    info!(
+        "Found {} missing takeoffs from {}.",
+        out.len(),
+        source.name()
+    );
+
+    Ok(out)
+}
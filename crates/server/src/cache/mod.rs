@@ -0,0 +1,235 @@
+//! HTTP caching of API responses.
+//!
+//! * [`Validators`] set `ETag`/`Last-Modified` and answer conditional requests.
+//! * [`ResponseCache`] keeps rendered responses in memory until takeoffs change.
+//! * `Cache-Control` policies are set per route, see [`CacheControl`].
+
+use crate::models::DatasetVersion;
+use axum::{
+    body::{Body, Bytes},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use futures::{stream, StreamExt};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tower_http::set_header::SetResponseHeaderLayer;
+
+/// Format of HTTP dates (e.g. `Sun, 06 Nov 1994 08:49:37 GMT`).
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Maximum number of cached responses.
+const MAX_ENTRIES: usize = 256;
+/// Maximum size of a cached response body in bytes, larger responses are only streamed.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// `Cache-Control` policies.
+#[derive(Debug, Clone, Copy)]
+pub enum CacheControl {
+    /// May be cached, but must be revalidated with `ETag`/`Last-Modified` before use.
+    Revalidate,
+    /// May be cached for a minute.
+    Short,
+    /// Must not be cached, for sessions and writes.
+    NoStore,
+}
+
+impl CacheControl {
+    /// Get the header value of the policy.
+    pub fn value(&self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            CacheControl::Revalidate => "public, no-cache",
+            CacheControl::Short => "public, max-age=60",
+            CacheControl::NoStore => "no-store",
+        })
+    }
+
+    /// Layer setting the policy on responses that don't have one.
+    ///
+    /// Unsuccessful responses are never cached.
+    pub fn layer(
+        self,
+    ) -> SetResponseHeaderLayer<impl Fn(&Response) -> Option<HeaderValue> + Clone> {
+        SetResponseHeaderLayer::if_not_present(header::CACHE_CONTROL, move |response: &Response| {
+            match response.status().is_success() || response.status() == StatusCode::NOT_MODIFIED {
+                true => Some(self.value()),
+                false => Some(CacheControl::NoStore.value()),
+            }
+        })
+    }
+}
+
+/// Cache validators of the takeoffs.
+#[derive(Debug, Clone)]
+pub struct Validators {
+    /// Weak `ETag` of the dataset version.
+    pub etag: String,
+    /// Optional date of the last change, in seconds.
+    pub last_modified: Option<DateTime<Utc>>,
+}
+
+impl Validators {
+    /// Create validators from a dataset version.
+    pub fn new(version: &DatasetVersion) -> Self {
+        Self {
+            etag: format!("W/\"{}\"", version.version),
+            last_modified: version
+                .modified_at
+                .and_then(|date| DateTime::from_timestamp(date.timestamp(), 0)),
+        }
+    }
+
+    /// Check if a conditional request can be answered with `304 Not Modified`.
+    ///
+    /// `If-None-Match` is compared weakly, and `If-Modified-Since` is ignored if it's present.
+    pub fn not_modified(&self, headers: &HeaderMap) -> bool {
+        let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+        let mut if_none_match = headers
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .peekable();
+        if if_none_match.peek().is_some() {
+            return if_none_match.any(|tag| tag.trim() == "*" || opaque(tag) == opaque(&self.etag));
+        }
+
+        let if_modified_since = headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok());
+        match (self.last_modified, if_modified_since) {
+            (Some(modified), Some(since)) => modified <= since,
+            _ => false,
+        }
+    }
+
+    /// Set `ETag` and `Last-Modified` headers.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let Ok(etag) = HeaderValue::from_str(&self.etag) {
+            headers.insert(header::ETAG, etag);
+        }
+        if let Some(Ok(modified)) = self
+            .last_modified
+            .map(|date| HeaderValue::from_str(&date.format(HTTP_DATE).to_string()))
+        {
+            headers.insert(header::LAST_MODIFIED, modified);
+        }
+    }
+
+    /// Respond with `304 Not Modified`.
+    pub fn not_modified_response(&self, cache_control: CacheControl) -> Response {
+        let mut response = StatusCode::NOT_MODIFIED.into_response();
+        self.apply(response.headers_mut());
+        response
+            .headers_mut()
+            .insert(header::CACHE_CONTROL, cache_control.value());
+        response
+    }
+}
+
+/// A cached response.
+#[derive(Debug, Clone)]
+struct Entry {
+    /// Dataset version the response was rendered from.
+    version: i64,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+/// In-process cache of successful responses, keyed by request URI.
+///
+/// Entries are only used for the dataset version they were rendered from, so changes by
+/// other processes (e.g. the scraper) are never served stale. Writes in this process should
+/// also [`ResponseCache::invalidate`] the cache to free memory early.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseCache {
+    entries: Arc<Mutex<HashMap<String, Entry>>>,
+}
+
+impl ResponseCache {
+    /// Get a cached response rendered from `version`.
+    pub fn get(&self, key: &str, version: i64) -> Option<Response> {
+        let entries = self.entries.lock().ok()?;
+        let entry = entries.get(key).filter(|entry| entry.version == version)?;
+
+        let mut response = Response::new(Body::from(entry.body.clone()));
+        *response.headers_mut() = entry.headers.clone();
+        Some(response)
+    }
+
+    /// Cache a response rendered from `version`, if it's successful.
+    ///
+    /// The body is passed through as it's streamed, and only kept if it's small enough.
+    ///
+    /// # Returns
+    ///
+    /// The same response.
+    pub fn insert(&self, key: &str, version: i64, response: Response) -> Response {
+        if !response.status().is_success() {
+            return response;
+        }
+
+        let (parts, body) = response.into_parts();
+        let state = (
+            self.clone(),
+            key.to_owned(),
+            parts.headers.clone(),
+            body.into_data_stream(),
+            Some(Vec::new()),
+        );
+        let body = stream::unfold(
+            state,
+            move |(cache, key, headers, mut body, mut buffer)| async move {
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer =
+                            buffer.filter(|buffer| buffer.len() + chunk.len() <= MAX_BODY_SIZE);
+                        if let Some(buffer) = buffer.as_mut() {
+                            buffer.extend_from_slice(&chunk);
+                        }
+                        Some((Ok(chunk), (cache, key, headers, body, buffer)))
+                    }
+                    Some(Err(error)) => Some((Err(error), (cache, key, headers, body, None))),
+                    None => {
+                        if let Some(buffer) = buffer {
+                            cache.store(key, version, headers, buffer.into());
+                        }
+                        None
+                    }
+                }
+            },
+        );
+
+        Response::from_parts(parts, Body::from_stream(body))
+    }
+
+    /// Store a complete response.
+    fn store(&self, key: String, version: i64, headers: HeaderMap, body: Bytes) {
+        if let Ok(mut entries) = self.entries.lock() {
+            // Drop entries of old versions first, then everything if still full
+            entries.retain(|_, entry| entry.version == version);
+            if entries.len() >= MAX_ENTRIES {
+                entries.clear();
+            }
+            entries.insert(
+                key,
+                Entry {
+                    version,
+                    headers,
+                    body,
+                },
+            );
+        }
+    }
+
+    /// Remove all cached responses, after takeoffs are written.
+    pub fn invalidate(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
@@ -8,6 +8,11 @@ use rand_chacha::ChaCha8Rng;
 use sqlx::PgPool;
 use std::sync::{Arc, Mutex};
 
+/// Role allowed to edit takeoffs.
+pub const EDITOR: &str = "editor";
+/// Role allowed to review suggestions.
+pub const MODERATOR: &str = "moderator";
+
 // TODO: Make sure the session token is created correctly.
 /// Create and set session token for the given `user_id`.
 ///
@@ -43,7 +48,7 @@ pub async fn create_session(
     // Construct the unhashed session
     let session = models::Session {
         id: session_id,
-        user_id: user_id,
+        user_id,
         token: session_token,
     };
 
@@ -104,6 +109,29 @@ pub async fn check_session(
     }
 }
 
+/// Check and delete a session.
+pub async fn delete_session(
+    db: PgPool,
+    session: &Option<models::Session>,
+) -> Result<(), ServerError> {
+    let user_id = check_session(db.clone(), session).await?;
+    let Some(session) = session else {
+        return Err(ServerError::UNAUTHORIZED("missing session"));
+    };
+
+    sqlx::query!(
+        r#"
+            DELETE FROM "sessions" WHERE id = $1 AND user_id = $2
+        "#,
+        session.id,
+        user_id
+    )
+    .execute(&db)
+    .await?;
+
+    Ok(())
+}
+
 /// Check if user has a certain role.
 ///
 /// Returns `true` or `false`.
@@ -125,3 +153,50 @@ pub async fn has_role(db: PgPool, user_id: i32, role: &str) -> Result<bool, Serv
 
     Ok(user_has_role)
 }
+
+/// Check session and that its user has a certain role.
+///
+/// Returns the user id.
+pub async fn require_role(
+    db: PgPool,
+    session: &Option<models::Session>,
+    role: &str,
+) -> Result<i32, ServerError> {
+    let user_id = check_session(db.clone(), session).await?;
+
+    if has_role(db, user_id, role).await? {
+        Ok(user_id)
+    } else {
+        Err(ServerError::FORBIDDEN(format!("missing role: {role}")))
+    }
+}
+
+/// Check session and that its user has one of the roles.
+///
+/// Returns the user id.
+pub async fn require_any_role(
+    db: PgPool,
+    session: &Option<models::Session>,
+    roles: &[&str],
+) -> Result<i32, ServerError> {
+    let user_id = check_session(db.clone(), session).await?;
+
+    for role in roles {
+        if has_role(db.clone(), user_id, role).await? {
+            return Ok(user_id);
+        }
+    }
+
+    Err(ServerError::FORBIDDEN(format!(
+        "missing role: one of {}",
+        roles.join(", ")
+    )))
+}
+
+/// Create a random token for public links, as 32 hex characters.
+pub fn create_token(random: Arc<Mutex<ChaCha8Rng>>) -> String {
+    let mut bytes = [0u8; 16];
+    random.lock().unwrap().fill_bytes(&mut bytes);
+
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -1,28 +1,57 @@
-use super::models::NewTakeoff;
-use sqlx::{Executor, Postgres};
+use super::models::{
+    BestFlight, ChangeNote, DatasetVersion, Flight, FlightStats, FlightTrack, ForecastHour,
+    GetTakeoff, NewFlight, NewStation, NewTakeoff, QualityIssue, Revision, ScrapeJobStatus,
+    SearchResult, SharedTakeoffList, SourceVersion, Station, StationReading, Suggestion,
+    SuggestionStatus, Takeoff, TakeoffChanges, TakeoffList,
+};
+use crate::validation::{duplicates, Issue, Rule, Severity};
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, PgConnection, Postgres};
+use std::collections::HashSet;
 
-pub async fn insert_takeoff<'a, E>(executor: E, data: &NewTakeoff) -> Result<(), sqlx::Error>
+/// Insert a takeoff.
+///
+/// The [`content_hash`] of `data` is saved with it, and a [`Revision`] of the creation is saved.
+/// Fails with a unique violation if a takeoff has the same `source_url`.
+pub async fn insert_takeoff<'a, E>(
+    executor: E,
+    data: &NewTakeoff,
+    note: &ChangeNote,
+) -> Result<(), sqlx::Error>
 where
     E: Executor<'a, Database = Postgres>,
 {
     sqlx::query!(
         r#"
-            INSERT INTO takeoffs(name, description, image, region, altitude, altitude_diff, latitude, longitude, wind_dirs, info_url, source_url, created, updated)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            WITH inserted AS (
+            INSERT INTO takeoffs(name, description, image, region, altitude_m, height_diff_m, latitude, longitude, wind_dirs, info_url, source, source_url, created_at, created_by, updated_at, updated_by, content_hash)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            RETURNING *
+            )
+            INSERT INTO takeoff_revisions(takeoff_id, action, snapshot, user_id, comment)
+            SELECT id, 'create', to_jsonb(inserted) - 'image' - 'content_hash', $18, $19
+            FROM inserted
         "#,
         data.name,
         data.description,
         data.image,
         data.region,
-        data.altitude,
-        data.altitude_diff,
+        data.altitude_m,
+        data.height_diff_m,
         data.latitude,
         data.longitude,
         &data.wind_dirs,
         data.info_url,
+        data.source,
         data.source_url,
-        data.created,
-        data.updated,
+        data.created_at,
+        data.created_by,
+        data.updated_at,
+        data.updated_by,
+        content_hash(data),
+        note.user_id,
+        note.comment,
     )
     .execute(executor)
     .await?;
@@ -30,13 +59,92 @@ where
     Ok(())
 }
 
-pub async fn get_source_urls<'a, E>(executor: E) -> Result<Vec<String>, sqlx::Error>
+/// Insert a takeoff, or update the takeoff with the same `source_url`.
+///
+/// The [`content_hash`] of `data` is saved with it, and a takeoff with the same hash is left
+/// unchanged. A [`Revision`] is saved for every change.
+pub async fn upsert_takeoff<'a, E>(
+    executor: E,
+    data: &NewTakeoff,
+    note: &ChangeNote,
+) -> Result<(), sqlx::Error>
 where
     E: Executor<'a, Database = Postgres>,
 {
-    let records = sqlx::query!(r#"SELECT source_url FROM takeoffs"#)
-        .fetch_all(executor)
-        .await?;
+    sqlx::query!(
+        r#"
+            WITH upserted AS (
+            INSERT INTO takeoffs(name, description, image, region, altitude_m, height_diff_m, latitude, longitude, wind_dirs, info_url, source, source_url, created_at, created_by, updated_at, updated_by, content_hash)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            ON CONFLICT (source_url) DO UPDATE SET
+                name = EXCLUDED.name,
+                description = EXCLUDED.description,
+                image = EXCLUDED.image,
+                region = EXCLUDED.region,
+                altitude_m = EXCLUDED.altitude_m,
+                height_diff_m = EXCLUDED.height_diff_m,
+                latitude = EXCLUDED.latitude,
+                longitude = EXCLUDED.longitude,
+                wind_dirs = EXCLUDED.wind_dirs,
+                info_url = EXCLUDED.info_url,
+                source = EXCLUDED.source,
+                created_at = EXCLUDED.created_at,
+                created_by = EXCLUDED.created_by,
+                updated_at = EXCLUDED.updated_at,
+                updated_by = EXCLUDED.updated_by,
+                content_hash = EXCLUDED.content_hash
+            WHERE takeoffs.content_hash IS DISTINCT FROM EXCLUDED.content_hash
+            RETURNING *, (xmax = 0) AS inserted
+            )
+            INSERT INTO takeoff_revisions(takeoff_id, action, snapshot, user_id, comment)
+            SELECT
+                id,
+                CASE WHEN inserted THEN 'create' ELSE 'update' END,
+                to_jsonb(upserted) - 'image' - 'content_hash' - 'inserted',
+                $18,
+                $19
+            FROM upserted
+        "#,
+        data.name,
+        data.description,
+        data.image,
+        data.region,
+        data.altitude_m,
+        data.height_diff_m,
+        data.latitude,
+        data.longitude,
+        &data.wind_dirs,
+        data.info_url,
+        data.source,
+        data.source_url,
+        data.created_at,
+        data.created_by,
+        data.updated_at,
+        data.updated_by,
+        content_hash(data),
+        note.user_id,
+        note.comment,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Get the source URLs of all takeoffs, including merged source URLs.
+pub async fn get_source_urls<'a, E>(executor: E) -> Result<HashSet<String>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT source_url FROM takeoffs
+            UNION
+            SELECT UNNEST(merged_source_urls) FROM takeoffs
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
     let out = records
         .into_iter()
         .filter_map(|record| record.source_url)
@@ -44,3 +152,1688 @@ where
 
     Ok(out)
 }
+
+/// Get the source URLs of takeoffs that were merged into other takeoffs.
+pub async fn get_merged_source_urls<'a, E>(executor: E) -> Result<HashSet<String>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT UNNEST(merged_source_urls) AS "source_url!" FROM takeoffs
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| record.source_url)
+        .collect())
+}
+
+/// Get the stored version of every takeoff from a source.
+///
+/// A takeoff is edited if its latest revision is by a user, and not by the scraper.
+pub async fn get_source_versions<'a, E>(
+    executor: E,
+    source: &str,
+) -> Result<Vec<SourceVersion>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let out = sqlx::query_as!(
+        SourceVersion,
+        r#"
+            SELECT
+                t.id, t.source_url AS "source_url!", t.updated_at, t.updated_by, t.content_hash,
+                COALESCE(
+                    (
+                        SELECT r.user_id IS NOT NULL
+                        FROM takeoff_revisions r
+                        WHERE r.takeoff_id = t.id
+                        ORDER BY r.id DESC
+                        LIMIT 1
+                    ),
+                    false
+                ) AS "edited!"
+            FROM takeoffs t
+            WHERE t.source = $1 AND t.source_url IS NOT NULL
+        "#,
+        source
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(out)
+}
+
+/// Set the content hash of a takeoff.
+pub async fn set_content_hash<'a, E>(executor: E, id: i32, hash: &str) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"UPDATE takeoffs SET content_hash = $2 WHERE id = $1"#,
+        id,
+        hash
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Add scrape jobs, or reset existing jobs to pending.
+pub async fn enqueue_scrape_jobs<'a, E>(
+    executor: E,
+    source: &str,
+    ids: &[String],
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            INSERT INTO scrape_jobs(source, source_id)
+            SELECT $1, source_id FROM UNNEST($2::TEXT[]) AS source_id
+            ON CONFLICT (source, source_id) DO UPDATE SET
+                status = 'pending',
+                attempts = 0,
+                last_error = NULL,
+                updated_at = now()
+        "#,
+        source,
+        ids
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Get the ids of scrape jobs from a source with a status.
+pub async fn get_scrape_jobs<'a, E>(
+    executor: E,
+    source: &str,
+    status: ScrapeJobStatus,
+) -> Result<Vec<String>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT source_id FROM scrape_jobs
+            WHERE source = $1 AND status = $2
+            ORDER BY updated_at, source_id
+        "#,
+        source,
+        status.as_str()
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records.into_iter().map(|record| record.source_id).collect())
+}
+
+/// Mark a scrape job as done, or as failed with an error.
+pub async fn finish_scrape_job<'a, E>(
+    executor: E,
+    source: &str,
+    id: &str,
+    attempts: i32,
+    error: Option<&str>,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let status = match error {
+        Some(_) => ScrapeJobStatus::Failed,
+        None => ScrapeJobStatus::Done,
+    };
+
+    sqlx::query!(
+        r#"
+            INSERT INTO scrape_jobs(source, source_id, status, attempts, last_error)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (source, source_id) DO UPDATE SET
+                status = EXCLUDED.status,
+                attempts = scrape_jobs.attempts + EXCLUDED.attempts,
+                last_error = EXCLUDED.last_error,
+                updated_at = now()
+        "#,
+        source,
+        id,
+        status.as_str(),
+        attempts,
+        error
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Get all takeoffs without images, ordered by id.
+pub async fn get_takeoffs_without_images<'a, E>(executor: E) -> Result<Vec<GetTakeoff>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as(
+        r#"
+            SELECT id, name, description, region, altitude_m, height_diff_m, latitude, longitude,
+                wind_dirs, info_url, source, source_url, created_at, created_by, updated_at, updated_by,
+                merged_source_urls
+            FROM takeoffs
+            ORDER BY id
+        "#,
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Get ids, names, coordinates and wind directions of takeoffs within bounds.
+///
+/// Bounds are in decimal degrees, and inclusive.
+pub async fn get_takeoffs_in_bounds<'a, E>(
+    executor: E,
+    (west, south, east, north): (f64, f64, f64, f64),
+) -> Result<Vec<GetTakeoff>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as(
+        r#"
+            SELECT id, name, latitude, longitude, wind_dirs
+            FROM takeoffs
+            WHERE latitude BETWEEN $1 AND $2 AND longitude BETWEEN $3 AND $4
+            ORDER BY id
+        "#,
+    )
+    .bind(south)
+    .bind(north)
+    .bind(west)
+    .bind(east)
+    .fetch_all(executor)
+    .await
+}
+
+/// Delete all quality issues.
+pub async fn clear_quality_issues<'a, E>(executor: E) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(r#"DELETE FROM quality_issues"#)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+/// Insert quality issues.
+///
+/// Issues without a takeoff id are skipped.
+pub async fn insert_quality_issues<'a, E>(executor: E, issues: &[Issue]) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let issues: Vec<(i32, &Issue)> = issues
+        .iter()
+        .filter_map(|issue| Some((issue.takeoff_id?, issue)))
+        .collect();
+    let takeoff_ids: Vec<i32> = issues.iter().map(|(id, _)| *id).collect();
+    let rules: Vec<&str> = issues
+        .iter()
+        .map(|(_, issue)| issue.rule.as_str())
+        .collect();
+    let severities: Vec<&str> = issues
+        .iter()
+        .map(|(_, issue)| issue.severity.as_str())
+        .collect();
+    let messages: Vec<&str> = issues
+        .iter()
+        .map(|(_, issue)| issue.message.as_str())
+        .collect();
+    let related_ids: Vec<Option<i32>> = issues.iter().map(|(_, issue)| issue.related_id).collect();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO quality_issues(takeoff_id, rule, severity, message, related_id)
+            SELECT * FROM UNNEST($1::INTEGER[], $2::TEXT[], $3::TEXT[], $4::TEXT[], $5::INTEGER[])
+        "#,
+        &takeoff_ids,
+        &rules as &[&str],
+        &severities as &[&str],
+        &messages as &[&str],
+        &related_ids as &[Option<i32>],
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Get quality issues, optionally of a takeoff, rule or severity.
+pub async fn get_quality_issues<'a, E>(
+    executor: E,
+    takeoff_id: Option<i32>,
+    rule: Option<Rule>,
+    severity: Option<Severity>,
+) -> Result<Vec<QualityIssue>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        QualityIssue,
+        r#"
+            SELECT id, takeoff_id, rule, severity, message, related_id, created_at
+            FROM quality_issues
+            WHERE ($1::INTEGER IS NULL OR takeoff_id = $1)
+                AND ($2::TEXT IS NULL OR rule = $2)
+                AND ($3::TEXT IS NULL OR severity = $3)
+            ORDER BY takeoff_id, id
+        "#,
+        takeoff_id,
+        rule.map(|rule| rule.as_str()),
+        severity.map(|severity| severity.as_str())
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Get a takeoff.
+pub async fn get_takeoff<'a, E>(executor: E, id: i32) -> Result<Option<Takeoff>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Takeoff,
+        r#"
+            SELECT id, name, description, image, region, altitude_m, height_diff_m, latitude, longitude,
+                wind_dirs, info_url, source, source_url, created_at, created_by, updated_at, updated_by,
+                merged_source_urls
+            FROM takeoffs
+            WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Get the username of a user.
+pub async fn get_username<'a, E>(executor: E, user_id: i32) -> Result<String, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let record = sqlx::query!(r#"SELECT username FROM users WHERE id = $1"#, user_id)
+        .fetch_one(executor)
+        .await?;
+
+    Ok(record.username)
+}
+
+/// Update every field of a takeoff, except the content hash, and save a [`Revision`].
+///
+/// Returns `false` if the takeoff doesn't exist.
+pub async fn update_takeoff<'a, E>(
+    executor: E,
+    data: &Takeoff,
+    note: &ChangeNote,
+) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+            WITH updated AS (
+            UPDATE takeoffs SET
+                name = $2,
+                description = $3,
+                image = $4,
+                region = $5,
+                altitude_m = $6,
+                height_diff_m = $7,
+                latitude = $8,
+                longitude = $9,
+                wind_dirs = $10,
+                info_url = $11,
+                source = $12,
+                source_url = $13,
+                created_at = $14,
+                created_by = $15,
+                updated_at = $16,
+                updated_by = $17,
+                merged_source_urls = $18
+            WHERE id = $1
+            RETURNING *
+            )
+            INSERT INTO takeoff_revisions(takeoff_id, action, snapshot, user_id, comment)
+            SELECT id, 'update', to_jsonb(updated) - 'image' - 'content_hash', $19, $20
+            FROM updated
+        "#,
+        data.id,
+        data.name,
+        data.description,
+        data.image,
+        data.region,
+        data.altitude_m,
+        data.height_diff_m,
+        data.latitude,
+        data.longitude,
+        &data.wind_dirs,
+        data.info_url,
+        data.source,
+        data.source_url,
+        data.created_at,
+        data.created_by,
+        data.updated_at,
+        data.updated_by,
+        &data.merged_source_urls,
+        note.user_id,
+        note.comment,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Insert a deleted takeoff again with the same id, and save a [`Revision`].
+pub async fn restore_takeoff<'a, E>(
+    executor: E,
+    data: &Takeoff,
+    note: &ChangeNote,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            WITH restored AS (
+            INSERT INTO takeoffs(id, name, description, image, region, altitude_m, height_diff_m, latitude, longitude, wind_dirs, info_url, source, source_url, created_at, created_by, updated_at, updated_by, merged_source_urls)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+            RETURNING *
+            )
+            INSERT INTO takeoff_revisions(takeoff_id, action, snapshot, user_id, comment)
+            SELECT id, 'create', to_jsonb(restored) - 'image' - 'content_hash', $19, $20
+            FROM restored
+        "#,
+        data.id,
+        data.name,
+        data.description,
+        data.image,
+        data.region,
+        data.altitude_m,
+        data.height_diff_m,
+        data.latitude,
+        data.longitude,
+        &data.wind_dirs,
+        data.info_url,
+        data.source,
+        data.source_url,
+        data.created_at,
+        data.created_by,
+        data.updated_at,
+        data.updated_by,
+        &data.merged_source_urls,
+        note.user_id,
+        note.comment,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Delete a takeoff, and save a [`Revision`] with its last state.
+///
+/// Returns `false` if the takeoff doesn't exist.
+pub async fn delete_takeoff<'a, E>(
+    executor: E,
+    id: i32,
+    note: &ChangeNote,
+) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+            WITH deleted AS (
+            DELETE FROM takeoffs WHERE id = $1
+            RETURNING *
+            )
+            INSERT INTO takeoff_revisions(takeoff_id, action, snapshot, user_id, comment)
+            SELECT id, 'delete', to_jsonb(deleted) - 'image' - 'content_hash', $2, $3
+            FROM deleted
+        "#,
+        id,
+        note.user_id,
+        note.comment,
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get the revisions of a takeoff, newest first.
+pub async fn get_takeoff_history<'a, E>(executor: E, id: i32) -> Result<Vec<Revision>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Revision,
+        r#"
+            SELECT r.id, r.takeoff_id, r.action, r.snapshot, r.user_id, u.username AS "username?", r.comment, r.created_at
+            FROM takeoff_revisions r
+            LEFT JOIN users u ON u.id = r.user_id
+            WHERE r.takeoff_id = $1
+            ORDER BY r.id DESC
+        "#,
+        id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Get a revision.
+pub async fn get_revision<'a, E>(executor: E, id: i32) -> Result<Option<Revision>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Revision,
+        r#"
+            SELECT r.id, r.takeoff_id, r.action, r.snapshot, r.user_id, u.username AS "username?", r.comment, r.created_at
+            FROM takeoff_revisions r
+            LEFT JOIN users u ON u.id = r.user_id
+            WHERE r.id = $1
+        "#,
+        id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Merge a takeoff into another with [`duplicates::merge`], and delete it.
+///
+/// Run this in a transaction.
+///
+/// Returns the merged takeoff.
+pub async fn merge_takeoffs(
+    connection: &mut PgConnection,
+    keep: Takeoff,
+    remove: Takeoff,
+    note: &ChangeNote,
+) -> Result<Takeoff, sqlx::Error> {
+    let remove_id = remove.id;
+    let merged = duplicates::merge(keep, remove);
+
+    delete_takeoff(&mut *connection, remove_id, note).await?;
+    update_takeoff(&mut *connection, &merged, note).await?;
+
+    Ok(merged)
+}
+
+/// Get pairs of takeoff ids that aren't duplicates, smallest first.
+pub async fn get_dismissed_duplicates<'a, E>(
+    executor: E,
+) -> Result<HashSet<(i32, i32)>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(r#"SELECT takeoff_a, takeoff_b FROM duplicate_dismissals"#)
+        .fetch_all(executor)
+        .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| (record.takeoff_a, record.takeoff_b))
+        .collect())
+}
+
+/// Mark a pair of takeoffs as not duplicates.
+pub async fn dismiss_duplicate<'a, E>(executor: E, a: i32, b: i32) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            INSERT INTO duplicate_dismissals(takeoff_a, takeoff_b)
+            VALUES ($1, $2)
+            ON CONFLICT DO NOTHING
+        "#,
+        a.min(b),
+        a.max(b)
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Insert a suggestion.
+///
+/// Returns the suggestion id.
+pub async fn insert_suggestion<'a, E>(
+    executor: E,
+    takeoff_id: i32,
+    user_id: i32,
+    changes: &TakeoffChanges,
+    comment: Option<&str>,
+) -> Result<i32, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"
+            INSERT INTO suggestions(takeoff_id, user_id, changes, comment)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+        "#,
+        takeoff_id,
+        user_id,
+        serde_json::Value::Object(changes.to_map()),
+        comment
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.id)
+}
+
+/// Get suggestions with a status, oldest first.
+pub async fn get_suggestions<'a, E>(
+    executor: E,
+    status: SuggestionStatus,
+) -> Result<Vec<Suggestion>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Suggestion,
+        r#"
+            SELECT s.id, s.takeoff_id, s.user_id, u.username AS "username?", s.changes, s.comment,
+                s.status, s.reviewer_id, s.review_comment, s.created_at, s.reviewed_at
+            FROM suggestions s
+            LEFT JOIN users u ON u.id = s.user_id
+            WHERE s.status = $1
+            ORDER BY s.id
+        "#,
+        status.as_str()
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Review a pending suggestion.
+///
+/// Returns the suggestion, or `None` if there's no pending suggestion with the id.
+pub async fn review_suggestion<'a, E>(
+    executor: E,
+    id: i32,
+    status: SuggestionStatus,
+    reviewer_id: i32,
+    comment: Option<&str>,
+) -> Result<Option<Suggestion>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Suggestion,
+        r#"
+            WITH reviewed AS (
+            UPDATE suggestions SET
+                status = $2,
+                reviewer_id = $3,
+                review_comment = $4,
+                reviewed_at = now()
+            WHERE id = $1 AND status = 'pending'
+            RETURNING *
+            )
+            SELECT r.id, r.takeoff_id, r.user_id, u.username AS "username?", r.changes, r.comment,
+                r.status, r.reviewer_id, r.review_comment, r.created_at, r.reviewed_at
+            FROM reviewed r
+            LEFT JOIN users u ON u.id = r.user_id
+        "#,
+        id,
+        status.as_str(),
+        reviewer_id,
+        comment
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Minimum trigram similarity of a search query to a word in a name, from 0 to 1.
+///
+/// Lower than the default of 0.6, so e.g. `hangruen` matches `Hanguren`.
+pub const WORD_SIMILARITY_THRESHOLD: f32 = 0.4;
+
+/// Search takeoffs by name, region and description, best match first.
+///
+/// Words are matched with Norwegian stemming, ignoring accents (`web_search` syntax, e.g.
+/// `"exact phrase" -excluded`). Names are also matched by trigram similarity, to allow typos.
+///
+/// Highlights are delimited by `<mark>` and `</mark>`, and the text isn't escaped.
+///
+/// Run this in a transaction, as it sets [`WORD_SIMILARITY_THRESHOLD`] for the transaction.
+pub async fn search_takeoffs(
+    connection: &mut PgConnection,
+    query: &str,
+    limit: i64,
+    offset: i64,
+) -> Result<Vec<SearchResult>, sqlx::Error> {
+    sqlx::query!(
+        r#"SELECT set_config('pg_trgm.word_similarity_threshold', $1, true)"#,
+        WORD_SIMILARITY_THRESHOLD.to_string()
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+
+    sqlx::query_as!(
+        SearchResult,
+        r#"
+            WITH q AS (
+                SELECT
+                    websearch_to_tsquery('norwegian_unaccent', $1) AS query,
+                    immutable_unaccent(lower($1)) AS text
+            )
+            SELECT
+                id,
+                name,
+                region,
+                latitude,
+                longitude,
+                (ts_rank(takeoff_document(name, region, description), q.query)
+                    + word_similarity(q.text, immutable_unaccent(lower(name)))) AS "rank!",
+                ts_headline('norwegian_unaccent', name, q.query,
+                    'StartSel=<mark>, StopSel=</mark>, HighlightAll=true') AS "name_highlight!",
+                ts_headline('norwegian_unaccent', description, q.query,
+                    'StartSel=<mark>, StopSel=</mark>, MaxWords=25, MinWords=10, MaxFragments=2') AS "snippet!"
+            FROM takeoffs, q
+            WHERE takeoff_document(name, region, description) @@ q.query
+                OR q.text <% immutable_unaccent(lower(name))
+            ORDER BY 6 DESC, id
+            LIMIT $2
+            OFFSET $3
+        "#,
+        query,
+        limit,
+        offset
+    )
+    .fetch_all(&mut *connection)
+    .await
+}
+
+/// Get the version of the takeoffs, which increases with every change.
+///
+/// The version is the number of revisions, as they're never deleted.
+pub async fn get_dataset_version<'a, E>(executor: E) -> Result<DatasetVersion, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        DatasetVersion,
+        r#"SELECT count(*) AS "version!", max(created_at) AS modified_at FROM takeoff_revisions"#
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Get a cursor for syncing changes.
+///
+/// The cursor is the oldest transaction that's still running, so every revision from
+/// transactions before it is committed (or rolled back) and can be synced.
+pub async fn get_sync_cursor<'a, E>(executor: E) -> Result<i64, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"SELECT pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT AS "cursor!""#
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.cursor)
+}
+
+/// Get the ids of takeoffs changed between two sync cursors, see [`get_sync_cursor`].
+///
+/// `since` is inclusive and `until` is exclusive.
+pub async fn get_changed_takeoff_ids<'a, E>(
+    executor: E,
+    since: i64,
+    until: i64,
+) -> Result<Vec<i32>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT DISTINCT takeoff_id FROM takeoff_revisions
+            WHERE txid >= $1 AND txid < $2
+            ORDER BY takeoff_id
+        "#,
+        since,
+        until
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| record.takeoff_id)
+        .collect())
+}
+
+/// Hash the content of a takeoff.
+///
+/// Returns a hex encoded SHA-256 digest of the takeoff as JSON.
+pub fn content_hash(data: &NewTakeoff) -> String {
+    let json = serde_json::to_vec(data).unwrap_or_default();
+    let digest = Sha256::digest(json);
+
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Get when the forecast of a takeoff was last fetched from a provider.
+pub async fn get_forecast_fetched_at<'a, E>(
+    executor: E,
+    takeoff_id: i32,
+    provider: &str,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"SELECT fetched_at FROM forecast_fetches WHERE takeoff_id = $1 AND provider = $2"#,
+        takeoff_id,
+        provider
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(record.map(|record| record.fetched_at))
+}
+
+/// Get the cached forecast of a takeoff from a provider, from the current hour on.
+pub async fn get_forecast_hours<'a, E>(
+    executor: E,
+    takeoff_id: i32,
+    provider: &str,
+) -> Result<Vec<ForecastHour>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        ForecastHour,
+        r#"
+            SELECT time, wind_speed_ms, wind_direction_deg, wind_gusts_ms, precipitation_mm, cloud_base_m
+            FROM forecasts
+            WHERE takeoff_id = $1 AND provider = $2 AND time >= date_trunc('hour', now())
+            ORDER BY time
+        "#,
+        takeoff_id,
+        provider
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Replace the cached forecast of a takeoff from a provider, and mark it as fetched now.
+///
+/// Returns when it was fetched.
+pub async fn replace_forecast(
+    conn: &mut PgConnection,
+    takeoff_id: i32,
+    provider: &str,
+    hours: &[ForecastHour],
+) -> Result<DateTime<Utc>, sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM forecasts WHERE takeoff_id = $1 AND provider = $2"#,
+        takeoff_id,
+        provider
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let times: Vec<DateTime<Utc>> = hours.iter().map(|hour| hour.time).collect();
+    let speeds: Vec<f64> = hours.iter().map(|hour| hour.wind_speed_ms).collect();
+    let directions: Vec<f64> = hours.iter().map(|hour| hour.wind_direction_deg).collect();
+    let gusts: Vec<Option<f64>> = hours.iter().map(|hour| hour.wind_gusts_ms).collect();
+    let precipitation: Vec<Option<f64>> = hours.iter().map(|hour| hour.precipitation_mm).collect();
+    let cloud_bases: Vec<Option<f64>> = hours.iter().map(|hour| hour.cloud_base_m).collect();
+    sqlx::query!(
+        r#"
+            INSERT INTO forecasts (
+                takeoff_id, provider, time, wind_speed_ms, wind_direction_deg, wind_gusts_ms, precipitation_mm, cloud_base_m
+            )
+            SELECT $1, $2, *
+            FROM UNNEST($3::TIMESTAMPTZ[], $4::FLOAT8[], $5::FLOAT8[], $6::FLOAT8[], $7::FLOAT8[], $8::FLOAT8[])
+            ON CONFLICT (takeoff_id, provider, time) DO NOTHING
+        "#,
+        takeoff_id,
+        provider,
+        &times,
+        &speeds,
+        &directions,
+        &gusts as &[Option<f64>],
+        &precipitation as &[Option<f64>],
+        &cloud_bases as &[Option<f64>]
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let record = sqlx::query!(
+        r#"
+            INSERT INTO forecast_fetches (takeoff_id, provider, fetched_at)
+            VALUES ($1, $2, now())
+            ON CONFLICT (takeoff_id, provider) DO UPDATE SET fetched_at = EXCLUDED.fetched_at
+            RETURNING fetched_at
+        "#,
+        takeoff_id,
+        provider
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    Ok(record.fetched_at)
+}
+
+/// Get all weather stations.
+pub async fn get_stations<'a, E>(executor: E) -> Result<Vec<Station>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Station,
+        r#"
+            SELECT id, provider, provider_id, name, latitude, longitude, altitude_m
+            FROM stations
+            ORDER BY id
+        "#
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Get when the stations of a provider were last updated.
+pub async fn get_stations_updated_at<'a, E>(
+    executor: E,
+    provider: &str,
+) -> Result<Option<DateTime<Utc>>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"SELECT max(updated_at) AS updated_at FROM stations WHERE provider = $1"#,
+        provider
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.updated_at)
+}
+
+/// Replace the stations of a provider.
+///
+/// Stations are updated by their provider id, and stations the provider no longer lists are
+/// deleted with their readings.
+pub async fn replace_stations(
+    conn: &mut PgConnection,
+    provider: &str,
+    stations: &[NewStation],
+) -> Result<(), sqlx::Error> {
+    let provider_ids: Vec<String> = stations.iter().map(|s| s.provider_id.clone()).collect();
+    let names: Vec<String> = stations.iter().map(|s| s.name.clone()).collect();
+    let latitudes: Vec<f64> = stations.iter().map(|s| s.latitude).collect();
+    let longitudes: Vec<f64> = stations.iter().map(|s| s.longitude).collect();
+    let altitudes: Vec<Option<i32>> = stations.iter().map(|s| s.altitude_m).collect();
+
+    sqlx::query!(
+        r#"DELETE FROM stations WHERE provider = $1 AND provider_id <> ALL($2)"#,
+        provider,
+        &provider_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO stations (provider, provider_id, name, latitude, longitude, altitude_m)
+            SELECT $1, *
+            FROM UNNEST($2::TEXT[], $3::TEXT[], $4::FLOAT8[], $5::FLOAT8[], $6::INTEGER[])
+            ON CONFLICT (provider, provider_id) DO UPDATE SET
+                name = EXCLUDED.name,
+                latitude = EXCLUDED.latitude,
+                longitude = EXCLUDED.longitude,
+                altitude_m = EXCLUDED.altitude_m,
+                updated_at = now()
+        "#,
+        provider,
+        &provider_ids,
+        &names,
+        &latitudes,
+        &longitudes,
+        &altitudes as &[Option<i32>]
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Insert readings of stations, skipping readings that are already saved.
+///
+/// `readings` are pairs of station ids and readings.
+///
+/// Returns the number of new readings.
+pub async fn insert_station_readings<'a, E>(
+    executor: E,
+    readings: &[(i32, StationReading)],
+) -> Result<u64, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let station_ids: Vec<i32> = readings.iter().map(|(id, _)| *id).collect();
+    let times: Vec<DateTime<Utc>> = readings.iter().map(|(_, r)| r.time).collect();
+    let speeds: Vec<Option<f64>> = readings.iter().map(|(_, r)| r.wind_speed_ms).collect();
+    let directions: Vec<Option<f64>> = readings.iter().map(|(_, r)| r.wind_direction_deg).collect();
+    let gusts: Vec<Option<f64>> = readings.iter().map(|(_, r)| r.wind_gusts_ms).collect();
+    let temperatures: Vec<Option<f64>> = readings.iter().map(|(_, r)| r.temperature_c).collect();
+
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO station_readings (
+                station_id, time, wind_speed_ms, wind_direction_deg, wind_gusts_ms, temperature_c
+            )
+            SELECT *
+            FROM UNNEST($1::INTEGER[], $2::TIMESTAMPTZ[], $3::FLOAT8[], $4::FLOAT8[], $5::FLOAT8[], $6::FLOAT8[])
+            ON CONFLICT (station_id, time) DO NOTHING
+        "#,
+        &station_ids,
+        &times,
+        &speeds as &[Option<f64>],
+        &directions as &[Option<f64>],
+        &gusts as &[Option<f64>],
+        &temperatures as &[Option<f64>]
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Delete station readings from before a date.
+///
+/// Returns the number of deleted readings.
+pub async fn delete_station_readings_before<'a, E>(
+    executor: E,
+    before: DateTime<Utc>,
+) -> Result<u64, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let result = sqlx::query!(r#"DELETE FROM station_readings WHERE time < $1"#, before)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Get the ids of takeoffs without linked stations.
+pub async fn get_unlinked_takeoff_ids<'a, E>(executor: E) -> Result<HashSet<i32>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT id
+            FROM takeoffs
+            WHERE NOT EXISTS (SELECT 1 FROM takeoff_stations WHERE takeoff_id = takeoffs.id)
+        "#
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records.into_iter().map(|record| record.id).collect())
+}
+
+/// Replace the stations linked to takeoffs.
+///
+/// `links` are `(takeoff_id, station_id, distance_m)`, and links of other takeoffs in
+/// `takeoff_ids` are removed.
+pub async fn replace_takeoff_stations(
+    conn: &mut PgConnection,
+    takeoff_ids: &[i32],
+    links: &[(i32, i32, f64)],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM takeoff_stations WHERE takeoff_id = ANY($1)"#,
+        takeoff_ids
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let link_takeoff_ids: Vec<i32> = links.iter().map(|link| link.0).collect();
+    let station_ids: Vec<i32> = links.iter().map(|link| link.1).collect();
+    let distances: Vec<f64> = links.iter().map(|link| link.2).collect();
+    sqlx::query!(
+        r#"
+            INSERT INTO takeoff_stations (takeoff_id, station_id, distance_m)
+            SELECT * FROM UNNEST($1::INTEGER[], $2::INTEGER[], $3::FLOAT8[])
+            ON CONFLICT (takeoff_id, station_id) DO UPDATE SET distance_m = EXCLUDED.distance_m
+        "#,
+        &link_takeoff_ids,
+        &station_ids,
+        &distances
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Get the stations linked to a takeoff with their distance in meters, closest first.
+pub async fn get_takeoff_stations<'a, E>(
+    executor: E,
+    takeoff_id: i32,
+) -> Result<Vec<(Station, f64)>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT s.id, s.provider, s.provider_id, s.name, s.latitude, s.longitude, s.altitude_m, ts.distance_m
+            FROM takeoff_stations ts
+            JOIN stations s ON s.id = ts.station_id
+            WHERE ts.takeoff_id = $1
+            ORDER BY ts.distance_m
+        "#,
+        takeoff_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let station = Station {
+                id: record.id,
+                provider: record.provider,
+                provider_id: record.provider_id,
+                name: record.name,
+                latitude: record.latitude,
+                longitude: record.longitude,
+                altitude_m: record.altitude_m,
+            };
+            (station, record.distance_m)
+        })
+        .collect())
+}
+
+/// Get readings of stations since a date, latest first.
+///
+/// Returns pairs of station ids and readings.
+pub async fn get_station_readings<'a, E>(
+    executor: E,
+    station_ids: &[i32],
+    since: DateTime<Utc>,
+) -> Result<Vec<(i32, StationReading)>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT station_id, time, wind_speed_ms, wind_direction_deg, wind_gusts_ms, temperature_c
+            FROM station_readings
+            WHERE station_id = ANY($1) AND time >= $2
+            ORDER BY time DESC
+        "#,
+        station_ids,
+        since
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let reading = StationReading {
+                time: record.time,
+                wind_speed_ms: record.wind_speed_ms,
+                wind_direction_deg: record.wind_direction_deg,
+                wind_gusts_ms: record.wind_gusts_ms,
+                temperature_c: record.temperature_c,
+            };
+            (record.station_id, reading)
+        })
+        .collect())
+}
+
+/// Star a takeoff for a user, if it isn't already.
+pub async fn insert_favorite<'a, E>(
+    executor: E,
+    user_id: i32,
+    takeoff_id: i32,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            INSERT INTO favorites (user_id, takeoff_id)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, takeoff_id) DO NOTHING
+        "#,
+        user_id,
+        takeoff_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Unstar a takeoff for a user.
+pub async fn delete_favorite<'a, E>(
+    executor: E,
+    user_id: i32,
+    takeoff_id: i32,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"DELETE FROM favorites WHERE user_id = $1 AND takeoff_id = $2"#,
+        user_id,
+        takeoff_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Get the ids of the favourite takeoffs of a user, latest first.
+pub async fn get_favorite_ids<'a, E>(executor: E, user_id: i32) -> Result<Vec<i32>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let records = sqlx::query!(
+        r#"
+            SELECT takeoff_id
+            FROM favorites
+            WHERE user_id = $1
+            ORDER BY created_at DESC, takeoff_id
+        "#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| record.takeoff_id)
+        .collect())
+}
+
+/// Insert a takeoff list of a user.
+///
+/// Returns the id of the list.
+pub async fn insert_takeoff_list<'a, E>(
+    executor: E,
+    user_id: i32,
+    name: &str,
+) -> Result<i32, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let record = sqlx::query!(
+        r#"INSERT INTO takeoff_lists (user_id, name) VALUES ($1, $2) RETURNING id"#,
+        user_id,
+        name
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(record.id)
+}
+
+/// Get the takeoff lists of a user, oldest first.
+///
+/// Only the list with `list_id` is returned if it's given.
+pub async fn get_takeoff_lists<'a, E>(
+    executor: E,
+    user_id: i32,
+    list_id: Option<i32>,
+) -> Result<Vec<TakeoffList>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        TakeoffList,
+        r#"
+            SELECT
+                l.id, l.name, l.share_token, l.created_at, l.updated_at,
+                COALESCE(
+                    array_agg(i.takeoff_id ORDER BY i.added_at, i.takeoff_id)
+                        FILTER (WHERE i.takeoff_id IS NOT NULL),
+                    '{}'
+                ) AS "takeoff_ids!"
+            FROM takeoff_lists l
+            LEFT JOIN takeoff_list_items i ON i.list_id = l.id
+            WHERE l.user_id = $1 AND ($2::INTEGER IS NULL OR l.id = $2)
+            GROUP BY l.id
+            ORDER BY l.id
+        "#,
+        user_id,
+        list_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Rename a takeoff list of a user.
+///
+/// Returns `false` if the user has no such list.
+pub async fn rename_takeoff_list<'a, E>(
+    executor: E,
+    list_id: i32,
+    user_id: i32,
+    name: &str,
+) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+            UPDATE takeoff_lists SET name = $3, updated_at = now()
+            WHERE id = $1 AND user_id = $2
+        "#,
+        list_id,
+        user_id,
+        name
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Set or remove the share token of a takeoff list of a user.
+///
+/// Returns `false` if the user has no such list.
+pub async fn set_takeoff_list_share_token<'a, E>(
+    executor: E,
+    list_id: i32,
+    user_id: i32,
+    share_token: Option<&str>,
+) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"UPDATE takeoff_lists SET share_token = $3 WHERE id = $1 AND user_id = $2"#,
+        list_id,
+        user_id,
+        share_token
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Delete a takeoff list of a user.
+///
+/// Returns `false` if the user has no such list.
+pub async fn delete_takeoff_list<'a, E>(
+    executor: E,
+    list_id: i32,
+    user_id: i32,
+) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"DELETE FROM takeoff_lists WHERE id = $1 AND user_id = $2"#,
+        list_id,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Add a takeoff to a list, if it isn't in it already.
+pub async fn insert_takeoff_list_item<'a, E>(
+    executor: E,
+    list_id: i32,
+    takeoff_id: i32,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            WITH touched AS (UPDATE takeoff_lists SET updated_at = now() WHERE id = $1)
+            INSERT INTO takeoff_list_items (list_id, takeoff_id)
+            VALUES ($1, $2)
+            ON CONFLICT (list_id, takeoff_id) DO NOTHING
+        "#,
+        list_id,
+        takeoff_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Remove a takeoff from a list.
+pub async fn delete_takeoff_list_item<'a, E>(
+    executor: E,
+    list_id: i32,
+    takeoff_id: i32,
+) -> Result<(), sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+            WITH touched AS (UPDATE takeoff_lists SET updated_at = now() WHERE id = $1)
+            DELETE FROM takeoff_list_items WHERE list_id = $1 AND takeoff_id = $2
+        "#,
+        list_id,
+        takeoff_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Get a shared takeoff list by its share token, with its takeoffs in the order they were added.
+///
+/// Takeoffs have all fields except the image and source.
+pub async fn get_shared_takeoff_list(
+    conn: &mut PgConnection,
+    share_token: &str,
+) -> Result<Option<SharedTakeoffList>, sqlx::Error> {
+    let Some(list) = sqlx::query!(
+        r#"SELECT id, name, updated_at FROM takeoff_lists WHERE share_token = $1"#,
+        share_token
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let takeoffs = sqlx::query_as(
+        r#"
+            SELECT
+                t.id, t.name, t.description, t.region, t.altitude_m, t.height_diff_m,
+                t.latitude, t.longitude, t.wind_dirs, t.info_url, t.created_at, t.updated_at
+            FROM takeoff_list_items i
+            JOIN takeoffs t ON t.id = i.takeoff_id
+            WHERE i.list_id = $1
+            ORDER BY i.added_at, i.takeoff_id
+        "#,
+    )
+    .bind(list.id)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(Some(SharedTakeoffList {
+        name: list.name,
+        updated_at: list.updated_at,
+        takeoffs,
+    }))
+}
+
+/// Insert a flight of a user.
+///
+/// Returns the flight.
+pub async fn insert_flight<'a, E>(
+    executor: E,
+    user_id: i32,
+    flight: &NewFlight,
+) -> Result<Flight, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Flight,
+        r#"
+            INSERT INTO flights (
+                user_id, takeoff_id, date, duration_min, distance_km, landing, notes, conditions
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *
+        "#,
+        user_id,
+        flight.takeoff_id,
+        flight.date,
+        flight.duration_min,
+        flight.distance_km,
+        flight.landing,
+        flight.notes,
+        flight.conditions
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// Get the flights of a user, latest first.
+pub async fn get_flights<'a, E>(executor: E, user_id: i32) -> Result<Vec<Flight>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Flight,
+        r#"SELECT * FROM flights WHERE user_id = $1 ORDER BY date DESC, id DESC"#,
+        user_id
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Update a flight of a user.
+///
+/// Returns the flight, or `None` if the user has no such flight.
+pub async fn update_flight<'a, E>(
+    executor: E,
+    id: i32,
+    user_id: i32,
+    flight: &NewFlight,
+) -> Result<Option<Flight>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Flight,
+        r#"
+            UPDATE flights SET
+                takeoff_id = $3, date = $4, duration_min = $5, distance_km = $6, landing = $7,
+                notes = $8, conditions = $9, updated_at = now()
+            WHERE id = $1 AND user_id = $2
+            RETURNING *
+        "#,
+        id,
+        user_id,
+        flight.takeoff_id,
+        flight.date,
+        flight.duration_min,
+        flight.distance_km,
+        flight.landing,
+        flight.notes,
+        flight.conditions
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Delete a flight of a user.
+///
+/// Returns `false` if the user has no such flight.
+pub async fn delete_flight<'a, E>(executor: E, id: i32, user_id: i32) -> Result<bool, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"DELETE FROM flights WHERE id = $1 AND user_id = $2"#,
+        id,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Get statistics of the flights logged from a takeoff.
+pub async fn get_flight_stats(
+    conn: &mut PgConnection,
+    takeoff_id: i32,
+) -> Result<FlightStats, sqlx::Error> {
+    let record = sqlx::query!(
+        r#"
+            SELECT
+                count(*) AS "flights!",
+                count(DISTINCT user_id) AS "pilots!",
+                avg(duration_min)::FLOAT8 AS average_duration_min
+            FROM flights
+            WHERE takeoff_id = $1
+        "#,
+        takeoff_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let best_flight = sqlx::query_as!(
+        BestFlight,
+        r#"
+            SELECT id, date, duration_min, distance_km
+            FROM flights
+            WHERE takeoff_id = $1
+            ORDER BY distance_km DESC NULLS LAST, duration_min DESC, date, id
+            LIMIT 1
+        "#,
+        takeoff_id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(FlightStats {
+        takeoff_id,
+        flights: record.flights,
+        pilots: record.pilots,
+        average_duration_min: record.average_duration_min,
+        best_flight,
+    })
+}
+
+/// Get a flight of a user.
+pub async fn get_flight<'a, E>(
+    executor: E,
+    id: i32,
+    user_id: i32,
+) -> Result<Option<Flight>, sqlx::Error>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    sqlx::query_as!(
+        Flight,
+        r#"SELECT * FROM flights WHERE id = $1 AND user_id = $2"#,
+        id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// Replace the track of a flight, and update the flight from it.
+///
+/// The date, duration and distance of the flight are replaced with those of the track, and
+/// its takeoff with the takeoff of the track if it has one.
+///
+/// Returns the updated flight.
+pub async fn replace_flight_track(
+    conn: &mut PgConnection,
+    track: &FlightTrack,
+    igc: &str,
+) -> Result<Flight, sqlx::Error> {
+    sqlx::query!(
+        r#"
+            INSERT INTO flight_tracks (
+                flight_id, igc, pilot, glider, signed, points, started_at, landed_at, duration_s,
+                max_altitude_m, distance_km, track_length_km, max_climb_ms, launch_latitude,
+                launch_longitude, takeoff_id, takeoff_distance_m
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
+            ON CONFLICT (flight_id) DO UPDATE SET
+                igc = EXCLUDED.igc,
+                pilot = EXCLUDED.pilot,
+                glider = EXCLUDED.glider,
+                signed = EXCLUDED.signed,
+                points = EXCLUDED.points,
+                started_at = EXCLUDED.started_at,
+                landed_at = EXCLUDED.landed_at,
+                duration_s = EXCLUDED.duration_s,
+                max_altitude_m = EXCLUDED.max_altitude_m,
+                distance_km = EXCLUDED.distance_km,
+                track_length_km = EXCLUDED.track_length_km,
+                max_climb_ms = EXCLUDED.max_climb_ms,
+                launch_latitude = EXCLUDED.launch_latitude,
+                launch_longitude = EXCLUDED.launch_longitude,
+                takeoff_id = EXCLUDED.takeoff_id,
+                takeoff_distance_m = EXCLUDED.takeoff_distance_m,
+                uploaded_at = now()
+        "#,
+        track.flight_id,
+        igc,
+        track.pilot,
+        track.glider,
+        track.signed,
+        track.points,
+        track.started_at,
+        track.landed_at,
+        track.duration_s,
+        track.max_altitude_m,
+        track.distance_km,
+        track.track_length_km,
+        track.max_climb_ms,
+        track.launch_latitude,
+        track.launch_longitude,
+        track.takeoff_id,
+        track.takeoff_distance_m
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query_as!(
+        Flight,
+        r#"
+            UPDATE flights SET
+                takeoff_id = COALESCE($2, takeoff_id),
+                date = ($3::TIMESTAMPTZ AT TIME ZONE 'UTC')::DATE,
+                duration_min = GREATEST(round($4::INTEGER / 60.0)::INTEGER, 1),
+                distance_km = $5,
+                updated_at = now()
+            WHERE id = $1
+            RETURNING *
+        "#,
+        track.flight_id,
+        track.takeoff_id,
+        track.started_at,
+        track.duration_s,
+        track.distance_km
+    )
+    .fetch_one(&mut *conn)
+    .await
+}
@@ -0,0 +1,5 @@
+/* Takeoff sources */
+
+ALTER TABLE "takeoffs" ADD COLUMN IF NOT EXISTS "source" TEXT;
+
+UPDATE "takeoffs" SET "source" = 'flightlog' WHERE "source_url" LIKE 'https://flightlog.org/%';
@@ -0,0 +1,3 @@
+/* Content hash of the last inserted takeoff data, for detecting upstream changes */
+
+ALTER TABLE "takeoffs" ADD COLUMN IF NOT EXISTS "content_hash" TEXT;
@@ -0,0 +1,11 @@
+/* Scrape jobs, a checkpoint of scraped takeoffs for resuming and retrying */
+
+CREATE TABLE IF NOT EXISTS "scrape_jobs" (
+    "source"        TEXT NOT NULL,
+    "source_id"     TEXT NOT NULL,
+    "status"        TEXT NOT NULL DEFAULT 'pending' CHECK ("status" IN ('pending', 'done', 'failed')),
+    "attempts"      INTEGER NOT NULL DEFAULT 0,
+    "last_error"    TEXT,
+    "updated_at"    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY ("source", "source_id")
+);
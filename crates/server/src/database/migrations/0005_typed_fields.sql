@@ -0,0 +1,63 @@
+/* Typed altitudes, and creation and update dates split from their author names */
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'takeoffs' AND column_name = 'altitude') THEN
+        ALTER TABLE "takeoffs" RENAME COLUMN "altitude" TO "altitude_m";
+        ALTER TABLE "takeoffs" RENAME COLUMN "altitude_diff" TO "height_diff_m";
+    END IF;
+END;
+$$;
+
+ALTER TABLE "takeoffs"
+    ADD COLUMN IF NOT EXISTS "created_at"   TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS "created_by"   TEXT,
+    ADD COLUMN IF NOT EXISTS "updated_at"   TIMESTAMPTZ,
+    ADD COLUMN IF NOT EXISTS "updated_by"   TEXT;
+
+/* Backfill from text like `2008-05-11 Ola Nordmann`, `11.05.2008 12:30 by Ola Nordmann` or `Ola Nordmann` */
+
+CREATE FUNCTION pg_temp.parse_date(text TEXT) RETURNS TIMESTAMPTZ AS $$
+DECLARE
+    iso TEXT := substring(text FROM '^\s*(\d{4}-\d{1,2}-\d{1,2}(?:[ T]\d{1,2}:\d{2}(?::\d{2})?)?)');
+    dmy TEXT := substring(text FROM '^\s*(\d{1,2}\.\d{1,2}\.\d{4}(?: \d{1,2}:\d{2}(?::\d{2})?)?)');
+BEGIN
+    IF iso IS NOT NULL THEN
+        RETURN iso::TIMESTAMP AT TIME ZONE 'UTC';
+    ELSIF dmy IS NOT NULL THEN
+        RETURN to_timestamp(dmy, 'DD.MM.YYYY HH24:MI:SS')::TIMESTAMP AT TIME ZONE 'UTC';
+    END IF;
+    RETURN NULL;
+EXCEPTION WHEN OTHERS THEN
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION pg_temp.parse_author(text TEXT) RETURNS TEXT AS $$
+    SELECT NULLIF(btrim(regexp_replace(
+        text,
+        '^\s*(\d{4}-\d{1,2}-\d{1,2}|\d{1,2}\.\d{1,2}\.\d{4})([ T]\d{1,2}:\d{2}(:\d{2})?)?\s*(by\s+)?',
+        '',
+        'i'
+    )), '');
+$$ LANGUAGE sql;
+
+DO $$
+BEGIN
+    IF EXISTS (SELECT 1 FROM information_schema.columns WHERE table_name = 'takeoffs' AND column_name = 'created') THEN
+        UPDATE "takeoffs" SET
+            "created_at" = pg_temp.parse_date("created"),
+            "created_by" = pg_temp.parse_author("created"),
+            "updated_at" = pg_temp.parse_date("updated"),
+            "updated_by" = pg_temp.parse_author("updated");
+
+        ALTER TABLE "takeoffs" DROP COLUMN "created", DROP COLUMN "updated";
+
+        /* Content hashes were of the old fields */
+        UPDATE "takeoffs" SET "content_hash" = NULL;
+    END IF;
+END;
+$$;
+
+CREATE INDEX IF NOT EXISTS "takeoffs_created_at_idx" ON "takeoffs" ("created_at");
+CREATE INDEX IF NOT EXISTS "takeoffs_updated_at_idx" ON "takeoffs" ("updated_at");
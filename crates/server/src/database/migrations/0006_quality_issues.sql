@@ -0,0 +1,13 @@
+/* Quality issues, found by validating takeoffs */
+
+CREATE TABLE IF NOT EXISTS "quality_issues" (
+    "id"            SERIAL PRIMARY KEY,
+    "takeoff_id"    INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "rule"          TEXT NOT NULL,
+    "severity"      TEXT NOT NULL CHECK ("severity" IN ('error', 'warning')),
+    "message"       TEXT NOT NULL,
+    "related_id"    INTEGER REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "created_at"    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS "quality_issues_takeoff_id" ON "quality_issues"("takeoff_id");
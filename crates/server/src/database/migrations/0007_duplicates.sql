@@ -0,0 +1,11 @@
+/* Source URLs of takeoffs merged into another takeoff, and pairs of takeoffs that aren't duplicates */
+
+ALTER TABLE "takeoffs" ADD COLUMN IF NOT EXISTS "merged_source_urls" TEXT[] NOT NULL DEFAULT '{}';
+
+CREATE TABLE IF NOT EXISTS "duplicate_dismissals" (
+    "takeoff_a"     INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "takeoff_b"     INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "created_at"    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY ("takeoff_a", "takeoff_b"),
+    CHECK ("takeoff_a" < "takeoff_b")
+);
@@ -0,0 +1,13 @@
+/* Takeoff revisions, a snapshot of every created, updated and deleted takeoff */
+
+CREATE TABLE IF NOT EXISTS "takeoff_revisions" (
+    "id"            SERIAL PRIMARY KEY,
+    "takeoff_id"    INTEGER NOT NULL,
+    "action"        TEXT NOT NULL CHECK ("action" IN ('create', 'update', 'delete')),
+    "snapshot"      JSONB NOT NULL,
+    "user_id"       INTEGER REFERENCES "users"("id") ON DELETE SET NULL,
+    "comment"       TEXT,
+    "created_at"    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS "takeoff_revisions_takeoff_id" ON "takeoff_revisions"("takeoff_id");
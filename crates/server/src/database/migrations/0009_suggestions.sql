@@ -0,0 +1,16 @@
+/* Suggested changes to takeoffs, reviewed by moderators */
+
+CREATE TABLE IF NOT EXISTS "suggestions" (
+    "id"                SERIAL PRIMARY KEY,
+    "takeoff_id"        INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "user_id"           INTEGER NOT NULL REFERENCES "users"("id") ON DELETE CASCADE,
+    "changes"           JSONB NOT NULL,
+    "comment"           TEXT,
+    "status"            TEXT NOT NULL DEFAULT 'pending' CHECK ("status" IN ('pending', 'approved', 'rejected')),
+    "reviewer_id"       INTEGER REFERENCES "users"("id") ON DELETE SET NULL,
+    "review_comment"    TEXT,
+    "created_at"        TIMESTAMPTZ NOT NULL DEFAULT now(),
+    "reviewed_at"       TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS "suggestions_status" ON "suggestions"("status");
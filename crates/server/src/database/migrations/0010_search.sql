@@ -0,0 +1,34 @@
+/* Full-text and trigram search of takeoffs */
+
+CREATE EXTENSION IF NOT EXISTS "unaccent";
+CREATE EXTENSION IF NOT EXISTS "pg_trgm";
+
+/* Norwegian stemming of unaccented words, so e.g. "hoyanger" matches "Høyanger" */
+DO $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM pg_ts_config WHERE cfgname = 'norwegian_unaccent') THEN
+        CREATE TEXT SEARCH CONFIGURATION "norwegian_unaccent" (COPY = norwegian);
+        ALTER TEXT SEARCH CONFIGURATION "norwegian_unaccent"
+            ALTER MAPPING FOR hword, hword_part, word WITH unaccent, norwegian_stem;
+    END IF;
+END
+$$;
+
+/* `unaccent` is only stable, as its dictionary can change, but indexes need immutable functions */
+CREATE OR REPLACE FUNCTION "immutable_unaccent"(TEXT) RETURNS TEXT
+    LANGUAGE sql IMMUTABLE PARALLEL SAFE STRICT
+    AS $$ SELECT public.unaccent('public.unaccent'::regdictionary, $1) $$;
+
+/* Search document of a takeoff, weighted by name, region and description */
+CREATE OR REPLACE FUNCTION "takeoff_document"(TEXT, TEXT, TEXT) RETURNS tsvector
+    LANGUAGE sql IMMUTABLE PARALLEL SAFE
+    AS $$
+        SELECT setweight(to_tsvector('norwegian_unaccent', coalesce($1, '')), 'A')
+            || setweight(to_tsvector('norwegian_unaccent', coalesce($2, '')), 'B')
+            || setweight(to_tsvector('norwegian_unaccent', coalesce($3, '')), 'C')
+    $$;
+
+CREATE INDEX IF NOT EXISTS "takeoffs_document" ON "takeoffs"
+    USING gin (takeoff_document("name", "region", "description"));
+CREATE INDEX IF NOT EXISTS "takeoffs_name_trgm" ON "takeoffs"
+    USING gin (immutable_unaccent(lower("name")) gin_trgm_ops);
@@ -0,0 +1,12 @@
+/* Transaction ids of revisions, used as a cursor for syncing changes */
+
+ALTER TABLE "takeoff_revisions"
+    ADD COLUMN IF NOT EXISTS "txid" BIGINT NOT NULL DEFAULT pg_current_xact_id()::TEXT::BIGINT;
+
+CREATE INDEX IF NOT EXISTS "takeoff_revisions_txid" ON "takeoff_revisions"("txid");
+
+/* Takeoffs from before revisions were saved */
+INSERT INTO "takeoff_revisions"("takeoff_id", "action", "snapshot", "comment")
+SELECT t."id", 'create', to_jsonb(t) - 'image' - 'content_hash', 'Initial revision'
+FROM "takeoffs" t
+WHERE NOT EXISTS (SELECT 1 FROM "takeoff_revisions" r WHERE r."takeoff_id" = t."id");
@@ -0,0 +1,3 @@
+/* Coordinates index, for takeoffs within the bounds of map tiles */
+
+CREATE INDEX IF NOT EXISTS "takeoffs_latitude_longitude" ON "takeoffs"("latitude", "longitude");
@@ -0,0 +1,18 @@
+/* Cached hourly forecasts of takeoffs, replaced on every fetch */
+
+CREATE TABLE IF NOT EXISTS "forecasts" (
+    "takeoff_id" INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "provider" TEXT NOT NULL,
+    "time" TIMESTAMPTZ NOT NULL,
+    "wind_speed_ms" DOUBLE PRECISION NOT NULL,
+    "wind_direction_deg" DOUBLE PRECISION NOT NULL,
+    "wind_gusts_ms" DOUBLE PRECISION,
+    PRIMARY KEY ("takeoff_id", "provider", "time")
+);
+
+CREATE TABLE IF NOT EXISTS "forecast_fetches" (
+    "takeoff_id" INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "provider" TEXT NOT NULL,
+    "fetched_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY ("takeoff_id", "provider")
+);
@@ -0,0 +1,4 @@
+/* Precipitation and cloud base of forecasts, for flyability */
+
+ALTER TABLE "forecasts" ADD COLUMN IF NOT EXISTS "precipitation_mm" DOUBLE PRECISION;
+ALTER TABLE "forecasts" ADD COLUMN IF NOT EXISTS "cloud_base_m" DOUBLE PRECISION;
@@ -0,0 +1,34 @@
+/* Live weather stations, their wind readings and the stations nearest to each takeoff */
+
+CREATE TABLE IF NOT EXISTS "stations" (
+    "id" SERIAL PRIMARY KEY,
+    "provider" TEXT NOT NULL,
+    "provider_id" TEXT NOT NULL,
+    "name" TEXT NOT NULL,
+    "latitude" DOUBLE PRECISION NOT NULL,
+    "longitude" DOUBLE PRECISION NOT NULL,
+    "altitude_m" INTEGER,
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    UNIQUE ("provider", "provider_id")
+);
+
+CREATE TABLE IF NOT EXISTS "station_readings" (
+    "station_id" INTEGER NOT NULL REFERENCES "stations"("id") ON DELETE CASCADE,
+    "time" TIMESTAMPTZ NOT NULL,
+    "wind_speed_ms" DOUBLE PRECISION,
+    "wind_direction_deg" DOUBLE PRECISION,
+    "wind_gusts_ms" DOUBLE PRECISION,
+    "temperature_c" DOUBLE PRECISION,
+    PRIMARY KEY ("station_id", "time")
+);
+
+CREATE INDEX IF NOT EXISTS "station_readings_time_idx" ON "station_readings" ("time");
+
+CREATE TABLE IF NOT EXISTS "takeoff_stations" (
+    "takeoff_id" INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "station_id" INTEGER NOT NULL REFERENCES "stations"("id") ON DELETE CASCADE,
+    "distance_m" DOUBLE PRECISION NOT NULL,
+    PRIMARY KEY ("takeoff_id", "station_id")
+);
+
+CREATE INDEX IF NOT EXISTS "takeoff_stations_station_idx" ON "takeoff_stations" ("station_id");
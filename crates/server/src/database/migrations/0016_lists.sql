@@ -0,0 +1,26 @@
+/* Favourite takeoffs of users, and named lists of takeoffs that can be shared read-only */
+
+CREATE TABLE IF NOT EXISTS "favorites" (
+    "user_id" INTEGER NOT NULL REFERENCES "users"("id") ON DELETE CASCADE,
+    "takeoff_id" INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY ("user_id", "takeoff_id")
+);
+
+CREATE TABLE IF NOT EXISTS "takeoff_lists" (
+    "id" SERIAL PRIMARY KEY,
+    "user_id" INTEGER NOT NULL REFERENCES "users"("id") ON DELETE CASCADE,
+    "name" TEXT NOT NULL,
+    "share_token" TEXT UNIQUE,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS "takeoff_lists_user_idx" ON "takeoff_lists" ("user_id");
+
+CREATE TABLE IF NOT EXISTS "takeoff_list_items" (
+    "list_id" INTEGER NOT NULL REFERENCES "takeoff_lists"("id") ON DELETE CASCADE,
+    "takeoff_id" INTEGER NOT NULL REFERENCES "takeoffs"("id") ON DELETE CASCADE,
+    "added_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY ("list_id", "takeoff_id")
+);
@@ -0,0 +1,18 @@
+/* Flights logged by users, kept if their takeoff is deleted */
+
+CREATE TABLE IF NOT EXISTS "flights" (
+    "id" SERIAL PRIMARY KEY,
+    "user_id" INTEGER NOT NULL REFERENCES "users"("id") ON DELETE CASCADE,
+    "takeoff_id" INTEGER REFERENCES "takeoffs"("id") ON DELETE SET NULL,
+    "date" DATE NOT NULL,
+    "duration_min" INTEGER NOT NULL CHECK ("duration_min" > 0),
+    "distance_km" DOUBLE PRECISION CHECK ("distance_km" >= 0),
+    "landing" TEXT,
+    "notes" TEXT,
+    "conditions" TEXT,
+    "created_at" TIMESTAMPTZ NOT NULL DEFAULT now(),
+    "updated_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS "flights_user_idx" ON "flights" ("user_id", "date");
+CREATE INDEX IF NOT EXISTS "flights_takeoff_idx" ON "flights" ("takeoff_id");
@@ -0,0 +1,22 @@
+/* IGC tracks of flights, with statistics and the takeoff matched to the launch */
+
+CREATE TABLE IF NOT EXISTS "flight_tracks" (
+    "flight_id" INTEGER PRIMARY KEY REFERENCES "flights"("id") ON DELETE CASCADE,
+    "igc" TEXT NOT NULL,
+    "pilot" TEXT,
+    "glider" TEXT,
+    "signed" BOOLEAN NOT NULL,
+    "points" INTEGER NOT NULL,
+    "started_at" TIMESTAMPTZ NOT NULL,
+    "landed_at" TIMESTAMPTZ NOT NULL,
+    "duration_s" INTEGER NOT NULL,
+    "max_altitude_m" INTEGER NOT NULL,
+    "distance_km" DOUBLE PRECISION NOT NULL,
+    "track_length_km" DOUBLE PRECISION NOT NULL,
+    "max_climb_ms" DOUBLE PRECISION NOT NULL,
+    "launch_latitude" DOUBLE PRECISION NOT NULL,
+    "launch_longitude" DOUBLE PRECISION NOT NULL,
+    "takeoff_id" INTEGER REFERENCES "takeoffs"("id") ON DELETE SET NULL,
+    "takeoff_distance_m" DOUBLE PRECISION,
+    "uploaded_at" TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -1,3 +1,4 @@
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 
@@ -16,7 +17,7 @@ pub struct Data<T> {
 ///
 /// * Use [`NewTakeoff`] for creating a new takeoff.
 /// * Use [`GetTakeoff`] for optional fields.
-#[derive(Debug, Serialize, FromRow)]
+#[derive(Debug, Deserialize, Serialize, FromRow)]
 pub struct Takeoff {
     /// Incrementing ID.
     pub id: i32,
@@ -24,14 +25,15 @@ pub struct Takeoff {
     pub name: String,
     /// Description.
     pub description: String,
-    /// Optional image.
+    /// Optional image, not in [`Revision`] snapshots.
+    #[serde(default)]
     pub image: Option<Vec<u8>>,
     /// Region.
     pub region: String,
-    /// Optional meters over sea level
-    pub altitude: Option<i32>,
-    /// Optional difference between takeoff and landing in altitude.
-    pub altitude_diff: Option<i32>,
+    /// Optional meters above sea level.
+    pub altitude_m: Option<i32>,
+    /// Optional meters from takeoff to landing.
+    pub height_diff_m: Option<i32>,
     /// Latitude coordinate.
     pub latitude: f64,
     /// Longitude coordinate.
@@ -40,18 +42,27 @@ pub struct Takeoff {
     pub wind_dirs: Vec<String>,
     /// Optional info URL.
     pub info_url: Option<String>,
+    /// Optional name of the source (e.g. `flightlog`).
+    pub source: Option<String>,
     /// Optional source URL.
     pub source_url: Option<String>,
-    /// Creation date and author name.
-    pub created: String,
-    /// Last update date and author name.
-    pub updated: String,
+    /// Optional creation date.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Optional author name.
+    pub created_by: Option<String>,
+    /// Optional last update date.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Optional name of the last updater.
+    pub updated_by: Option<String>,
+    /// Source URLs of takeoffs merged into this one.
+    #[serde(default)]
+    pub merged_source_urls: Vec<String>,
 }
 
 /// New takeoff model.
 ///
 /// Used for creating new takeoffs.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct NewTakeoff {
     /// String.
     pub name: String,
@@ -61,10 +72,10 @@ pub struct NewTakeoff {
     pub image: Option<Vec<u8>>,
     /// Region.
     pub region: String,
-    // Optional meters over sea level
-    pub altitude: Option<i32>,
-    /// Optional difference between takeoff and landing in altitude.
-    pub altitude_diff: Option<i32>,
+    /// Optional meters above sea level.
+    pub altitude_m: Option<i32>,
+    /// Optional meters from takeoff to landing.
+    pub height_diff_m: Option<i32>,
     /// Latitude coordinate.
     pub latitude: f64,
     /// Longitude coordinate.
@@ -73,12 +84,19 @@ pub struct NewTakeoff {
     pub wind_dirs: Vec<String>,
     /// Optional info URL.
     pub info_url: Option<String>,
+    /// Optional name of the source (e.g. `flightlog`).
+    #[serde(default)]
+    pub source: Option<String>,
     /// Optional source URL.
     pub source_url: Option<String>,
-    /// Creation date and author name.
-    pub created: String,
-    /// Last update date and author name.
-    pub updated: String,
+    /// Optional creation date.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Optional author name.
+    pub created_by: Option<String>,
+    /// Optional last update date.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Optional name of the last updater.
+    pub updated_by: Option<String>,
 }
 
 /// Get takeoff model.
@@ -97,10 +115,10 @@ pub struct GetTakeoff {
     pub image: Option<Vec<u8>>,
     /// Region.
     pub region: Option<String>,
-    // Optional meters over sea level
-    pub altitude: Option<i32>,
-    /// Optional difference between takeoff and landing in altitude.
-    pub altitude_diff: Option<i32>,
+    /// Optional meters above sea level.
+    pub altitude_m: Option<i32>,
+    /// Optional meters from takeoff to landing.
+    pub height_diff_m: Option<i32>,
     /// Latitude coordinate.
     pub latitude: Option<f64>,
     /// Longitude coordinate.
@@ -109,12 +127,564 @@ pub struct GetTakeoff {
     pub wind_dirs: Option<Vec<String>>,
     /// Optional info URL.
     pub info_url: Option<String>,
+    /// Optional name of the source (e.g. `flightlog`).
+    pub source: Option<String>,
     /// Optional source URL.
     pub source_url: Option<String>,
-    /// Creation date and author name.
-    pub created: Option<String>,
-    /// Last update date and author name.
-    pub updated: Option<String>,
+    /// Creation date.
+    pub created_at: Option<DateTime<Utc>>,
+    /// Author name.
+    pub created_by: Option<String>,
+    /// Last update date.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Name of the last updater.
+    pub updated_by: Option<String>,
+    /// Source URLs of takeoffs merged into this one.
+    pub merged_source_urls: Option<Vec<String>>,
+}
+
+impl From<NewTakeoff> for GetTakeoff {
+    fn from(takeoff: NewTakeoff) -> Self {
+        Self {
+            id: None,
+            name: Some(takeoff.name),
+            description: Some(takeoff.description),
+            image: takeoff.image,
+            region: Some(takeoff.region),
+            altitude_m: takeoff.altitude_m,
+            height_diff_m: takeoff.height_diff_m,
+            latitude: Some(takeoff.latitude),
+            longitude: Some(takeoff.longitude),
+            wind_dirs: Some(takeoff.wind_dirs),
+            info_url: takeoff.info_url,
+            source: takeoff.source,
+            source_url: takeoff.source_url,
+            created_at: takeoff.created_at,
+            created_by: takeoff.created_by,
+            updated_at: takeoff.updated_at,
+            updated_by: takeoff.updated_by,
+            merged_source_urls: None,
+        }
+    }
+}
+
+/// Author and comment of a change to a takeoff, saved with its [`Revision`].
+#[derive(Debug, Default, Clone)]
+pub struct ChangeNote {
+    /// Optional id of the user making the change.
+    pub user_id: Option<i32>,
+    /// Optional comment.
+    pub comment: Option<String>,
+}
+
+impl ChangeNote {
+    /// Create a note with a comment and no user.
+    pub fn comment(comment: impl Into<String>) -> Self {
+        Self {
+            user_id: None,
+            comment: Some(comment.into()),
+        }
+    }
+}
+
+/// Takeoff revision model.
+///
+/// Saved for every created, updated and deleted takeoff.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Revision {
+    /// Incrementing ID.
+    pub id: i32,
+    /// Takeoff id, the takeoff might be deleted.
+    pub takeoff_id: i32,
+    /// `create`, `update` or `delete`.
+    pub action: String,
+    /// The takeoff after the change (or before deletion) as a [`Takeoff`], without the image.
+    pub snapshot: serde_json::Value,
+    /// Optional id of the user who made the change.
+    pub user_id: Option<i32>,
+    /// Optional name of the user who made the change.
+    pub username: Option<String>,
+    /// Optional comment.
+    pub comment: Option<String>,
+    /// When the change was made.
+    pub created_at: DateTime<Utc>,
+}
+
+/// Suggested changes to a takeoff.
+///
+/// Missing fields are left unchanged.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct TakeoffChanges {
+    /// New name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// New description.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// New region.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    /// New meters above sea level.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub altitude_m: Option<i32>,
+    /// New meters from takeoff to landing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height_diff_m: Option<i32>,
+    /// New latitude coordinate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latitude: Option<f64>,
+    /// New longitude coordinate.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longitude: Option<f64>,
+    /// New wind directions.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wind_dirs: Option<Vec<String>>,
+    /// New info URL.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub info_url: Option<String>,
+}
+
+impl TakeoffChanges {
+    /// Get the changes as a JSON object of the changed fields.
+    pub fn to_map(&self) -> serde_json::Map<String, serde_json::Value> {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        }
+    }
+
+    /// Apply the changes to a takeoff.
+    pub fn apply(self, takeoff: &mut Takeoff) {
+        if let Some(name) = self.name {
+            takeoff.name = name;
+        }
+        if let Some(description) = self.description {
+            takeoff.description = description;
+        }
+        if let Some(region) = self.region {
+            takeoff.region = region;
+        }
+        if let Some(altitude_m) = self.altitude_m {
+            takeoff.altitude_m = Some(altitude_m);
+        }
+        if let Some(height_diff_m) = self.height_diff_m {
+            takeoff.height_diff_m = Some(height_diff_m);
+        }
+        if let Some(latitude) = self.latitude {
+            takeoff.latitude = latitude;
+        }
+        if let Some(longitude) = self.longitude {
+            takeoff.longitude = longitude;
+        }
+        if let Some(wind_dirs) = self.wind_dirs {
+            takeoff.wind_dirs = wind_dirs;
+        }
+        if let Some(info_url) = self.info_url {
+            takeoff.info_url = Some(info_url);
+        }
+    }
+}
+
+/// Status of a suggestion.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SuggestionStatus {
+    /// Not reviewed yet.
+    Pending,
+    /// Applied to the takeoff.
+    Approved,
+    /// Not applied.
+    Rejected,
+}
+
+impl SuggestionStatus {
+    /// Get the status as stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuggestionStatus::Pending => "pending",
+            SuggestionStatus::Approved => "approved",
+            SuggestionStatus::Rejected => "rejected",
+        }
+    }
+}
+
+/// Suggestion model.
+///
+/// Suggested [`TakeoffChanges`] by a user, reviewed by a moderator.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Suggestion {
+    /// Incrementing ID.
+    pub id: i32,
+    /// Takeoff id.
+    pub takeoff_id: i32,
+    /// Id of the user who made the suggestion.
+    pub user_id: i32,
+    /// Optional name of the user who made the suggestion.
+    pub username: Option<String>,
+    /// [`TakeoffChanges`] as JSON.
+    pub changes: serde_json::Value,
+    /// Optional comment.
+    pub comment: Option<String>,
+    /// `pending`, `approved` or `rejected`.
+    pub status: String,
+    /// Optional id of the reviewer.
+    pub reviewer_id: Option<i32>,
+    /// Optional comment of the reviewer.
+    pub review_comment: Option<String>,
+    /// When the suggestion was made.
+    pub created_at: DateTime<Utc>,
+    /// When the suggestion was reviewed.
+    pub reviewed_at: Option<DateTime<Utc>>,
+}
+
+/// Forecast hour model.
+///
+/// Used for weather forecasts of takeoffs, see [`crate::forecast`].
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ForecastHour {
+    /// Start of the hour.
+    pub time: DateTime<Utc>,
+    /// Mean wind speed 10 m above ground, in meters per second.
+    pub wind_speed_ms: f64,
+    /// Direction the wind comes from, in degrees clockwise from north.
+    pub wind_direction_deg: f64,
+    /// Optional wind gusts 10 m above ground, in meters per second.
+    pub wind_gusts_ms: Option<f64>,
+    /// Optional precipitation during the hour, in millimeters.
+    pub precipitation_mm: Option<f64>,
+    /// Optional height of the cloud base above ground, in meters.
+    pub cloud_base_m: Option<f64>,
+}
+
+/// Forecast model.
+#[derive(Debug, Serialize)]
+pub struct Forecast {
+    /// Takeoff id.
+    pub takeoff_id: i32,
+    /// Name of the forecast provider (e.g. `open-meteo`).
+    pub provider: String,
+    /// When the forecast was fetched.
+    pub fetched_at: DateTime<Utc>,
+    /// Hours from the current hour on.
+    pub hours: Vec<ForecastHour>,
+}
+
+/// Weather station model.
+///
+/// Used for live wind readings near takeoffs, see [`crate::stations`].
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Station {
+    /// Incrementing ID.
+    pub id: i32,
+    /// Name of the station provider (e.g. `holfuy`).
+    pub provider: String,
+    /// ID of the station at the provider.
+    pub provider_id: String,
+    /// Name.
+    pub name: String,
+    /// Latitude coordinate.
+    pub latitude: f64,
+    /// Longitude coordinate.
+    pub longitude: f64,
+    /// Optional altitude in meters.
+    pub altitude_m: Option<i32>,
+}
+
+/// New weather station model.
+///
+/// Used for stations listed by a provider.
+#[derive(Debug, Clone)]
+pub struct NewStation {
+    /// ID of the station at the provider.
+    pub provider_id: String,
+    /// Name.
+    pub name: String,
+    /// Latitude coordinate.
+    pub latitude: f64,
+    /// Longitude coordinate.
+    pub longitude: f64,
+    /// Optional altitude in meters.
+    pub altitude_m: Option<i32>,
+}
+
+/// Station reading model.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct StationReading {
+    /// When the station measured it.
+    pub time: DateTime<Utc>,
+    /// Optional mean wind speed, in meters per second.
+    pub wind_speed_ms: Option<f64>,
+    /// Optional direction the wind comes from, in degrees clockwise from north.
+    pub wind_direction_deg: Option<f64>,
+    /// Optional wind gusts, in meters per second.
+    pub wind_gusts_ms: Option<f64>,
+    /// Optional air temperature, in degrees Celsius.
+    pub temperature_c: Option<f64>,
+}
+
+/// Takeoff station model.
+///
+/// Used for the stations nearest to a takeoff, with their recent readings.
+#[derive(Debug, Serialize)]
+pub struct TakeoffStation {
+    /// The station, with its fields at the top level.
+    #[serde(flatten)]
+    pub station: Station,
+    /// Distance from the takeoff in kilometers.
+    pub distance_km: f64,
+    /// Optional latest reading.
+    pub current: Option<StationReading>,
+    /// Recent readings, latest first.
+    pub readings: Vec<StationReading>,
+}
+
+/// Takeoff list model.
+///
+/// Used for named lists of takeoffs of a user.
+#[derive(Debug, Serialize)]
+pub struct TakeoffList {
+    /// Incrementing ID.
+    pub id: i32,
+    /// Name (e.g. `Home sites`).
+    pub name: String,
+    /// Optional token of the public read-only link, if the list is shared.
+    pub share_token: Option<String>,
+    /// Creation date.
+    pub created_at: DateTime<Utc>,
+    /// Date of the last change.
+    pub updated_at: DateTime<Utc>,
+    /// Ids of the takeoffs, in the order they were added.
+    pub takeoff_ids: Vec<i32>,
+}
+
+/// Shared takeoff list model.
+///
+/// Used for the public read-only view of a list.
+#[derive(Debug, Serialize)]
+pub struct SharedTakeoffList {
+    /// Name.
+    pub name: String,
+    /// Date of the last change.
+    pub updated_at: DateTime<Utc>,
+    /// Takeoffs, in the order they were added.
+    pub takeoffs: Vec<GetTakeoff>,
+}
+
+/// Flight model.
+///
+/// * Use [`NewFlight`] for logging a flight.
+#[derive(Debug, Serialize, FromRow)]
+pub struct Flight {
+    /// Incrementing ID.
+    pub id: i32,
+    /// Id of the pilot.
+    pub user_id: i32,
+    /// Takeoff id, missing if the takeoff was deleted.
+    pub takeoff_id: Option<i32>,
+    /// Day of the flight.
+    pub date: NaiveDate,
+    /// Duration in minutes.
+    pub duration_min: i32,
+    /// Optional distance in kilometers.
+    pub distance_km: Option<f64>,
+    /// Optional landing (e.g. a place name).
+    pub landing: Option<String>,
+    /// Optional notes of the pilot.
+    pub notes: Option<String>,
+    /// Optional description of the conditions (e.g. `thermic, 3 m/s W`).
+    pub conditions: Option<String>,
+    /// Creation date.
+    pub created_at: DateTime<Utc>,
+    /// Date of the last change.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// New flight model.
+///
+/// Used for logging and editing flights.
+#[derive(Debug, Deserialize)]
+pub struct NewFlight {
+    /// Takeoff id.
+    pub takeoff_id: i32,
+    /// Day of the flight.
+    pub date: NaiveDate,
+    /// Duration in minutes.
+    pub duration_min: i32,
+    /// Optional distance in kilometers.
+    pub distance_km: Option<f64>,
+    /// Optional landing (e.g. a place name).
+    pub landing: Option<String>,
+    /// Optional notes of the pilot.
+    pub notes: Option<String>,
+    /// Optional description of the conditions.
+    pub conditions: Option<String>,
+}
+
+/// Flight track model.
+///
+/// Used for the parsed IGC track of a flight, without the file itself.
+#[derive(Debug, Serialize, FromRow)]
+pub struct FlightTrack {
+    /// Flight id.
+    pub flight_id: i32,
+    /// Optional pilot in the header.
+    pub pilot: Option<String>,
+    /// Optional glider type in the header.
+    pub glider: Option<String>,
+    /// If the file has a G-record signature (its format is checked, not the signature).
+    pub signed: bool,
+    /// Number of fixes.
+    pub points: i32,
+    /// Time of the first valid fix.
+    pub started_at: DateTime<Utc>,
+    /// Time of the last valid fix.
+    pub landed_at: DateTime<Utc>,
+    /// Duration in seconds.
+    pub duration_s: i32,
+    /// Highest altitude in meters.
+    pub max_altitude_m: i32,
+    /// Straight distance from launch to landing, in kilometers.
+    pub distance_km: f64,
+    /// Length of the track, in kilometers.
+    pub track_length_km: f64,
+    /// Best climb rate, in meters per second.
+    pub max_climb_ms: f64,
+    /// Latitude of the launch.
+    pub launch_latitude: f64,
+    /// Longitude of the launch.
+    pub launch_longitude: f64,
+    /// Optional id of the takeoff closest to the launch.
+    pub takeoff_id: Option<i32>,
+    /// Optional distance from the launch to the takeoff, in meters.
+    pub takeoff_distance_m: Option<f64>,
+}
+
+/// Flight statistics model.
+///
+/// Used for aggregates of the flights logged from a takeoff.
+#[derive(Debug, Serialize)]
+pub struct FlightStats {
+    /// Takeoff id.
+    pub takeoff_id: i32,
+    /// Number of flights.
+    pub flights: i64,
+    /// Number of pilots.
+    pub pilots: i64,
+    /// Optional average duration in minutes, missing without flights.
+    pub average_duration_min: Option<f64>,
+    /// Optional longest flight by distance, then duration.
+    pub best_flight: Option<BestFlight>,
+}
+
+/// Best flight model.
+///
+/// Used for the best flight from a takeoff, without the pilot and notes.
+#[derive(Debug, Serialize, FromRow)]
+pub struct BestFlight {
+    /// Flight id.
+    pub id: i32,
+    /// Day of the flight.
+    pub date: NaiveDate,
+    /// Duration in minutes.
+    pub duration_min: i32,
+    /// Optional distance in kilometers.
+    pub distance_km: Option<f64>,
+}
+
+/// Search result model.
+///
+/// Used for full-text search of takeoffs.
+#[derive(Debug, Serialize, FromRow)]
+pub struct SearchResult {
+    /// Takeoff id.
+    pub id: i32,
+    /// Name.
+    pub name: String,
+    /// Region.
+    pub region: String,
+    /// Latitude coordinate.
+    pub latitude: f64,
+    /// Longitude coordinate.
+    pub longitude: f64,
+    /// Relevance, higher is better.
+    pub rank: f32,
+    /// Name as HTML, with matches in `<mark>` elements.
+    pub name_highlight: String,
+    /// Parts of the description as HTML, with matches in `<mark>` elements.
+    pub snippet: String,
+}
+
+/// Source version model.
+///
+/// Used for detecting changes to takeoffs in their source.
+#[derive(Debug)]
+pub struct SourceVersion {
+    /// Takeoff id.
+    pub id: i32,
+    /// Source URL.
+    pub source_url: String,
+    /// Optional last update date.
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Optional name of the last updater.
+    pub updated_by: Option<String>,
+    /// Optional hash of the takeoff content, see [`crate::helpers::content_hash`].
+    pub content_hash: Option<String>,
+    /// If a user changed the takeoff since it was last scraped.
+    pub edited: bool,
+}
+
+/// Dataset version model.
+///
+/// Used for HTTP caching of takeoffs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DatasetVersion {
+    /// Number of revisions, increases with every change.
+    pub version: i64,
+    /// Optional date of the last change.
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// Status of a scrape job.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScrapeJobStatus {
+    /// Not scraped yet.
+    Pending,
+    /// Scraped and written.
+    Done,
+    /// Scraping or writing failed.
+    Failed,
+}
+
+impl ScrapeJobStatus {
+    /// Get the status as stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScrapeJobStatus::Pending => "pending",
+            ScrapeJobStatus::Done => "done",
+            ScrapeJobStatus::Failed => "failed",
+        }
+    }
+}
+
+/// Quality issue model.
+///
+/// Issues are found by [`crate::validation`] and replaced on every flagging run.
+#[derive(Debug, Serialize, FromRow)]
+pub struct QualityIssue {
+    /// Incrementing ID.
+    pub id: i32,
+    /// Takeoff id.
+    pub takeoff_id: i32,
+    /// Rule name (e.g. `null_island`).
+    pub rule: String,
+    /// `error` or `warning`.
+    pub severity: String,
+    /// Description of the issue.
+    pub message: String,
+    /// Optional id of the other takeoff for duplicates.
+    pub related_id: Option<i32>,
+    /// When the issue was found.
+    pub created_at: DateTime<Utc>,
 }
 
 /// User model.
@@ -78,6 +78,15 @@ impl ServerError {
         Self::new(error, StatusCode::CONFLICT)
     }
 
+    /// Create `ServerError` with status `502 Bad Gateway`.
+    #[allow(non_snake_case)]
+    pub fn BAD_GATEWAY<E>(error: E) -> Self
+    where
+        E: std::fmt::Display,
+    {
+        Self::new(error, StatusCode::BAD_GATEWAY)
+    }
+
     /// Create `ServerError` with status `511 Network Authentication Required`.
     #[allow(non_snake_case)]
     pub fn NETWORK_AUTHENTICATION_REQUIRED<E>(error: E) -> Self
@@ -0,0 +1,168 @@
+//! Rate forecast hours of takeoffs as flyable, marginal or no-fly.
+//!
+//! Every criterion (wind direction against the wind directions of the takeoff, wind speed,
+//! gusts, precipitation and cloud base) is rated on its own, and an hour gets the worst rating.
+//! Unknown values (e.g. missing gusts) are ignored.
+
+use crate::{formats::wind_dir_degrees, models::ForecastHour};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Serialize;
+
+/// Rating of an hour, worst first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    NoFly,
+    Marginal,
+    Flyable,
+}
+
+/// Thresholds of ratings as `(flyable, marginal)`.
+///
+/// Values beyond the flyable threshold are marginal, and beyond the marginal threshold no-fly.
+#[derive(Debug, Clone, Copy)]
+pub struct Thresholds {
+    /// Wind speed in meters per second.
+    pub wind_speed: (f64, f64),
+    /// Wind gusts in meters per second.
+    pub wind_gusts: (f64, f64),
+    /// Degrees between the wind and the closest wind direction of the takeoff.
+    pub wind_direction: (f64, f64),
+    /// Wind speed in meters per second where the direction doesn't matter.
+    pub calm: f64,
+    /// Precipitation in millimeters per hour.
+    pub precipitation: (f64, f64),
+    /// Minimum cloud base above ground in meters.
+    pub cloud_base: (f64, f64),
+}
+
+impl Default for Thresholds {
+    fn default() -> Self {
+        Self {
+            wind_speed: (6.0, 8.0),
+            wind_gusts: (8.0, 11.0),
+            // Half a sector of the 8 compass points, then a full sector
+            wind_direction: (22.5, 45.0),
+            calm: 1.5,
+            precipitation: (0.1, 0.5),
+            cloud_base: (500.0, 300.0),
+        }
+    }
+}
+
+/// A rated forecast hour.
+#[derive(Debug, Clone, Serialize)]
+pub struct HourRating {
+    #[serde(flatten)]
+    pub hour: ForecastHour,
+    pub rating: Rating,
+    /// Why the hour isn't flyable.
+    pub reasons: Vec<String>,
+}
+
+/// Rate a forecast hour of a takeoff.
+///
+/// # Arguments
+///
+/// * `hour` - A forecast hour.
+/// * `wind_dirs` - Wind directions of the takeoff (e.g. `["N", "NE"]`).
+/// * `thresholds` - Thresholds of ratings.
+pub fn rate_hour(hour: &ForecastHour, wind_dirs: &[String], thresholds: &Thresholds) -> HourRating {
+    // Ratings worse than flyable, with reasons
+    let mut ratings: Vec<(Rating, String)> = Vec::new();
+    let rate = |value: f64, (flyable, marginal): (f64, f64), reason: String| {
+        // Thresholds are upper limits, or lower limits if the flyable one is highest (cloud base)
+        let beyond = |limit: f64| match flyable > marginal {
+            true => value < limit,
+            false => value > limit,
+        };
+        if beyond(marginal) {
+            Some((Rating::NoFly, reason))
+        } else if beyond(flyable) {
+            Some((Rating::Marginal, reason))
+        } else {
+            None
+        }
+    };
+
+    if hour.wind_speed_ms > thresholds.calm {
+        let off = wind_dirs
+            .iter()
+            .filter_map(|dir| wind_dir_degrees(dir))
+            .map(|degrees| angle_between(degrees, hour.wind_direction_deg))
+            .min_by(f64::total_cmp);
+        match off {
+            Some(off) => ratings.extend(rate(
+                off,
+                thresholds.wind_direction,
+                format!("wind is {off:.0}° off the takeoff directions"),
+            )),
+            None => ratings.push((
+                Rating::Marginal,
+                "takeoff has no known wind directions".to_owned(),
+            )),
+        }
+    }
+    ratings.extend(rate(
+        hour.wind_speed_ms,
+        thresholds.wind_speed,
+        format!("wind of {:.1} m/s", hour.wind_speed_ms),
+    ));
+    if let Some(gusts) = hour.wind_gusts_ms {
+        ratings.extend(rate(
+            gusts,
+            thresholds.wind_gusts,
+            format!("gusts of {gusts:.1} m/s"),
+        ));
+    }
+    if let Some(precipitation) = hour.precipitation_mm {
+        ratings.extend(rate(
+            precipitation,
+            thresholds.precipitation,
+            format!("{precipitation:.1} mm of precipitation"),
+        ));
+    }
+    if let Some(cloud_base) = hour.cloud_base_m {
+        ratings.extend(rate(
+            cloud_base,
+            thresholds.cloud_base,
+            format!("cloud base at {cloud_base:.0} m"),
+        ));
+    }
+
+    HourRating {
+        hour: hour.clone(),
+        rating: ratings
+            .iter()
+            .map(|(rating, _)| *rating)
+            .min()
+            .unwrap_or(Rating::Flyable),
+        reasons: ratings.into_iter().map(|(_, reason)| reason).collect(),
+    }
+}
+
+/// Get the smallest angle between two directions in degrees, from 0 to 180.
+fn angle_between(a: f64, b: f64) -> f64 {
+    let diff = (a - b).rem_euclid(360.0);
+    diff.min(360.0 - diff)
+}
+
+/// Get the daylight hours of a date at a longitude, as solar noon ± 6 hours in UTC.
+pub fn daylight(date: NaiveDate, longitude: f64) -> (DateTime<Utc>, DateTime<Utc>) {
+    let noon = date.and_hms_opt(12, 0, 0).unwrap_or_default().and_utc()
+        - Duration::seconds((longitude / 15.0 * 3600.0) as i64);
+
+    (noon - Duration::hours(6), noon + Duration::hours(6))
+}
+
+/// Get the score of rated hours, flyable hours count twice as much as marginal hours.
+pub fn score(hours: &[HourRating]) -> u32 {
+    hours
+        .iter()
+        .map(|hour| match hour.rating {
+            Rating::Flyable => 2,
+            Rating::Marginal => 1,
+            Rating::NoFly => 0,
+        })
+        .sum()
+}
@@ -0,0 +1,153 @@
+//! Weather forecasts of takeoffs.
+//!
+//! Each provider implements [`ForecastProvider`], and is registered in [`NAMES`] and [`create`].
+//! Forecasts are cached in the database, and fetched again when they're older than
+//! [`MAX_AGE_MINUTES`].
+
+pub mod flyability;
+pub mod open_meteo;
+
+use crate::{
+    database::helpers,
+    error::ServerError,
+    models::{Forecast, ForecastHour},
+};
+use anyhow::anyhow;
+use axum::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+/// Names of all providers.
+pub const NAMES: [&str; 1] = [open_meteo::NAME];
+
+/// Minutes before a cached forecast is fetched again.
+pub const MAX_AGE_MINUTES: i64 = 60;
+
+/// Seconds before a request times out.
+const REQUEST_TIMEOUT: u64 = 30;
+
+/// A weather service with hourly wind forecasts.
+#[async_trait]
+pub trait ForecastProvider: Send + Sync {
+    /// Unique name, saved with cached forecasts.
+    fn name(&self) -> &'static str;
+
+    /// Fetch the hourly wind forecast of a coordinate.
+    ///
+    /// # Arguments
+    ///
+    /// * `latitude` - Latitude in decimal degrees.
+    /// * `longitude` - Longitude in decimal degrees.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails or the response is invalid.
+    ///
+    /// # Returns
+    ///
+    /// Forecast hours, earliest first.
+    async fn fetch(
+        &self,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Vec<ForecastHour>, anyhow::Error>;
+}
+
+/// Configuration shared by providers.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Optional URL replacing the URL of the provider (e.g. a local mock server).
+    pub url: Option<String>,
+}
+
+impl Config {
+    /// Read the configuration from environment variables.
+    ///
+    /// * `FORECAST_URL` - Optional URL replacing the URL of the provider.
+    pub fn from_env() -> Self {
+        Self {
+            url: std::env::var("FORECAST_URL").ok(),
+        }
+    }
+}
+
+/// Create a provider.
+///
+/// # Arguments
+///
+/// * `name` - One of [`NAMES`].
+/// * `config` - Configuration for the provider.
+///
+/// # Errors
+///
+/// This function will return an error if the provider is unknown or can't be created.
+pub fn create(name: &str, config: &Config) -> Result<Arc<dyn ForecastProvider>, anyhow::Error> {
+    match name {
+        open_meteo::NAME => Ok(Arc::new(open_meteo::OpenMeteo::new(config)?)),
+        _ => Err(anyhow!(
+            "unknown forecast provider {name}, expected one of: {}",
+            NAMES.join(", ")
+        )),
+    }
+}
+
+/// Create an HTTP client for providers, also used for weather stations.
+pub(crate) fn client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .user_agent(concat!("parastart/", env!("CARGO_PKG_VERSION")))
+        .timeout(std::time::Duration::from_secs(REQUEST_TIMEOUT))
+        .build()
+}
+
+/// Get the forecast of a takeoff, from the cache if it's fresh.
+///
+/// If fetching fails, a stale cached forecast is used instead.
+///
+/// # Errors
+///
+/// This function will return an error if the database query fails, or if fetching fails and
+/// nothing is cached.
+pub async fn get_forecast(
+    pool: &PgPool,
+    provider: &dyn ForecastProvider,
+    takeoff_id: i32,
+    latitude: f64,
+    longitude: f64,
+) -> Result<Forecast, ServerError> {
+    let name = provider.name();
+    let fetched_at = helpers::get_forecast_fetched_at(pool, takeoff_id, name).await?;
+    let fresh = fetched_at
+        .is_some_and(|fetched_at| Utc::now() - fetched_at < Duration::minutes(MAX_AGE_MINUTES));
+
+    let fetched_at = match (fresh, fetched_at) {
+        (true, Some(fetched_at)) => fetched_at,
+        (_, stale) => match provider.fetch(latitude, longitude).await {
+            Ok(hours) => {
+                let mut tx = pool.begin().await?;
+                let fetched_at =
+                    helpers::replace_forecast(&mut tx, takeoff_id, name, &hours).await?;
+                tx.commit().await?;
+                fetched_at
+            }
+            Err(error) => match stale {
+                Some(stale) => {
+                    tracing::warn!("failed fetching forecast of takeoff {takeoff_id} from {name}, using cached: {error:#}");
+                    stale
+                }
+                None => {
+                    return Err(ServerError::BAD_GATEWAY(format!(
+                        "failed fetching forecast from {name}: {error:#}"
+                    )))
+                }
+            },
+        },
+    };
+
+    Ok(Forecast {
+        takeoff_id,
+        provider: name.to_owned(),
+        fetched_at,
+        hours: helpers::get_forecast_hours(pool, takeoff_id, name).await?,
+    })
+}
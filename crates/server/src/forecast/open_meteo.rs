@@ -0,0 +1,223 @@
+//! Forecasts from [Open-Meteo](https://open-meteo.com/en/docs).
+
+use super::{client, Config, ForecastProvider};
+use crate::models::ForecastHour;
+use anyhow::anyhow;
+use axum::async_trait;
+use chrono::DateTime;
+use serde::Deserialize;
+
+/// Name of the provider.
+pub const NAME: &str = "open-meteo";
+
+/// URL of the forecast API.
+pub const DEFAULT_URL: &str = "https://api.open-meteo.com/v1/forecast";
+
+/// Number of days to forecast, including today.
+const FORECAST_DAYS: u32 = 7;
+
+/// Hourly variables to fetch.
+const HOURLY: &str =
+    "wind_speed_10m,wind_direction_10m,wind_gusts_10m,precipitation,temperature_2m,dew_point_2m";
+
+/// Meters the cloud base rises per degree Celsius between temperature and dew point.
+const CLOUD_BASE_PER_DEGREE: f64 = 125.0;
+
+/// Open-Meteo forecast provider.
+pub struct OpenMeteo {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl OpenMeteo {
+    /// Create a provider, using the URL of `config` if any.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the HTTP client can't be built.
+    pub fn new(config: &Config) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            client: client()?,
+            url: config.url.clone().unwrap_or(DEFAULT_URL.to_owned()),
+        })
+    }
+}
+
+/// Forecast response, with the requested hourly variables.
+#[derive(Debug, Deserialize)]
+struct Response {
+    hourly: Hourly,
+}
+
+/// Hourly variables, with an element per hour.
+#[derive(Debug, Deserialize)]
+struct Hourly {
+    /// Unix timestamps.
+    time: Vec<i64>,
+    wind_speed_10m: Vec<Option<f64>>,
+    wind_direction_10m: Vec<Option<f64>>,
+    wind_gusts_10m: Vec<Option<f64>>,
+    /// Optional, hours without it are unknown.
+    #[serde(default)]
+    precipitation: Vec<Option<f64>>,
+    /// Optional, for the cloud base.
+    #[serde(default)]
+    temperature_2m: Vec<Option<f64>>,
+    /// Optional, for the cloud base.
+    #[serde(default)]
+    dew_point_2m: Vec<Option<f64>>,
+}
+
+#[async_trait]
+impl ForecastProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn fetch(
+        &self,
+        latitude: f64,
+        longitude: f64,
+    ) -> Result<Vec<ForecastHour>, anyhow::Error> {
+        let response: Response = self
+            .client
+            .get(&self.url)
+            .query(&[
+                ("latitude", latitude.to_string()),
+                ("longitude", longitude.to_string()),
+                ("hourly", HOURLY.to_owned()),
+                ("wind_speed_unit", "ms".to_owned()),
+                ("timeformat", "unixtime".to_owned()),
+                ("timezone", "UTC".to_owned()),
+                ("forecast_days", FORECAST_DAYS.to_string()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let hourly = response.hourly;
+        let len = hourly.time.len();
+        if [
+            hourly.wind_speed_10m.len(),
+            hourly.wind_direction_10m.len(),
+            hourly.wind_gusts_10m.len(),
+        ]
+        .iter()
+        .any(|other| *other != len)
+        {
+            return Err(anyhow!("hourly variables have different lengths"));
+        }
+
+        // Hours without wind speed or direction are skipped
+        let optional = |values: &[Option<f64>], i: usize| values.get(i).copied().flatten();
+        let hours = (0..len)
+            .filter_map(|i| {
+                // Estimated from the spread between temperature and dew point
+                let cloud_base_m = optional(&hourly.temperature_2m, i)
+                    .zip(optional(&hourly.dew_point_2m, i))
+                    .map(|(temperature, dew_point)| {
+                        ((temperature - dew_point) * CLOUD_BASE_PER_DEGREE).max(0.0)
+                    });
+
+                Some(ForecastHour {
+                    time: DateTime::from_timestamp(hourly.time[i], 0)?,
+                    wind_speed_ms: hourly.wind_speed_10m[i]?,
+                    wind_direction_deg: hourly.wind_direction_10m[i]?,
+                    wind_gusts_ms: hourly.wind_gusts_10m[i],
+                    precipitation_mm: optional(&hourly.precipitation, i),
+                    cloud_base_m,
+                })
+            })
+            .collect();
+
+        Ok(hours)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Provider fetching from a mock server responding to any forecast request.
+    async fn mock(status: usize, body: &str) -> (mockito::ServerGuard, OpenMeteo) {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/v1/forecast")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("latitude".into(), "61.46".into()),
+                mockito::Matcher::UrlEncoded("longitude".into(), "5.88".into()),
+                mockito::Matcher::UrlEncoded("timeformat".into(), "unixtime".into()),
+            ]))
+            .with_status(status)
+            .with_header("content-type", "application/json")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let provider = OpenMeteo::new(&Config {
+            url: Some(format!("{}/v1/forecast", server.url())),
+        })
+        .unwrap();
+
+        (server, provider)
+    }
+
+    #[tokio::test]
+    async fn fetches_hours() {
+        let (_server, provider) = mock(
+            200,
+            include_str!("../../tests/fixtures/open_meteo/forecast.json"),
+        )
+        .await;
+        let hours = provider.fetch(61.46, 5.88).await.unwrap();
+
+        // Hours without wind speed or direction are skipped
+        let times: Vec<i64> = hours.iter().map(|hour| hour.time.timestamp()).collect();
+        assert_eq!(times, [1752537600, 1752541200, 1752552000]);
+
+        assert_eq!(hours[0].wind_speed_ms, 2.4);
+        assert_eq!(hours[0].wind_direction_deg, 265.0);
+        assert_eq!(hours[0].wind_gusts_ms, Some(4.8));
+        assert_eq!(hours[0].precipitation_mm, Some(0.0));
+        assert!((hours[0].cloud_base_m.unwrap() - 762.5).abs() < 1e-6);
+
+        assert_eq!(hours[2].wind_gusts_ms, None);
+        assert_eq!(hours[2].precipitation_mm, Some(1.2));
+        // Dew point above the temperature
+        assert_eq!(hours[2].cloud_base_m, Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn fetches_without_optional_variables() {
+        let body = r#"{"hourly":{"time":[1752537600],"wind_speed_10m":[2.4],"wind_direction_10m":[265],"wind_gusts_10m":[null]}}"#;
+        let (_server, provider) = mock(200, body).await;
+        let hours = provider.fetch(61.46, 5.88).await.unwrap();
+
+        assert_eq!(hours.len(), 1);
+        assert_eq!(hours[0].wind_gusts_ms, None);
+        assert_eq!(hours[0].precipitation_mm, None);
+        assert_eq!(hours[0].cloud_base_m, None);
+    }
+
+    #[tokio::test]
+    async fn rejects_mismatched_lengths() {
+        let body = r#"{"hourly":{"time":[1752537600,1752541200],"wind_speed_10m":[2.4,3.1],"wind_direction_10m":[265],"wind_gusts_10m":[4.8,5.9]}}"#;
+        let (_server, provider) = mock(200, body).await;
+        let error = provider.fetch(61.46, 5.88).await.unwrap_err();
+
+        assert_eq!(error.to_string(), "hourly variables have different lengths");
+    }
+
+    #[tokio::test]
+    async fn rejects_server_errors() {
+        let (_server, provider) = mock(503, r#"{"error":true,"reason":"unavailable"}"#).await;
+        let error = provider.fetch(61.46, 5.88).await.unwrap_err();
+
+        let status = error
+            .downcast_ref::<reqwest::Error>()
+            .and_then(reqwest::Error::status);
+        assert_eq!(status, Some(reqwest::StatusCode::SERVICE_UNAVAILABLE));
+    }
+}
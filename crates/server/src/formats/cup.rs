@@ -0,0 +1,161 @@
+//! SeeYou waypoint (`.cup`) export, as used by flight instruments.
+
+use super::mean_wind_dir;
+use crate::models::GetTakeoff;
+use std::fmt::Write;
+
+/// Media type of SeeYou waypoint files.
+pub const CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+/// Waypoint style for paragliding takeoffs.
+const STYLE_PG_TAKEOFF: u8 = 20;
+
+/// Write takeoffs as a SeeYou waypoint file.
+///
+/// The mean wind direction goes in `rwdir`, and all wind directions in `desc`.
+/// Takeoffs without coordinates are skipped.
+pub fn write(takeoffs: &[GetTakeoff]) -> String {
+    let mut out = String::from("name,code,country,lat,lon,elev,style,rwdir,rwlen,freq,desc\r\n");
+
+    for takeoff in takeoffs {
+        let (Some(lat), Some(lon)) = (takeoff.latitude, takeoff.longitude) else {
+            continue;
+        };
+
+        let name = takeoff.name.as_deref().unwrap_or_default();
+        let code = takeoff.id.map(|id| format!("PS{id}")).unwrap_or_default();
+        let elev = takeoff
+            .altitude_m
+            .map(|alt| format!("{alt}.0m"))
+            .unwrap_or_default();
+        let wind_dirs = takeoff.wind_dirs.as_deref().unwrap_or_default();
+        let rwdir = mean_wind_dir(wind_dirs)
+            .map(|deg| format!("{:.0}", deg.round() % 360.0))
+            .unwrap_or_default();
+        let desc = if wind_dirs.is_empty() {
+            String::new()
+        } else {
+            format!("Wind: {}", wind_dirs.join(" "))
+        };
+
+        let _ = write!(
+            out,
+            "{},{},,{},{},{},{},{},,,{}\r\n",
+            quote(name),
+            quote(&code),
+            coordinate(lat, 2, ['N', 'S']),
+            coordinate(lon, 3, ['E', 'W']),
+            elev,
+            STYLE_PG_TAKEOFF,
+            rwdir,
+            quote(&desc),
+        );
+    }
+
+    out
+}
+
+/// Format a coordinate as degrees and decimal minutes (e.g. `6030.000N`).
+fn coordinate(value: f64, degree_digits: usize, [positive, negative]: [char; 2]) -> String {
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    let thousandths = (value.abs() * 60_000.0).round() as u64;
+    let degrees = thousandths / 60_000;
+    let minutes = (thousandths % 60_000) as f64 / 1000.0;
+
+    format!("{degrees:0degree_digits$}{minutes:06.3}{hemisphere}")
+}
+
+/// Quote a CSV field.
+fn quote(text: &str) -> String {
+    format!(
+        "\"{}\"",
+        text.replace('"', "\"\"").replace(['\r', '\n'], " ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixtures::{self, Waypoint};
+
+    /// Split a CSV line into fields, unquoting quoted fields.
+    fn fields(line: &str) -> Vec<String> {
+        let mut out = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    out.last_mut().unwrap().push('"');
+                }
+                '"' => quoted = !quoted,
+                ',' if !quoted => out.push(String::new()),
+                c => out.last_mut().unwrap().push(c),
+            }
+        }
+
+        out
+    }
+
+    /// Read a coordinate in degrees and decimal minutes.
+    fn parse_coordinate(value: &str, degree_digits: usize) -> f64 {
+        let (number, hemisphere) = value.split_at(value.len() - 1);
+        let degrees: f64 = number[..degree_digits].parse().unwrap();
+        let minutes: f64 = number[degree_digits..].parse().unwrap();
+        let out = degrees + minutes / 60.0;
+
+        match hemisphere {
+            "S" | "W" => -out,
+            _ => out,
+        }
+    }
+
+    /// Read the waypoints of a SeeYou file.
+    fn read(cup: &str) -> Vec<Waypoint> {
+        cup.split("\r\n")
+            .skip(1)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let fields = fields(line);
+                assert_eq!(fields.len(), 11, "{line}");
+
+                Waypoint {
+                    name: fields[0].clone(),
+                    latitude: parse_coordinate(&fields[3], 2),
+                    longitude: parse_coordinate(&fields[4], 3),
+                    altitude_m: fields[5]
+                        .strip_suffix('m')
+                        .map(|elev| elev.parse::<f64>().unwrap() as i32),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let cup = write(&fixtures::takeoffs());
+
+        // Minutes have three decimals
+        fixtures::assert_same(&read(&cup), &fixtures::waypoints(), 1.0 / 60_000.0);
+    }
+
+    #[test]
+    fn quotes_fields() {
+        let cup = write(&fixtures::takeoffs());
+        let lines: Vec<&str> = cup.split("\r\n").collect();
+
+        assert_eq!(
+            lines[2],
+            "\"Rock & Roll <Top> \"\"West\"\", Ridge\",\"PS2\",,3354.768S,01825.218E,350.0m,20,248,,,\"Wind: SW W\""
+        );
+        assert_eq!(quote("a\r\nb"), "\"a  b\"");
+    }
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(coordinate(-0.25, 3, ['E', 'W']), "00015.000W");
+        assert_eq!(coordinate(59.99999999, 2, ['N', 'S']), "6000.000N");
+    }
+}
@@ -0,0 +1,63 @@
+//! Takeoffs for testing exports, and how to compare them after reading an export back.
+
+use crate::models::{GetTakeoff, NewTakeoff};
+
+/// Takeoffs of `tests/fixtures/takeoffs.json` with ids, followed by one without coordinates.
+pub fn takeoffs() -> Vec<GetTakeoff> {
+    let takeoffs: Vec<NewTakeoff> =
+        serde_json::from_str(include_str!("../../tests/fixtures/takeoffs.json")).unwrap();
+
+    takeoffs
+        .into_iter()
+        .zip(1..)
+        .map(|(takeoff, id)| GetTakeoff {
+            id: Some(id),
+            ..takeoff.into()
+        })
+        .chain([GetTakeoff {
+            id: Some(99),
+            name: Some("No coordinates".to_owned()),
+            ..Default::default()
+        }])
+        .collect()
+}
+
+/// A takeoff as read back from an export.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Waypoint {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude_m: Option<i32>,
+}
+
+/// Waypoints of the [`takeoffs`] with coordinates.
+pub fn waypoints() -> Vec<Waypoint> {
+    takeoffs()
+        .into_iter()
+        .filter_map(|takeoff| {
+            Some(Waypoint {
+                name: takeoff.name?,
+                latitude: takeoff.latitude?,
+                longitude: takeoff.longitude?,
+                altitude_m: takeoff.altitude_m,
+            })
+        })
+        .collect()
+}
+
+/// Assert that waypoints have the same names and altitudes, and coordinates within `epsilon`
+/// degrees.
+pub fn assert_same(read: &[Waypoint], expected: &[Waypoint], epsilon: f64) {
+    assert_eq!(read.len(), expected.len(), "{read:#?}");
+
+    for (read, expected) in read.iter().zip(expected) {
+        assert_eq!(read.name, expected.name);
+        assert!(
+            (read.latitude - expected.latitude).abs() < epsilon
+                && (read.longitude - expected.longitude).abs() < epsilon,
+            "{read:?} is not at {expected:?}"
+        );
+        assert_eq!(read.altitude_m, expected.altitude_m, "{}", read.name);
+    }
+}
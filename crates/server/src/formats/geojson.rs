@@ -0,0 +1,285 @@
+//! GeoJSON ([RFC 7946](https://datatracker.ietf.org/doc/html/rfc7946)) conversions.
+
+use crate::error::ServerError;
+use crate::models::{GetTakeoff, NewTakeoff};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Media type of GeoJSON documents.
+pub const CONTENT_TYPE: &str = "application/geo+json";
+
+/// Feature collection.
+///
+/// Serializes with `"type": "FeatureCollection"`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub struct FeatureCollection<P> {
+    /// Features.
+    pub features: Vec<Feature<P>>,
+}
+
+/// Feature.
+///
+/// Serializes with `"type": "Feature"`.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub struct Feature<P> {
+    /// Optional identifier.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    /// Optional geometry.
+    pub geometry: Option<Geometry>,
+    /// Properties.
+    pub properties: P,
+}
+
+/// Geometry.
+///
+/// Takeoffs are points, so no other geometry types are supported.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type")]
+pub enum Geometry {
+    /// Point with `[longitude, latitude]` or `[longitude, latitude, altitude_m]`.
+    Point {
+        /// Position.
+        coordinates: Vec<f64>,
+    },
+}
+
+/// Convert takeoffs to a feature collection.
+///
+/// Takeoffs without both coordinates get a `null` geometry.
+pub fn to_feature_collection(takeoffs: Vec<GetTakeoff>) -> FeatureCollection<GetTakeoff> {
+    let features = takeoffs
+        .into_iter()
+        .map(|takeoff| {
+            let geometry = match (takeoff.longitude, takeoff.latitude, takeoff.altitude_m) {
+                (Some(lon), Some(lat), Some(alt)) => Some(vec![lon, lat, f64::from(alt)]),
+                (Some(lon), Some(lat), None) => Some(vec![lon, lat]),
+                _ => None,
+            }
+            .map(|coordinates| Geometry::Point { coordinates });
+
+            Feature {
+                id: takeoff.id.map(Value::from),
+                geometry,
+                properties: takeoff,
+            }
+        })
+        .collect();
+
+    FeatureCollection { features }
+}
+
+/// Convert a feature collection to new takeoffs, with the feature ids of existing takeoffs.
+///
+/// The geometry takes precedence over `latitude`, `longitude` and `altitude_m` properties.
+///
+/// Returns an error naming the first invalid feature.
+pub fn to_new_takeoffs(
+    collection: FeatureCollection<Map<String, Value>>,
+) -> Result<Vec<(Option<i32>, NewTakeoff)>, ServerError> {
+    collection
+        .features
+        .into_iter()
+        .enumerate()
+        .map(|(i, feature)| {
+            to_new_takeoff(feature)
+                .map_err(|err| ServerError::BAD_REQUEST(format!("feature {i}: {err}")))
+        })
+        .collect()
+}
+
+/// Convert and validate a single feature.
+fn to_new_takeoff(
+    feature: Feature<Map<String, Value>>,
+) -> Result<(Option<i32>, NewTakeoff), String> {
+    let id = match feature.id {
+        None | Some(Value::Null) => None,
+        Some(id) => Some(
+            id.as_i64()
+                .and_then(|id| i32::try_from(id).ok())
+                .ok_or(format!("id {id} is not a takeoff id"))?,
+        ),
+    };
+    let mut properties = feature.properties;
+
+    match feature.geometry {
+        Some(Geometry::Point { coordinates }) => match coordinates[..] {
+            [lon, lat, ..] => {
+                properties.insert("longitude".to_owned(), Value::from(lon));
+                properties.insert("latitude".to_owned(), Value::from(lat));
+
+                if let Some(alt) = coordinates.get(2) {
+                    properties.insert("altitude_m".to_owned(), Value::from(alt.round() as i32));
+                }
+            }
+            _ => return Err("point must have at least two coordinates".to_owned()),
+        },
+        None => return Err("missing geometry".to_owned()),
+    }
+
+    let takeoff: NewTakeoff =
+        serde_json::from_value(Value::Object(properties)).map_err(|err| err.to_string())?;
+
+    if takeoff.name.trim().is_empty() {
+        return Err("empty name".to_owned());
+    }
+    if !(-90.0..=90.0).contains(&takeoff.latitude) {
+        return Err(format!("latitude {} out of range", takeoff.latitude));
+    }
+    if !(-180.0..=180.0).contains(&takeoff.longitude) {
+        return Err(format!("longitude {} out of range", takeoff.longitude));
+    }
+
+    Ok((id, takeoff))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixtures;
+    use serde_json::json;
+
+    /// A feature collection of `features`.
+    fn collection(features: Value) -> FeatureCollection<Map<String, Value>> {
+        serde_json::from_value(json!({ "type": "FeatureCollection", "features": features }))
+            .unwrap()
+    }
+
+    /// A feature with the required properties, and the fields of `extra` on top.
+    fn feature(extra: Value) -> Value {
+        let mut feature = json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": [6.4102, 60.6405, 812.6] },
+            "properties": {
+                "name": "Hanguren",
+                "description": "",
+                "region": "Vestland",
+                "wind_dirs": ["E", "SE"],
+                "altitude_m": 1,
+                "latitude": 1.0
+            }
+        });
+        feature
+            .as_object_mut()
+            .unwrap()
+            .extend(extra.as_object().unwrap().clone());
+        feature
+    }
+
+    /// The error of converting a single feature.
+    fn error(feature: Value) -> String {
+        to_new_takeoffs(collection(json!([feature])))
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn round_trip() {
+        let takeoffs = fixtures::takeoffs()
+            .into_iter()
+            .filter(|takeoff| takeoff.latitude.is_some())
+            .collect::<Vec<_>>();
+        let expected = takeoffs
+            .iter()
+            .map(|takeoff| (takeoff.id, takeoff.name.clone().unwrap()))
+            .collect::<Vec<_>>();
+
+        let json = serde_json::to_value(to_feature_collection(takeoffs)).unwrap();
+        let read = to_new_takeoffs(serde_json::from_value(json).unwrap()).unwrap();
+
+        let ids = read
+            .iter()
+            .map(|(id, takeoff)| (*id, takeoff.name.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(ids, expected);
+        let waypoints = read
+            .into_iter()
+            .map(|(_, takeoff)| fixtures::Waypoint {
+                name: takeoff.name,
+                latitude: takeoff.latitude,
+                longitude: takeoff.longitude,
+                altitude_m: takeoff.altitude_m,
+            })
+            .collect::<Vec<_>>();
+        fixtures::assert_same(&waypoints, &fixtures::waypoints(), 1e-12);
+    }
+
+    #[test]
+    fn geometry_takes_precedence() {
+        let (id, takeoff) = to_new_takeoffs(collection(json!([feature(json!({}))])))
+            .unwrap()
+            .remove(0);
+
+        assert_eq!(id, None);
+        assert_eq!(takeoff.name, "Hanguren");
+        assert_eq!(takeoff.longitude, 6.4102);
+        assert_eq!(takeoff.latitude, 60.6405);
+        assert_eq!(takeoff.altitude_m, Some(813));
+        assert_eq!(takeoff.wind_dirs, ["E", "SE"]);
+    }
+
+    #[test]
+    fn reads_feature_ids() {
+        let ids = to_new_takeoffs(collection(json!([
+            feature(json!({ "id": 12 })),
+            feature(json!({ "id": null })),
+        ])))
+        .unwrap()
+        .into_iter()
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+        assert_eq!(ids, [Some(12), None]);
+
+        assert!(error(feature(json!({ "id": "12" })))
+            .contains("feature 0: id \"12\" is not a takeoff id"));
+        assert!(error(feature(json!({ "id": 1.5 }))).contains("is not a takeoff id"));
+        assert!(error(feature(json!({ "id": 4_294_967_296_i64 }))).contains("is not a takeoff id"));
+    }
+
+    #[test]
+    fn rejects_invalid_geometry() {
+        assert!(error(feature(json!({ "geometry": null }))).contains("missing geometry"));
+        assert!(error(feature(
+            json!({ "geometry": { "type": "Point", "coordinates": [6.4] } })
+        ))
+        .contains("at least two coordinates"));
+
+        // Only points are takeoffs
+        let line = feature(json!({
+            "geometry": { "type": "LineString", "coordinates": [[6.4, 60.6], [6.5, 60.7]] }
+        }));
+        let collection = json!({ "type": "FeatureCollection", "features": [line] });
+        assert!(
+            serde_json::from_value::<FeatureCollection<Map<String, Value>>>(collection).is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_takeoffs() {
+        let point = |lon: f64, lat: f64| {
+            feature(json!({ "geometry": { "type": "Point", "coordinates": [lon, lat] } }))
+        };
+        assert!(error(point(6.4, 90.5)).contains("latitude 90.5 out of range"));
+        assert!(error(point(-180.5, 60.6)).contains("longitude -180.5 out of range"));
+
+        let mut blank = feature(json!({}));
+        blank["properties"]["name"] = json!("  ");
+        assert!(error(blank).contains("empty name"));
+
+        let mut unnamed = feature(json!({}));
+        unnamed["properties"]
+            .as_object_mut()
+            .unwrap()
+            .remove("name");
+        assert!(error(unnamed).contains("missing field `name`"));
+
+        // The index of the first invalid feature is named
+        let features = json!([feature(json!({})), point(6.4, 91.0)]);
+        assert!(to_new_takeoffs(collection(features))
+            .unwrap_err()
+            .to_string()
+            .contains("feature 1: latitude 91 out of range"));
+    }
+}
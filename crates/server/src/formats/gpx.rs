@@ -0,0 +1,110 @@
+//! GPX 1.1 export, as used by GPS units and XCTrack.
+
+use super::escape_xml;
+use crate::models::GetTakeoff;
+use std::fmt::Write;
+
+/// Media type of GPX documents.
+pub const CONTENT_TYPE: &str = "application/gpx+xml";
+
+/// Write takeoffs as a GPX document with one waypoint per takeoff.
+///
+/// Wind directions go in the waypoint comment. Takeoffs without coordinates are skipped.
+pub fn write(takeoffs: &[GetTakeoff]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<gpx version=\"1.1\" creator=\"Parastart\" xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    ));
+
+    for takeoff in takeoffs {
+        let (Some(lat), Some(lon)) = (takeoff.latitude, takeoff.longitude) else {
+            continue;
+        };
+
+        let _ = writeln!(out, "<wpt lat=\"{lat}\" lon=\"{lon}\">");
+        if let Some(alt) = takeoff.altitude_m {
+            let _ = writeln!(out, "<ele>{alt}</ele>");
+        }
+        if let Some(name) = &takeoff.name {
+            let _ = writeln!(out, "<name>{}</name>", escape_xml(name));
+        }
+        if let Some(wind_dirs) = takeoff.wind_dirs.as_ref().filter(|dirs| !dirs.is_empty()) {
+            let _ = writeln!(out, "<cmt>{}</cmt>", escape_xml(&wind_dirs.join(" ")));
+        }
+        if let Some(description) = &takeoff.description {
+            let _ = writeln!(out, "<desc>{}</desc>", escape_xml(description));
+        }
+        if let Some(source_url) = &takeoff.source_url {
+            let _ = writeln!(out, "<link href=\"{}\"/>", escape_xml(source_url));
+        }
+        out.push_str("<sym>Flag</sym>\n<type>Takeoff</type>\n</wpt>\n");
+    }
+
+    out.push_str("</gpx>\n");
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixtures::{self, Waypoint};
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    /// Read the waypoints of a GPX document.
+    fn read(gpx: &str) -> Vec<Waypoint> {
+        let mut reader = Reader::from_str(gpx);
+        reader.config_mut().trim_text(true);
+        let mut field = String::new();
+        let mut out: Vec<Waypoint> = Vec::new();
+
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(element) => {
+                    field = String::from_utf8(element.local_name().as_ref().to_vec()).unwrap();
+                    if field == "wpt" {
+                        let attribute = |name: &str| -> f64 {
+                            let value = element.try_get_attribute(name).unwrap().unwrap();
+                            value.unescape_value().unwrap().parse().unwrap()
+                        };
+                        out.push(Waypoint {
+                            latitude: attribute("lat"),
+                            longitude: attribute("lon"),
+                            ..Default::default()
+                        });
+                    }
+                }
+                Event::Text(text) => {
+                    let text = text.unescape().unwrap();
+                    let waypoint = out.last_mut().unwrap();
+                    match field.as_str() {
+                        "name" => waypoint.name = text.into_owned(),
+                        "ele" => waypoint.altitude_m = Some(text.parse().unwrap()),
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        out
+    }
+
+    #[test]
+    fn round_trip() {
+        let gpx = write(&fixtures::takeoffs());
+
+        fixtures::assert_same(&read(&gpx), &fixtures::waypoints(), 1e-12);
+    }
+
+    #[test]
+    fn escapes_text() {
+        let gpx = write(&fixtures::takeoffs());
+
+        assert!(gpx.contains("<name>Rock &amp; Roll &lt;Top&gt; &quot;West&quot;, Ridge</name>"));
+        assert!(gpx.contains("<desc>Launch at &quot;the rocks&quot; &amp; &lt;not&gt; the road"));
+        assert!(gpx.contains("<cmt>N NE</cmt>"));
+    }
+}
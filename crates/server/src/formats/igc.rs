@@ -0,0 +1,398 @@
+//! IGC flight recorder (`.igc`) import, see the
+//! [FAI technical specification](https://www.fai.org/page/igc-approved-flight-recorders).
+//!
+//! Only the header (`H`), fixes (`B`) and security (`G`) records are read. The G-record
+//! signature is specific to the manufacturer of the recorder, so only its format is validated.
+
+use crate::{error::ServerError, validation::geo};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::Serialize;
+
+/// Minimum length of a B record, up to the GNSS altitude.
+const B_RECORD_LENGTH: usize = 35;
+/// Seconds the climb rate is averaged over, so single noisy fixes don't count.
+const CLIMB_WINDOW_SECONDS: i64 = 20;
+
+/// A parsed IGC file.
+#[derive(Debug, Clone)]
+pub struct Igc {
+    pub header: Header,
+    /// Fixes, earliest first.
+    pub fixes: Vec<Fix>,
+    /// If the file has G records.
+    pub signed: bool,
+}
+
+/// Header of an IGC file.
+#[derive(Debug, Clone, Default)]
+pub struct Header {
+    /// Optional manufacturer and serial of the recorder, from the A record.
+    pub recorder: Option<String>,
+    /// Day of the first fix (UTC).
+    pub date: Option<NaiveDate>,
+    pub pilot: Option<String>,
+    pub glider_type: Option<String>,
+    pub glider_id: Option<String>,
+}
+
+/// A fix of a B record.
+#[derive(Debug, Clone, Copy)]
+pub struct Fix {
+    pub time: DateTime<Utc>,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// `true` for 3D fixes, `false` for 2D or no GNSS fix.
+    pub valid: bool,
+    /// Altitude from the pressure sensor, in meters above the 1013.25 hPa sea level datum.
+    pub pressure_altitude_m: i32,
+    /// Altitude from GNSS, in meters above the WGS84 ellipsoid.
+    pub gnss_altitude_m: i32,
+}
+
+/// Statistics of a track.
+#[derive(Debug, Clone, Serialize)]
+pub struct Stats {
+    pub started_at: DateTime<Utc>,
+    pub landed_at: DateTime<Utc>,
+    pub duration_s: i64,
+    /// Highest altitude in meters, from GNSS or from pressure if the recorder has no GNSS
+    /// altitude.
+    pub max_altitude_m: i32,
+    /// Straight distance from launch to landing, in kilometers.
+    pub distance_km: f64,
+    /// Length of the whole track, in kilometers.
+    pub track_length_km: f64,
+    /// Best climb rate averaged over [`CLIMB_WINDOW_SECONDS`], in meters per second.
+    pub max_climb_ms: f64,
+    pub launch_latitude: f64,
+    pub launch_longitude: f64,
+}
+
+/// Parse an IGC file.
+///
+/// # Errors
+///
+/// This function will return an error naming the first invalid line, or if the date or fixes
+/// are missing.
+pub fn parse(text: &str) -> Result<Igc, ServerError> {
+    let mut header = Header::default();
+    let mut records = Vec::new();
+    let mut signed = false;
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim_end();
+        let invalid = |what: &str| ServerError::BAD_REQUEST(format!("line {}: {what}", i + 1));
+        if line.is_empty() {
+            continue;
+        }
+
+        // G records come last, and their content depends on the recorder
+        if signed && !line.starts_with('G') {
+            return Err(invalid("only G records may follow G records"));
+        }
+        match line.as_bytes()[0] {
+            b'A' => header.recorder = Some(line[1..].trim().to_owned()).filter(|s| !s.is_empty()),
+            b'H' => parse_header(line, &mut header).ok_or_else(|| invalid("invalid H record"))?,
+            b'B' => records.push(parse_b_record(line).ok_or_else(|| invalid("invalid B record"))?),
+            b'G' => {
+                let signature = &line[1..];
+                if signature.is_empty() || !signature.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                    return Err(invalid("invalid G record"));
+                }
+                signed = true;
+            }
+            _ => {}
+        }
+    }
+
+    let date = header
+        .date
+        .ok_or(ServerError::BAD_REQUEST("missing date (HFDTE record)"))?;
+    if records.is_empty() {
+        return Err(ServerError::BAD_REQUEST("no fixes (B records)"));
+    }
+
+    // Times are UTC without a date, so a time before the previous one is the next day
+    let mut day = date;
+    let mut previous: Option<NaiveTime> = None;
+    let fixes = records
+        .into_iter()
+        .map(|(time, fix)| {
+            if previous.is_some_and(|previous| time < previous) {
+                day = day.succ_opt().unwrap_or(day);
+            }
+            previous = Some(time);
+            Fix {
+                time: day.and_time(time).and_utc(),
+                ..fix
+            }
+        })
+        .collect();
+
+    Ok(Igc {
+        header,
+        fixes,
+        signed,
+    })
+}
+
+/// Read an H record into `header`, ignoring unknown ones.
+///
+/// Returns `None` if a known record is invalid.
+fn parse_header(line: &str, header: &mut Header) -> Option<()> {
+    let code = line.get(2..5)?;
+    // Values follow the long name and a colon (e.g. `HFPLTPILOTINCHARGE:Kari`), but some
+    // recorders leave it out for the date (e.g. `HFDTE150725`)
+    let value = match line.split_once(':') {
+        Some((_, value)) => value.trim(),
+        None => line.get(5..)?.trim(),
+    };
+    let value = Some(value.to_owned()).filter(|value| !value.is_empty());
+
+    match code {
+        "DTE" => {
+            // `DDMMYY`, optionally followed by a flight number (e.g. `150725,01`)
+            let date = value?.split(',').next()?.trim().to_owned();
+            header.date = Some(NaiveDate::parse_from_str(&date, "%d%m%y").ok()?);
+        }
+        "PLT" => header.pilot = value,
+        "GTY" => header.glider_type = value,
+        "GID" => header.glider_id = value,
+        _ => {}
+    }
+
+    Some(())
+}
+
+/// Read a B record (e.g. `B1101355206343N00006198WA0058700558`).
+///
+/// Returns the time of day and the fix, with the time set to midnight of 1970-01-01.
+fn parse_b_record(line: &str) -> Option<(NaiveTime, Fix)> {
+    if line.len() < B_RECORD_LENGTH || !line.is_ascii() {
+        return None;
+    }
+
+    let time = NaiveTime::parse_from_str(&line[1..7], "%H%M%S").ok()?;
+    let latitude = parse_coordinate(&line[7..15], 2, 'N', 'S')?;
+    let longitude = parse_coordinate(&line[15..24], 3, 'E', 'W')?;
+    let valid = match &line[24..25] {
+        "A" => true,
+        "V" => false,
+        _ => return None,
+    };
+    let pressure_altitude_m = line[25..30].parse().ok()?;
+    let gnss_altitude_m = line[30..35].parse().ok()?;
+
+    let fix = Fix {
+        time: DateTime::UNIX_EPOCH,
+        latitude,
+        longitude,
+        valid,
+        pressure_altitude_m,
+        gnss_altitude_m,
+    };
+
+    Some((time, fix))
+}
+
+/// Read a coordinate as degrees, minutes with three decimals and a hemisphere
+/// (e.g. `5206343N` or `00006198W`).
+fn parse_coordinate(
+    value: &str,
+    degree_digits: usize,
+    positive: char,
+    negative: char,
+) -> Option<f64> {
+    let degrees: f64 = value.get(..degree_digits)?.parse().ok()?;
+    let minutes: f64 = value
+        .get(degree_digits..degree_digits + 5)?
+        .parse::<u32>()
+        .ok()? as f64;
+    let coordinate = degrees + minutes / 1000.0 / 60.0;
+
+    match value.chars().last()? {
+        c if c == positive => Some(coordinate),
+        c if c == negative => Some(-coordinate),
+        _ => None,
+    }
+}
+
+impl Igc {
+    /// Compute statistics of the valid fixes.
+    ///
+    /// Returns `None` if there are no valid fixes.
+    pub fn stats(&self) -> Option<Stats> {
+        let fixes: Vec<&Fix> = self.fixes.iter().filter(|fix| fix.valid).collect();
+        let (launch, landing) = (*fixes.first()?, *fixes.last()?);
+
+        // Recorders without a GNSS altitude write zeros
+        let gnss = fixes.iter().any(|fix| fix.gnss_altitude_m != 0);
+        let altitude = |fix: &Fix| match gnss {
+            true => fix.gnss_altitude_m,
+            false => fix.pressure_altitude_m,
+        };
+        let distance =
+            |a: &Fix, b: &Fix| geo::haversine(a.latitude, a.longitude, b.latitude, b.longitude);
+
+        // Climb between each fix and the first fix at least a window later
+        let mut max_climb_ms = 0.0_f64;
+        let mut end = 0;
+        for (start, fix) in fixes.iter().enumerate() {
+            end = end.max(start);
+            while end < fixes.len()
+                && fixes[end].time - fix.time < Duration::seconds(CLIMB_WINDOW_SECONDS)
+            {
+                end += 1;
+            }
+            let Some(later) = fixes.get(end).copied() else {
+                break;
+            };
+            let seconds = (later.time - fix.time).num_seconds() as f64;
+            max_climb_ms = max_climb_ms.max(f64::from(altitude(later) - altitude(fix)) / seconds);
+        }
+
+        Some(Stats {
+            started_at: launch.time,
+            landed_at: landing.time,
+            duration_s: (landing.time - launch.time).num_seconds(),
+            max_altitude_m: fixes.iter().map(|fix| altitude(fix)).max()?,
+            distance_km: distance(launch, landing) / 1000.0,
+            track_length_km: fixes
+                .windows(2)
+                .map(|pair| distance(pair[0], pair[1]))
+                .sum::<f64>()
+                / 1000.0,
+            max_climb_ms,
+            launch_latitude: launch.latitude,
+            launch_longitude: launch.longitude,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// Signed flight with `HFDTEDATE:` and a flight number, starting with a 2D fix.
+    const FLIGHT: &str = include_str!("../../tests/fixtures/igc/flight.igc");
+    /// Unsigned flight over midnight on new year's eve, without GNSS altitudes.
+    const MIDNIGHT: &str = include_str!("../../tests/fixtures/igc/midnight.igc");
+
+    fn time(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap()
+    }
+
+    #[test]
+    fn parses_header() {
+        let igc = parse(FLIGHT).unwrap();
+
+        assert_eq!(igc.header.recorder.as_deref(), Some("XCT0a1b2c3d4e5f"));
+        assert_eq!(igc.header.date, NaiveDate::from_ymd_opt(2025, 7, 15));
+        assert_eq!(igc.header.pilot.as_deref(), Some("Kari Nordmann"));
+        assert_eq!(igc.header.glider_type.as_deref(), Some("Ozone Rush 6"));
+        assert_eq!(igc.header.glider_id.as_deref(), Some("LN-123"));
+        assert!(igc.signed);
+
+        let igc = parse(MIDNIGHT).unwrap();
+        assert_eq!(igc.header.date, NaiveDate::from_ymd_opt(2025, 12, 31));
+        assert_eq!(igc.header.pilot, None);
+        assert!(!igc.signed);
+    }
+
+    #[test]
+    fn parses_fixes() {
+        let fixes = parse(FLIGHT).unwrap().fixes;
+
+        assert_eq!(fixes.len(), 6);
+        assert_eq!(fixes[0].time, time(2025, 7, 15, 10, 59, 50));
+        assert!(!fixes[0].valid);
+
+        let fix = fixes[1];
+        assert_eq!(fix.time, time(2025, 7, 15, 11, 0, 0));
+        assert!(fix.valid);
+        assert!((fix.latitude - (60.0 + 38.43 / 60.0)).abs() < 1e-9);
+        assert!((fix.longitude - (6.0 + 24.612 / 60.0)).abs() < 1e-9);
+        assert_eq!(fix.pressure_altitude_m, 980);
+        assert_eq!(fix.gnss_altitude_m, 1000);
+
+        let fix = parse_b_record("B1100005206343S00006198WA0058700558")
+            .unwrap()
+            .1;
+        assert!(fix.latitude < 0.0 && fix.longitude < 0.0);
+    }
+
+    #[test]
+    fn rolls_over_midnight() {
+        let times: Vec<DateTime<Utc>> = parse(MIDNIGHT)
+            .unwrap()
+            .fixes
+            .iter()
+            .map(|fix| fix.time)
+            .collect();
+
+        assert_eq!(
+            times,
+            [
+                time(2025, 12, 31, 23, 59, 50),
+                time(2025, 12, 31, 23, 59, 55),
+                time(2026, 1, 1, 0, 0, 5),
+                time(2026, 1, 1, 0, 0, 15),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_files() {
+        let error = |text: &str| parse(text).unwrap_err().to_string();
+
+        assert!(error(&format!("{FLIGHT}G12 34\r\n")).contains("line 17: invalid G record"));
+        assert!(error(&format!("{FLIGHT}G\r\n")).contains("invalid G record"));
+        assert!(
+            error(&format!("{FLIGHT}B1100506039105N00624612EA0105001070\r\n"))
+                .contains("line 17: only G records may follow G records")
+        );
+        assert!(
+            error(&FLIGHT.replace("HFDTEDATE:150725,01", "HFDTEDATE:310225"))
+                .contains("line 2: invalid H record")
+        );
+        assert!(error(&FLIGHT.replace("HFDTEDATE:150725,01", "HFFXA035")).contains("missing date"));
+        assert!(error(&MIDNIGHT.replace("EA0054000000", "EA00540")).contains("invalid B record"));
+        assert!(error(&MIDNIGHT.replace("N00624650E", "X00624650E")).contains("line 5"));
+        assert!(error("HFDTE150725\r\n").contains("no fixes"));
+    }
+
+    #[test]
+    fn computes_stats() {
+        let stats = parse(FLIGHT).unwrap().stats().unwrap();
+
+        // The 2D fix before launch is left out
+        assert_eq!(stats.started_at, time(2025, 7, 15, 11, 0, 0));
+        assert_eq!(stats.landed_at, time(2025, 7, 15, 11, 0, 40));
+        assert_eq!(stats.duration_s, 40);
+        assert_eq!(stats.max_altitude_m, 1090);
+        // 0.54 minutes of latitude north
+        assert!((stats.distance_km - 1.0008).abs() < 0.001, "{stats:?}");
+        assert!((stats.track_length_km - stats.distance_km).abs() < 1e-9);
+        // 1010 m to 1090 m from 11:00:10 to 11:00:30
+        assert_eq!(stats.max_climb_ms, 4.0);
+        assert!((stats.launch_latitude - (60.0 + 38.43 / 60.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn computes_stats_from_pressure_altitude() {
+        let stats = parse(MIDNIGHT).unwrap().stats().unwrap();
+
+        assert_eq!(stats.duration_s, 25);
+        assert_eq!(stats.max_altitude_m, 560);
+        // 500 m to 540 m over the 25 seconds to the first fix at least 20 seconds later
+        assert_eq!(stats.max_climb_ms, 1.6);
+    }
+
+    #[test]
+    fn has_no_stats_without_valid_fixes() {
+        let igc = parse(&FLIGHT.replace("EA0", "EV0")).unwrap();
+
+        assert!(igc.stats().is_none());
+    }
+}
@@ -0,0 +1,144 @@
+//! KML export, as used by Google Earth.
+
+use super::escape_xml;
+use crate::models::GetTakeoff;
+use std::fmt::Write;
+
+/// Media type of KML documents.
+pub const CONTENT_TYPE: &str = "application/vnd.google-earth.kml+xml";
+
+/// Write takeoffs as a KML document with one placemark per takeoff.
+///
+/// Wind directions are kept in the placemark's extended data. Takeoffs without coordinates are skipped.
+pub fn write(takeoffs: &[GetTakeoff]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<kml xmlns=\"http://www.opengis.net/kml/2.2\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n",
+        "<Document>\n",
+        "<name>Parastart</name>\n",
+    ));
+
+    for takeoff in takeoffs {
+        let (Some(lat), Some(lon)) = (takeoff.latitude, takeoff.longitude) else {
+            continue;
+        };
+
+        out.push_str("<Placemark>\n");
+        if let Some(name) = &takeoff.name {
+            let _ = writeln!(out, "<name>{}</name>", escape_xml(name));
+        }
+        if let Some(source_url) = &takeoff.source_url {
+            let _ = writeln!(out, "<atom:link href=\"{}\"/>", escape_xml(source_url));
+        }
+        if let Some(description) = &takeoff.description {
+            let _ = writeln!(
+                out,
+                "<description>{}</description>",
+                escape_xml(description)
+            );
+        }
+        out.push_str("<ExtendedData>\n");
+        if let Some(id) = takeoff.id {
+            let _ = writeln!(out, "<Data name=\"id\"><value>{id}</value></Data>");
+        }
+        if let Some(wind_dirs) = &takeoff.wind_dirs {
+            let _ = writeln!(
+                out,
+                "<Data name=\"wind_dirs\"><value>{}</value></Data>",
+                escape_xml(&wind_dirs.join(" "))
+            );
+        }
+        out.push_str("</ExtendedData>\n");
+        match takeoff.altitude_m {
+            Some(alt) => {
+                let _ = writeln!(
+                    out,
+                    "<Point><altitudeMode>absolute</altitudeMode><coordinates>{lon},{lat},{alt}</coordinates></Point>"
+                );
+            }
+            None => {
+                let _ = writeln!(out, "<Point><coordinates>{lon},{lat}</coordinates></Point>");
+            }
+        }
+        out.push_str("</Placemark>\n");
+    }
+
+    out.push_str("</Document>\n</kml>\n");
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixtures::{self, Waypoint};
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    /// Read the placemarks of a KML document.
+    fn read(kml: &str) -> Vec<Waypoint> {
+        let mut reader = Reader::from_str(kml);
+        reader.config_mut().trim_text(true);
+        let mut path = Vec::new();
+        let mut out: Vec<Waypoint> = Vec::new();
+
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(element) => {
+                    let name = String::from_utf8(element.local_name().as_ref().to_vec()).unwrap();
+                    if name == "Placemark" {
+                        out.push(Waypoint::default());
+                    }
+                    path.push(name);
+                }
+                Event::End(_) => {
+                    path.pop();
+                }
+                Event::Text(text) => {
+                    let text = text.unescape().unwrap();
+                    let placemark = out.last_mut();
+                    match (path.as_slice(), placemark) {
+                        ([.., parent, field], Some(placemark))
+                            if parent == "Placemark" && field == "name" =>
+                        {
+                            placemark.name = text.into_owned();
+                        }
+                        ([.., field], Some(placemark)) if field == "coordinates" => {
+                            let values: Vec<f64> =
+                                text.split(',').map(|v| v.parse().unwrap()).collect();
+                            placemark.longitude = values[0];
+                            placemark.latitude = values[1];
+                            placemark.altitude_m = values.get(2).map(|alt| *alt as i32);
+                        }
+                        _ => {}
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        out
+    }
+
+    #[test]
+    fn round_trip() {
+        let kml = write(&fixtures::takeoffs());
+
+        fixtures::assert_same(&read(&kml), &fixtures::waypoints(), 1e-12);
+    }
+
+    #[test]
+    fn escapes_text() {
+        let kml = write(&fixtures::takeoffs());
+
+        assert!(kml.contains("<name>Rock &amp; Roll &lt;Top&gt; &quot;West&quot;, Ridge</name>"));
+        assert!(kml.contains("href=\"https://flightlog.org/fl.html?l=1&amp;a=22&amp;"));
+        assert!(kml.contains("<Data name=\"wind_dirs\"><value>SW W</value></Data>"));
+    }
+
+    #[test]
+    fn empty() {
+        assert!(read(&write(&[])).is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+//! Conversions between takeoffs and exchange formats.
+
+pub mod cup;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod geojson;
+pub mod gpx;
+pub mod igc;
+pub mod kml;
+pub mod mvt;
+pub mod wpt;
+
+use crate::validation::WIND_DIRS;
+
+/// Convert a compass point (e.g. `SW`) to degrees.
+pub fn wind_dir_degrees(dir: &str) -> Option<f64> {
+    WIND_DIRS
+        .iter()
+        .position(|point| point.eq_ignore_ascii_case(dir.trim()))
+        .map(|i| i as f64 * 22.5)
+}
+
+/// Circular mean of compass points, in degrees.
+///
+/// Returns `None` if there are no known directions, or if they cancel out (e.g. `N` and `S`).
+pub fn mean_wind_dir(dirs: &[String]) -> Option<f64> {
+    let (sin, cos) = dirs
+        .iter()
+        .filter_map(|dir| wind_dir_degrees(dir))
+        .fold((0.0, 0.0), |(sin, cos), deg: f64| {
+            (sin + deg.to_radians().sin(), cos + deg.to_radians().cos())
+        });
+
+    if sin.abs() < 1e-9 && cos.abs() < 1e-9 {
+        None
+    } else {
+        Some(sin.atan2(cos).to_degrees().rem_euclid(360.0))
+    }
+}
+
+/// Escape text for use in XML (or HTML) content and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+
+    out
+}
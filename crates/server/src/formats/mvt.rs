@@ -0,0 +1,252 @@
+//! Mapbox Vector Tile (`.mvt`) export of takeoffs, see <https://github.com/mapbox/vector-tile-spec>.
+//!
+//! Tiles have a single `takeoffs` layer of points. Up to [`CLUSTER_MAX_ZOOM`], takeoffs close to
+//! each other are clustered.
+
+use crate::models::GetTakeoff;
+use std::{collections::BTreeMap, collections::HashMap, f64::consts::PI};
+
+/// Media type of vector tiles.
+pub const CONTENT_TYPE: &str = "application/vnd.mapbox-vector-tile";
+
+/// Size of a tile in tile coordinates.
+pub const EXTENT: u32 = 4096;
+/// Maximum zoom level of tiles.
+pub const MAX_ZOOM: u32 = 22;
+/// Maximum zoom level with clustering, takeoffs are individual points above it.
+pub const CLUSTER_MAX_ZOOM: u32 = 12;
+
+/// Name of the layer of takeoffs.
+const LAYER: &str = "takeoffs";
+/// Size of clustering cells in tile coordinates (32 pixels on 256 pixel tiles).
+const CLUSTER_CELL: f64 = 512.0;
+/// Tile coordinates outside the tile where takeoffs are still included, so they aren't cut off.
+pub const BUFFER: f64 = 64.0;
+/// Maximum latitude of Web Mercator.
+const MAX_LATITUDE: f64 = 85.051_128_78;
+
+/// Property value of a feature.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Value {
+    String(String),
+    Uint(u64),
+}
+
+/// Point feature.
+#[derive(Debug, Clone)]
+pub struct Feature {
+    /// Optional id, the takeoff id for takeoffs.
+    pub id: Option<u64>,
+    /// Tile coordinate from the left.
+    pub x: i32,
+    /// Tile coordinate from the top.
+    pub y: i32,
+    /// Properties by key.
+    pub properties: Vec<(&'static str, Value)>,
+}
+
+/// Takeoff with tile coordinates.
+type Point<'a> = (&'a GetTakeoff, f64, f64);
+
+/// Check if a tile exists.
+pub fn valid_tile(z: u32, x: u32, y: u32) -> bool {
+    z <= MAX_ZOOM && x < 1 << z && y < 1 << z
+}
+
+/// Get the bounds of a tile as `(west, south, east, north)` in decimal degrees.
+///
+/// `buffer` is in tile coordinates, and widens the bounds on every side.
+pub fn tile_bounds(z: u32, x: u32, y: u32, buffer: f64) -> (f64, f64, f64, f64) {
+    let n = f64::from(1u32 << z);
+    let buffer = buffer / f64::from(EXTENT);
+    let lon = |x: f64| x / n * 360.0 - 180.0;
+    let lat = |y: f64| (PI * (1.0 - 2.0 * y / n)).sinh().atan().to_degrees();
+
+    (
+        lon(f64::from(x) - buffer),
+        lat(f64::from(y) + 1.0 + buffer),
+        lon(f64::from(x) + 1.0 + buffer),
+        lat(f64::from(y) - buffer),
+    )
+}
+
+/// Project a coordinate in decimal degrees to tile coordinates of a tile.
+///
+/// Coordinates outside the tile are below 0 or above [`EXTENT`].
+pub fn project(lat: f64, lon: f64, z: u32, x: u32, y: u32) -> (f64, f64) {
+    let n = f64::from(1u32 << z);
+    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let tile_x = (lon + 180.0) / 360.0 * n;
+    let tile_y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / PI) / 2.0 * n;
+
+    (
+        (tile_x - f64::from(x)) * f64::from(EXTENT),
+        (tile_y - f64::from(y)) * f64::from(EXTENT),
+    )
+}
+
+/// Write takeoffs as a vector tile.
+///
+/// * Takeoffs are points with `name` and comma separated `wind_dirs`, and their id.
+/// * Up to [`CLUSTER_MAX_ZOOM`], takeoffs in the same cell of a grid are clustered into a point
+///   at their center with `point_count`. Cells are aligned with tiles, so clusters never span two.
+///
+/// Takeoffs without coordinates are skipped.
+pub fn write_takeoffs(takeoffs: &[GetTakeoff], z: u32, x: u32, y: u32) -> Vec<u8> {
+    let points = takeoffs.iter().filter_map(|takeoff| {
+        let (point_x, point_y) = project(takeoff.latitude?, takeoff.longitude?, z, x, y);
+        Some((takeoff, point_x, point_y))
+    });
+    let extent = f64::from(EXTENT);
+
+    let features: Vec<Feature> = if z <= CLUSTER_MAX_ZOOM {
+        let mut cells: BTreeMap<(i64, i64), Vec<Point>> = BTreeMap::new();
+        for point in
+            points.filter(|(_, x, y)| (0.0..extent).contains(x) && (0.0..extent).contains(y))
+        {
+            let cell = (
+                (point.1 / CLUSTER_CELL) as i64,
+                (point.2 / CLUSTER_CELL) as i64,
+            );
+            cells.entry(cell).or_default().push(point);
+        }
+
+        cells
+            .into_values()
+            .map(|points| match points.as_slice() {
+                [(takeoff, x, y)] => takeoff_feature(takeoff, *x, *y),
+                _ => {
+                    let count = points.len() as f64;
+                    let x = points.iter().map(|point| point.1).sum::<f64>() / count;
+                    let y = points.iter().map(|point| point.2).sum::<f64>() / count;
+                    Feature {
+                        id: None,
+                        x: x.round() as i32,
+                        y: y.round() as i32,
+                        properties: vec![("point_count", Value::Uint(points.len() as u64))],
+                    }
+                }
+            })
+            .collect()
+    } else {
+        let bounds = -BUFFER..extent + BUFFER;
+        points
+            .filter(|(_, x, y)| bounds.contains(x) && bounds.contains(y))
+            .map(|(takeoff, x, y)| takeoff_feature(takeoff, x, y))
+            .collect()
+    };
+
+    write(LAYER, &features)
+}
+
+/// Create a point feature of a takeoff.
+fn takeoff_feature(takeoff: &GetTakeoff, x: f64, y: f64) -> Feature {
+    let name = takeoff.name.clone().unwrap_or_default();
+    let wind_dirs = takeoff.wind_dirs.as_deref().unwrap_or_default().join(",");
+
+    Feature {
+        id: takeoff.id.and_then(|id| u64::try_from(id).ok()),
+        x: x.round() as i32,
+        y: y.round() as i32,
+        properties: vec![
+            ("name", Value::String(name)),
+            ("wind_dirs", Value::String(wind_dirs)),
+        ],
+    }
+}
+
+/// Write a vector tile with a single layer of point features.
+pub fn write(layer: &str, features: &[Feature]) -> Vec<u8> {
+    // Keys and values are stored once per layer, and referenced by index in feature tags
+    let mut keys: Vec<&str> = Vec::new();
+    let mut values: Vec<&Value> = Vec::new();
+    let mut key_index: HashMap<&str, u32> = HashMap::new();
+    let mut value_index: HashMap<&Value, u32> = HashMap::new();
+
+    let mut encoded_features = Vec::new();
+    for feature in features {
+        let mut tags = Vec::new();
+        for (key, value) in &feature.properties {
+            let key = *key_index.entry(key).or_insert_with(|| {
+                keys.push(key);
+                keys.len() as u32 - 1
+            });
+            let value = *value_index.entry(value).or_insert_with(|| {
+                values.push(value);
+                values.len() as u32 - 1
+            });
+            tags.extend([key, value]);
+        }
+
+        // A single `MoveTo` command with one point
+        let geometry = [(1 << 3) | 1, zigzag(feature.x), zigzag(feature.y)];
+
+        let mut out = Vec::new();
+        if let Some(id) = feature.id {
+            write_varint_field(&mut out, 1, id);
+        }
+        write_packed_field(&mut out, 2, &tags);
+        write_varint_field(&mut out, 3, 1); // POINT
+        write_packed_field(&mut out, 4, &geometry);
+        encoded_features.push(out);
+    }
+
+    let mut out = Vec::new();
+    write_varint_field(&mut out, 15, 2); // Version
+    write_bytes_field(&mut out, 1, layer.as_bytes());
+    for feature in &encoded_features {
+        write_bytes_field(&mut out, 2, feature);
+    }
+    for key in keys {
+        write_bytes_field(&mut out, 3, key.as_bytes());
+    }
+    for value in values {
+        let mut encoded = Vec::new();
+        match value {
+            Value::String(value) => write_bytes_field(&mut encoded, 1, value.as_bytes()),
+            Value::Uint(value) => write_varint_field(&mut encoded, 5, *value),
+        }
+        write_bytes_field(&mut out, 4, &encoded);
+    }
+    write_varint_field(&mut out, 5, u64::from(EXTENT));
+
+    let mut tile = Vec::new();
+    write_bytes_field(&mut tile, 3, &out);
+    tile
+}
+
+/// ZigZag encode a signed integer, as used for geometry parameters.
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+/// Write a protobuf varint.
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Write a protobuf varint field.
+fn write_varint_field(out: &mut Vec<u8>, field: u64, value: u64) {
+    write_varint(out, field << 3);
+    write_varint(out, value);
+}
+
+/// Write a protobuf length-delimited field.
+fn write_bytes_field(out: &mut Vec<u8>, field: u64, bytes: &[u8]) {
+    write_varint(out, field << 3 | 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Write a protobuf packed field of varints.
+fn write_packed_field(out: &mut Vec<u8>, field: u64, values: &[u32]) {
+    let mut packed = Vec::new();
+    for value in values {
+        write_varint(&mut packed, u64::from(*value));
+    }
+    write_bytes_field(out, field, &packed);
+}
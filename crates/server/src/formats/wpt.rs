@@ -0,0 +1,124 @@
+//! CompeGPS waypoint (`.wpt`) export, as used by flight instruments.
+
+use crate::models::GetTakeoff;
+use std::fmt::Write;
+
+/// Media type of CompeGPS waypoint files.
+pub const CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Date of waypoints without an update date, as written by CompeGPS.
+const NO_DATE: &str = "27-MAR-62 00:00:00";
+
+/// Write takeoffs as a CompeGPS waypoint file with WGS 84 decimal degrees.
+///
+/// Waypoint names can't contain spaces, so they're replaced with underscores.
+/// The description holds the full name and wind directions, and the date is the last update.
+/// Takeoffs without coordinates are skipped.
+pub fn write(takeoffs: &[GetTakeoff]) -> String {
+    let mut out = String::from("G  WGS 84\r\nU  1\r\n");
+
+    for takeoff in takeoffs {
+        let (Some(lat), Some(lon)) = (takeoff.latitude, takeoff.longitude) else {
+            continue;
+        };
+
+        let name = takeoff.name.as_deref().unwrap_or_default();
+        let mut description = name.to_owned();
+        if let Some(wind_dirs) = takeoff.wind_dirs.as_ref().filter(|dirs| !dirs.is_empty()) {
+            let _ = write!(description, " ({})", wind_dirs.join(" "));
+        }
+        let date = takeoff.updated_at.map_or(NO_DATE.to_owned(), |date| {
+            date.format("%d-%b-%y %H:%M:%S").to_string().to_uppercase()
+        });
+
+        let _ = write!(
+            out,
+            "W  {} A {:.10}º{} {:.10}º{} {} {:.6} {}\r\n",
+            waypoint_name(name),
+            lat.abs(),
+            if lat < 0.0 { 'S' } else { 'N' },
+            lon.abs(),
+            if lon < 0.0 { 'W' } else { 'E' },
+            date,
+            f64::from(takeoff.altitude_m.unwrap_or_default()),
+            description.replace(['\r', '\n'], " "),
+        );
+    }
+
+    out
+}
+
+/// Make a name without whitespace, or `-` if empty.
+fn waypoint_name(name: &str) -> String {
+    let out = name.split_whitespace().collect::<Vec<_>>().join("_");
+
+    if out.is_empty() {
+        "-".to_owned()
+    } else {
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::fixtures::{self, Waypoint};
+
+    /// Read the waypoints of a CompeGPS file, taking the name from the description.
+    fn read(wpt: &str) -> Vec<Waypoint> {
+        let coordinate = |value: &str, negative: char| -> f64 {
+            let (degrees, hemisphere) = value.split_once('º').unwrap();
+            let degrees: f64 = degrees.parse().unwrap();
+            match hemisphere.chars().next() {
+                Some(c) if c == negative => -degrees,
+                _ => degrees,
+            }
+        };
+
+        wpt.split("\r\n")
+            .filter_map(|line| line.strip_prefix("W  "))
+            .map(|line| {
+                let fields: Vec<&str> = line.splitn(8, ' ').collect();
+                let description = fields[7];
+                let name = match description.rsplit_once(" (") {
+                    Some((name, _)) if description.ends_with(')') => name,
+                    _ => description,
+                };
+
+                Waypoint {
+                    name: name.to_owned(),
+                    latitude: coordinate(fields[2], 'S'),
+                    longitude: coordinate(fields[3], 'W'),
+                    altitude_m: Some(fields[6].parse::<f64>().unwrap() as i32),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let wpt = write(&fixtures::takeoffs());
+        // Missing altitudes are written as 0
+        let expected: Vec<Waypoint> = fixtures::waypoints()
+            .into_iter()
+            .map(|waypoint| Waypoint {
+                altitude_m: waypoint.altitude_m.or(Some(0)),
+                ..waypoint
+            })
+            .collect();
+
+        fixtures::assert_same(&read(&wpt), &expected, 1e-9);
+    }
+
+    #[test]
+    fn writes_names_and_dates() {
+        let wpt = write(&fixtures::takeoffs());
+        let lines: Vec<&str> = wpt.split("\r\n").collect();
+
+        assert_eq!(lines[0], "G  WGS 84");
+        assert!(lines[2].starts_with("W  Høyanger_-_Flyplassen A 61.2195000000ºN 6.0712000000ºE 15-JUL-24 10:30:00 790.000000 "));
+        assert!(lines[3].starts_with("W  Rock_&_Roll_<Top>_\"West\",_Ridge A 33.9128000000ºS "));
+        assert!(lines[5].contains(&format!(" {NO_DATE} ")));
+        assert_eq!(waypoint_name("  "), "-");
+    }
+}
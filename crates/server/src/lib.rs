@@ -1,17 +1,23 @@
+mod cache;
 mod database;
 mod error;
+mod forecast;
+mod formats;
 mod routers;
+mod stations;
+pub mod validation;
 
 pub use database::connection;
 pub use database::helpers;
 pub use database::models;
 pub use error::ServerError;
+pub use formats::geojson;
 
 use axum::{Extension, Router};
 use rand::{RngCore, SeedableRng};
 use rand_chacha::{rand_core::OsRng, ChaCha8Rng};
 use std::sync::{Arc, Mutex};
-use tower_http::{cors::CorsLayer, trace::TraceLayer};
+use tower_http::{compression::CompressionLayer, cors::CorsLayer, trace::TraceLayer};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
 pub async fn run() -> Result<(), ServerError> {
@@ -26,12 +32,36 @@ pub async fn run() -> Result<(), ServerError> {
     let listener = tokio::net::TcpListener::bind("0.0.0.0:5050").await?;
     let random = Arc::new(Mutex::new(ChaCha8Rng::seed_from_u64(OsRng.next_u64())));
     let pool = database::connection::pool().await?;
+    let forecasts = forecast::create(
+        &std::env::var("FORECAST_PROVIDER").unwrap_or(forecast::open_meteo::NAME.to_owned()),
+        &forecast::Config::from_env(),
+    )
+    .map_err(ServerError::INTERNAL_SERVER_ERROR)?;
+
+    // Poll weather stations of providers in `STATION_PROVIDERS`, comma separated
+    let station_config = stations::Config::from_env();
+    let station_providers = std::env::var("STATION_PROVIDERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| stations::create(name, &station_config))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ServerError::INTERNAL_SERVER_ERROR)?;
+    if !station_providers.is_empty() {
+        stations::spawn(pool.clone(), station_providers);
+    }
+
     let app = Router::new()
         .merge(routers::default::router())
         .merge(routers::api::router())
+        .merge(routers::tiles::router())
+        .layer(CompressionLayer::new())
         .layer(CorsLayer::very_permissive()) // TODO
         .layer(TraceLayer::new_for_http())
         .layer(Extension(pool))
+        .layer(Extension(cache::ResponseCache::default()))
+        .layer(Extension(forecasts))
         .layer(Extension(random));
 
     // Start listening
@@ -0,0 +1,125 @@
+use super::version::Version;
+use crate::{
+    cache::ResponseCache,
+    database::{auth, helpers},
+    error::ServerError,
+    models::{ChangeNote, Data},
+    validation::duplicates::{self, Candidate},
+};
+use axum::{routing::post, Extension, Json, Router};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/:version/takeoffs/duplicates", post(post_duplicates))
+        .route(
+            "/api/:version/takeoffs/duplicates/dismiss",
+            post(post_duplicates_dismiss),
+        )
+        .route("/api/:version/takeoffs/merge", post(post_merge))
+}
+
+/// Thresholds for finding duplicates, see [`duplicates::Config`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct DuplicatesQuery {
+    max_distance: Option<f64>,
+    min_similarity: Option<f64>,
+}
+
+/// A pair of takeoffs.
+#[derive(Debug, Deserialize)]
+struct TakeoffPair {
+    a: i32,
+    b: i32,
+}
+
+/// Takeoffs to merge.
+#[derive(Debug, Deserialize)]
+struct Merge {
+    /// Id of the takeoff to keep.
+    keep: i32,
+    /// Id of the takeoff to merge into `keep` and delete.
+    remove: i32,
+}
+
+/// Get candidate duplicates, best first.
+///
+/// Requires the `editor` role.
+async fn post_duplicates(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<DuplicatesQuery>>,
+) -> Result<Json<Vec<Candidate>>, ServerError> {
+    auth::require_role((*pool).clone(), &data.session, auth::EDITOR).await?;
+
+    let default = duplicates::Config::default();
+    let config = duplicates::Config {
+        max_distance: data.value.max_distance.unwrap_or(default.max_distance),
+        min_similarity: data.value.min_similarity.unwrap_or(default.min_similarity),
+    };
+    let takeoffs = helpers::get_takeoffs_without_images(&*pool).await?;
+    let dismissed = helpers::get_dismissed_duplicates(&*pool).await?;
+
+    Ok(Json(duplicates::find_candidates(
+        &takeoffs, &config, &dismissed,
+    )))
+}
+
+/// Marks a pair of takeoffs as not duplicates.
+///
+/// Requires the `editor` role.
+async fn post_duplicates_dismiss(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<TakeoffPair>>,
+) -> Result<(), ServerError> {
+    auth::require_role((*pool).clone(), &data.session, auth::EDITOR).await?;
+
+    let TakeoffPair { a, b } = data.value;
+    if a == b {
+        return Err(ServerError::BAD_REQUEST(
+            "can't dismiss a takeoff with itself",
+        ));
+    }
+    helpers::dismiss_duplicate(&*pool, a, b).await?;
+
+    Ok(())
+}
+
+/// Merges a takeoff into another and deletes it.
+///
+/// Requires the `editor` role.
+async fn post_merge(
+    _version: Version,
+    pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    Json(data): Json<Data<Merge>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::require_role((*pool).clone(), &data.session, auth::EDITOR).await?;
+
+    let Merge { keep, remove } = data.value;
+    if keep == remove {
+        return Err(ServerError::BAD_REQUEST(
+            "can't merge a takeoff with itself",
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+    let keep = helpers::get_takeoff(&mut *tx, keep)
+        .await?
+        .ok_or(ServerError::NOT_FOUND(format!("no takeoff {keep}")))?;
+    let remove = helpers::get_takeoff(&mut *tx, remove)
+        .await?
+        .ok_or(ServerError::NOT_FOUND(format!("no takeoff {remove}")))?;
+    let note = ChangeNote {
+        user_id: Some(user_id),
+        comment: Some(format!("Merged {} into {}", remove.id, keep.id)),
+    };
+    helpers::merge_takeoffs(&mut tx, keep, remove, &note).await?;
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(())
+}
@@ -0,0 +1,258 @@
+use super::version::Version;
+use crate::{
+    cache::CacheControl,
+    database::{auth, helpers},
+    error::ServerError,
+    formats::igc,
+    models::{Data, Flight, FlightStats, FlightTrack, NewFlight},
+    validation::geo,
+};
+use axum::{
+    extract::{DefaultBodyLimit, Path},
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/api/:version/flights", post(post_flights))
+        .route("/api/:version/flights/mine", post(post_flights_mine))
+        .route(
+            "/api/:version/flights/:id",
+            put(put_flight).delete(delete_flight),
+        )
+        .route(
+            "/api/:version/flights/:id/track",
+            post(post_flight_track).layer(DefaultBodyLimit::max(MAX_TRACK_SIZE)),
+        )
+        .route(
+            "/api/:version/takeoffs/:id/flights/stats",
+            get(get_flight_stats).layer(CacheControl::Short.layer()),
+        )
+}
+
+/// Maximum duration of a flight in minutes.
+const MAX_DURATION_MIN: i32 = 24 * 60;
+/// Maximum distance of a flight in kilometers.
+const MAX_DISTANCE_KM: f64 = 1000.0;
+/// Maximum length of text fields in characters.
+const MAX_TEXT_LENGTH: usize = 5000;
+/// Maximum size of a track upload in bytes, enough for a day of fixes every second.
+const MAX_TRACK_SIZE: usize = 16 * 1024 * 1024;
+/// Maximum distance in meters between the launch of a track and its takeoff.
+const MAX_LAUNCH_DISTANCE_M: f64 = 2000.0;
+
+/// An IGC file.
+#[derive(Debug, Deserialize)]
+struct TrackUpload {
+    igc: String,
+}
+
+/// A parsed track and the flight it updated.
+#[derive(Debug, Serialize)]
+struct TrackResponse {
+    track: FlightTrack,
+    flight: Flight,
+}
+
+/// Logs a flight.
+///
+/// Requires a session.
+async fn post_flights(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<NewFlight>>,
+) -> Result<Json<Flight>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    validate_flight(&pool, &data.value).await?;
+    let flight = helpers::insert_flight(&*pool, user_id, &data.value).await?;
+
+    Ok(Json(flight))
+}
+
+/// Gets the flights of the user, latest first.
+///
+/// Requires a session.
+async fn post_flights_mine(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<()>>,
+) -> Result<Json<Vec<Flight>>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    Ok(Json(helpers::get_flights(&*pool, user_id).await?))
+}
+
+/// Replaces a flight.
+///
+/// Requires a session of the pilot.
+async fn put_flight(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<NewFlight>>,
+) -> Result<Json<Flight>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    validate_flight(&pool, &data.value).await?;
+    let flight = helpers::update_flight(&*pool, id, user_id, &data.value)
+        .await?
+        .ok_or(ServerError::NOT_FOUND(format!("no flight {id}")))?;
+
+    Ok(Json(flight))
+}
+
+/// Deletes a flight.
+///
+/// Requires a session of the pilot.
+async fn delete_flight(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    if !helpers::delete_flight(&*pool, id, user_id).await? {
+        return Err(ServerError::NOT_FOUND(format!("no flight {id}")));
+    }
+
+    Ok(())
+}
+
+/// Uploads the IGC track of a flight, replacing any previous one, see [`igc`].
+///
+/// The launch is matched to the closest takeoff within [`MAX_LAUNCH_DISTANCE_M`], and the date,
+/// duration, distance and takeoff of the flight are updated from the track.
+///
+/// Requires a session of the pilot.
+async fn post_flight_track(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<TrackUpload>>,
+) -> Result<Json<TrackResponse>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    if helpers::get_flight(&*pool, id, user_id).await?.is_none() {
+        return Err(ServerError::NOT_FOUND(format!("no flight {id}")));
+    }
+    let parsed = igc::parse(&data.value.igc)?;
+    let stats = parsed
+        .stats()
+        .ok_or(ServerError::BAD_REQUEST("no valid fixes (B records)"))?;
+    let takeoff = nearest_takeoff(&pool, stats.launch_latitude, stats.launch_longitude).await?;
+
+    let track = FlightTrack {
+        flight_id: id,
+        pilot: parsed.header.pilot,
+        glider: parsed.header.glider_type,
+        signed: parsed.signed,
+        points: i32::try_from(parsed.fixes.len())?,
+        started_at: stats.started_at,
+        landed_at: stats.landed_at,
+        duration_s: i32::try_from(stats.duration_s)?,
+        max_altitude_m: stats.max_altitude_m,
+        distance_km: stats.distance_km,
+        track_length_km: stats.track_length_km,
+        max_climb_ms: stats.max_climb_ms,
+        launch_latitude: stats.launch_latitude,
+        launch_longitude: stats.launch_longitude,
+        takeoff_id: takeoff.map(|(id, _)| id),
+        takeoff_distance_m: takeoff.map(|(_, distance)| distance),
+    };
+    let mut tx = pool.begin().await?;
+    let flight = helpers::replace_flight_track(&mut tx, &track, &data.value.igc).await?;
+    tx.commit().await?;
+
+    Ok(Json(TrackResponse { track, flight }))
+}
+
+/// Get the id of the closest takeoff within [`MAX_LAUNCH_DISTANCE_M`] of a launch, and its
+/// distance in meters.
+async fn nearest_takeoff(
+    pool: &PgPool,
+    latitude: f64,
+    longitude: f64,
+) -> Result<Option<(i32, f64)>, ServerError> {
+    let lat_span = geo::meters_to_latitude(MAX_LAUNCH_DISTANCE_M);
+    let lon_span = lat_span / latitude.to_radians().cos().max(0.01);
+    let bounds = (
+        longitude - lon_span,
+        latitude - lat_span,
+        longitude + lon_span,
+        latitude + lat_span,
+    );
+
+    Ok(helpers::get_takeoffs_in_bounds(pool, bounds)
+        .await?
+        .into_iter()
+        .filter_map(|takeoff| {
+            let distance =
+                geo::haversine(latitude, longitude, takeoff.latitude?, takeoff.longitude?);
+            Some((takeoff.id?, distance))
+        })
+        .filter(|(_, distance)| *distance <= MAX_LAUNCH_DISTANCE_M)
+        .min_by(|a, b| a.1.total_cmp(&b.1)))
+}
+
+/// Gets statistics of the flights logged from a takeoff: the number of flights and pilots, the
+/// average duration, and the best flight.
+async fn get_flight_stats(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+) -> Result<Json<FlightStats>, ServerError> {
+    if helpers::get_takeoff(&*pool, id).await?.is_none() {
+        return Err(ServerError::NOT_FOUND(format!("no takeoff {id}")));
+    }
+
+    let mut conn = pool.acquire().await?;
+    Ok(Json(helpers::get_flight_stats(&mut conn, id).await?))
+}
+
+/// Check that a flight is plausible and its takeoff exists.
+///
+/// Dates up to a day ahead of UTC are allowed, for time zones east of it.
+async fn validate_flight(pool: &PgPool, flight: &NewFlight) -> Result<(), ServerError> {
+    if !(1..=MAX_DURATION_MIN).contains(&flight.duration_min) {
+        return Err(ServerError::BAD_REQUEST(format!(
+            "duration_min must be between 1 and {MAX_DURATION_MIN}"
+        )));
+    }
+    if flight
+        .distance_km
+        .is_some_and(|distance| !(0.0..=MAX_DISTANCE_KM).contains(&distance))
+    {
+        return Err(ServerError::BAD_REQUEST(format!(
+            "distance_km must be between 0 and {MAX_DISTANCE_KM}"
+        )));
+    }
+    if flight.date > (Utc::now() + Duration::days(1)).date_naive() {
+        return Err(ServerError::BAD_REQUEST("date is in the future"));
+    }
+    let texts = [&flight.landing, &flight.notes, &flight.conditions];
+    if texts.iter().any(|text| {
+        text.as_ref()
+            .is_some_and(|text| text.chars().count() > MAX_TEXT_LENGTH)
+    }) {
+        return Err(ServerError::BAD_REQUEST(format!(
+            "landing, notes and conditions must be at most {MAX_TEXT_LENGTH} characters"
+        )));
+    }
+    if helpers::get_takeoff(pool, flight.takeoff_id)
+        .await?
+        .is_none()
+    {
+        return Err(ServerError::NOT_FOUND(format!(
+            "no takeoff {}",
+            flight.takeoff_id
+        )));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+use super::version::Version;
+use crate::{
+    cache::CacheControl,
+    database::helpers,
+    error::ServerError,
+    forecast::{
+        self,
+        flyability::{self, HourRating, Rating, Thresholds},
+        ForecastProvider,
+    },
+    models::{Forecast, GetTakeoff},
+    validation::geo,
+};
+use axum::{extract::Path, routing::get, Extension, Json, Router};
+use axum_extra::extract::Query;
+use chrono::{NaiveDate, Utc};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/api/:version/takeoffs/:id/forecast",
+            get(get_forecast).layer(CacheControl::Short.layer()),
+        )
+        .route(
+            "/api/:version/takeoffs/flyable",
+            get(get_flyable).layer(CacheControl::Short.layer()),
+        )
+}
+
+/// Gets the hourly wind forecast of a takeoff, fetched again when the cached one is stale.
+async fn get_forecast(
+    _version: Version,
+    pool: Extension<PgPool>,
+    provider: Extension<Arc<dyn ForecastProvider>>,
+    Path((_, id)): Path<(String, i32)>,
+) -> Result<Json<Forecast>, ServerError> {
+    let takeoff = helpers::get_takeoff(&*pool, id)
+        .await?
+        .ok_or(ServerError::NOT_FOUND(format!("no takeoff {id}")))?;
+
+    let forecast = forecast::get_forecast(
+        &pool,
+        provider.as_ref(),
+        id,
+        takeoff.latitude,
+        takeoff.longitude,
+    )
+    .await?;
+
+    Ok(Json(forecast))
+}
+
+/// Default search radius of flyable takeoffs in kilometers.
+const DEFAULT_RADIUS_KM: f64 = 50.0;
+/// Maximum search radius of flyable takeoffs in kilometers.
+const MAX_RADIUS_KM: f64 = 200.0;
+/// Maximum number of takeoffs rated, closest first, as each can need a forecast request.
+const MAX_SITES: usize = 50;
+/// Number of forecasts fetched at once.
+const CONCURRENT_FORECASTS: usize = 8;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct FlyableParams {
+    /// `YYYY-MM-DD`, today (UTC) if missing.
+    date: Option<String>,
+    /// `latitude,longitude` in decimal degrees.
+    near: Option<String>,
+    radius_km: f64,
+}
+
+impl Default for FlyableParams {
+    fn default() -> Self {
+        Self {
+            date: None,
+            near: None,
+            radius_km: DEFAULT_RADIUS_KM,
+        }
+    }
+}
+
+/// A takeoff with hours that work.
+#[derive(Debug, Serialize)]
+struct FlyableSite {
+    id: i32,
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    wind_dirs: Vec<String>,
+    distance_km: f64,
+    /// Best rating of the hours.
+    rating: Rating,
+    /// Flyable hours count twice as much as marginal hours.
+    score: u32,
+    /// Flyable and marginal daylight hours.
+    hours: Vec<HourRating>,
+}
+
+/// Gets takeoffs near a coordinate with flyable or marginal daylight hours on a date, best
+/// first, see [`flyability`].
+///
+/// Takeoffs without a forecast (e.g. if fetching it fails) are skipped.
+async fn get_flyable(
+    _version: Version,
+    pool: Extension<PgPool>,
+    provider: Extension<Arc<dyn ForecastProvider>>,
+    Query(params): Query<FlyableParams>,
+) -> Result<Json<Vec<FlyableSite>>, ServerError> {
+    let (latitude, longitude) = params
+        .near
+        .as_deref()
+        .and_then(|near| near.split_once(','))
+        .and_then(|(lat, lon)| {
+            Some((
+                lat.trim().parse::<f64>().ok()?,
+                lon.trim().parse::<f64>().ok()?,
+            ))
+        })
+        .filter(|(lat, lon)| lat.abs() <= 90.0 && lon.abs() <= 180.0)
+        .ok_or(ServerError::BAD_REQUEST(
+            "missing or invalid near, expected latitude,longitude",
+        ))?;
+    let date = match params.date.as_deref() {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .map_err(|_| ServerError::BAD_REQUEST(format!("invalid date {date}")))?,
+        None => Utc::now().date_naive(),
+    };
+    if !(params.radius_km > 0.0 && params.radius_km <= MAX_RADIUS_KM) {
+        return Err(ServerError::BAD_REQUEST(format!(
+            "radius_km must be above 0 and at most {MAX_RADIUS_KM}"
+        )));
+    }
+
+    // Takeoffs in a bounding box, then within the radius
+    let radius_m = params.radius_km * 1000.0;
+    let lat_span = geo::meters_to_latitude(radius_m);
+    let lon_span = lat_span / latitude.to_radians().cos().max(0.01);
+    let bounds = (
+        longitude - lon_span,
+        latitude - lat_span,
+        longitude + lon_span,
+        latitude + lat_span,
+    );
+    let mut sites: Vec<(GetTakeoff, f64)> = helpers::get_takeoffs_in_bounds(&*pool, bounds)
+        .await?
+        .into_iter()
+        .filter_map(|takeoff| {
+            let distance =
+                geo::haversine(latitude, longitude, takeoff.latitude?, takeoff.longitude?);
+            (distance <= radius_m).then_some((takeoff, distance))
+        })
+        .collect();
+    sites.sort_by(|a, b| a.1.total_cmp(&b.1));
+    sites.truncate(MAX_SITES);
+
+    let thresholds = Thresholds::default();
+    let mut out: Vec<FlyableSite> = stream::iter(sites)
+        .map(|(takeoff, distance)| {
+            let pool = &*pool;
+            let provider = provider.as_ref();
+            async move {
+                let (id, lat, lon) = (takeoff.id?, takeoff.latitude?, takeoff.longitude?);
+                let forecast = forecast::get_forecast(pool, provider, id, lat, lon)
+                    .await
+                    .inspect_err(|error| tracing::warn!("no forecast of takeoff {id}: {error}"))
+                    .ok()?;
+
+                let wind_dirs = takeoff.wind_dirs.unwrap_or_default();
+                let (start, end) = flyability::daylight(date, lon);
+                let hours: Vec<HourRating> = forecast
+                    .hours
+                    .iter()
+                    .filter(|hour| hour.time >= start && hour.time <= end)
+                    .map(|hour| flyability::rate_hour(hour, &wind_dirs, &thresholds))
+                    .filter(|hour| hour.rating != Rating::NoFly)
+                    .collect();
+
+                Some(FlyableSite {
+                    id,
+                    name: takeoff.name.unwrap_or_default(),
+                    latitude: lat,
+                    longitude: lon,
+                    wind_dirs,
+                    distance_km: distance / 1000.0,
+                    rating: hours.iter().map(|hour| hour.rating).max()?,
+                    score: flyability::score(&hours),
+                    hours,
+                })
+            }
+        })
+        .buffer_unordered(CONCURRENT_FORECASTS)
+        .filter_map(|site| async move { site })
+        .collect()
+        .await;
+    out.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(a.distance_km.total_cmp(&b.distance_km))
+    });
+
+    Ok(Json(out))
+}
@@ -10,7 +10,7 @@ pub fn router() -> Router {
 }
 
 async fn get_health(version: Version, pool: Extension<PgPool>) -> Result<String, ServerError> {
-    if let Ok(_) = pool.acquire().await {
+    if pool.acquire().await.is_ok() {
         Ok(format!("OK\n\nVersion: {:?}\nDatabase: OK", version))
     } else {
         Err(ServerError::INTERNAL_SERVER_ERROR(anyhow!(
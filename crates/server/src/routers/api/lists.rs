@@ -0,0 +1,291 @@
+use super::version::Version;
+use crate::{
+    cache::CacheControl,
+    database::{auth, helpers},
+    error::ServerError,
+    models::{Data, SharedTakeoffList, TakeoffList},
+};
+use axum::{
+    extract::Path,
+    routing::{get, post, put},
+    Extension, Json, Router,
+};
+use rand_chacha::ChaCha8Rng;
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::sync::{Arc, Mutex};
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/api/:version/takeoffs/:id/favorite",
+            put(put_favorite).delete(delete_favorite),
+        )
+        .route("/api/:version/favorites", post(post_favorites))
+        .route("/api/:version/lists", post(post_lists))
+        .route("/api/:version/lists/mine", post(post_lists_mine))
+        .route("/api/:version/lists/:id", put(put_list).delete(delete_list))
+        .route(
+            "/api/:version/lists/:id/takeoffs/:takeoff_id",
+            put(put_list_takeoff).delete(delete_list_takeoff),
+        )
+        .route(
+            "/api/:version/lists/:id/share",
+            post(post_list_share).delete(delete_list_share),
+        )
+        .route(
+            "/api/:version/lists/shared/:token",
+            get(get_shared_list).layer(CacheControl::Short.layer()),
+        )
+}
+
+/// Maximum length of list names in characters.
+const MAX_NAME_LENGTH: usize = 100;
+
+/// Name of a list.
+#[derive(Debug, Deserialize)]
+struct ListName {
+    name: String,
+}
+
+impl ListName {
+    /// Get the trimmed name.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the name is empty or too long.
+    fn validate(&self) -> Result<&str, ServerError> {
+        let name = self.name.trim();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(ServerError::BAD_REQUEST(format!(
+                "name must be 1 to {MAX_NAME_LENGTH} characters"
+            )));
+        }
+
+        Ok(name)
+    }
+}
+
+/// Stars a takeoff.
+///
+/// Requires a session.
+async fn put_favorite(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    require_takeoff(&pool, id).await?;
+    helpers::insert_favorite(&*pool, user_id, id).await?;
+
+    Ok(())
+}
+
+/// Unstars a takeoff.
+///
+/// Requires a session.
+async fn delete_favorite(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    helpers::delete_favorite(&*pool, user_id, id).await?;
+
+    Ok(())
+}
+
+/// Gets the ids of the favourite takeoffs of the user, latest first.
+///
+/// Use `favorite=true` with the takeoffs list for the takeoffs themselves.
+///
+/// Requires a session.
+async fn post_favorites(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<()>>,
+) -> Result<Json<Vec<i32>>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    Ok(Json(helpers::get_favorite_ids(&*pool, user_id).await?))
+}
+
+/// Creates an empty list of takeoffs.
+///
+/// Requires a session.
+async fn post_lists(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<ListName>>,
+) -> Result<Json<TakeoffList>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    let list_id = helpers::insert_takeoff_list(&*pool, user_id, data.value.validate()?).await?;
+
+    Ok(Json(get_list(&pool, list_id, user_id).await?))
+}
+
+/// Gets the lists of the user, oldest first.
+///
+/// Requires a session.
+async fn post_lists_mine(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<()>>,
+) -> Result<Json<Vec<TakeoffList>>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    Ok(Json(
+        helpers::get_takeoff_lists(&*pool, user_id, None).await?,
+    ))
+}
+
+/// Renames a list.
+///
+/// Requires a session of the owner.
+async fn put_list(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<ListName>>,
+) -> Result<Json<TakeoffList>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    let name = data.value.validate()?;
+    if !helpers::rename_takeoff_list(&*pool, id, user_id, name).await? {
+        return Err(ServerError::NOT_FOUND(format!("no list {id}")));
+    }
+
+    Ok(Json(get_list(&pool, id, user_id).await?))
+}
+
+/// Deletes a list, and its public link.
+///
+/// Requires a session of the owner.
+async fn delete_list(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    if !helpers::delete_takeoff_list(&*pool, id, user_id).await? {
+        return Err(ServerError::NOT_FOUND(format!("no list {id}")));
+    }
+
+    Ok(())
+}
+
+/// Adds a takeoff to a list.
+///
+/// Requires a session of the owner.
+async fn put_list_takeoff(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id, takeoff_id)): Path<(String, i32, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<Json<TakeoffList>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    get_list(&pool, id, user_id).await?;
+    require_takeoff(&pool, takeoff_id).await?;
+    helpers::insert_takeoff_list_item(&*pool, id, takeoff_id).await?;
+
+    Ok(Json(get_list(&pool, id, user_id).await?))
+}
+
+/// Removes a takeoff from a list.
+///
+/// Requires a session of the owner.
+async fn delete_list_takeoff(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id, takeoff_id)): Path<(String, i32, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<Json<TakeoffList>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    get_list(&pool, id, user_id).await?;
+    helpers::delete_takeoff_list_item(&*pool, id, takeoff_id).await?;
+
+    Ok(Json(get_list(&pool, id, user_id).await?))
+}
+
+/// Shares a list with a public read-only link, keeping the existing one if it's shared already.
+///
+/// Requires a session of the owner.
+async fn post_list_share(
+    _version: Version,
+    pool: Extension<PgPool>,
+    random: Extension<Arc<Mutex<ChaCha8Rng>>>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<Json<TakeoffList>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    let list = get_list(&pool, id, user_id).await?;
+    if list.share_token.is_some() {
+        return Ok(Json(list));
+    }
+    let token = auth::create_token((*random).clone());
+    helpers::set_takeoff_list_share_token(&*pool, id, user_id, Some(&token)).await?;
+
+    Ok(Json(get_list(&pool, id, user_id).await?))
+}
+
+/// Stops sharing a list, so its public link stops working.
+///
+/// Requires a session of the owner.
+async fn delete_list_share(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<()>>,
+) -> Result<Json<TakeoffList>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    if !helpers::set_takeoff_list_share_token(&*pool, id, user_id, None).await? {
+        return Err(ServerError::NOT_FOUND(format!("no list {id}")));
+    }
+
+    Ok(Json(get_list(&pool, id, user_id).await?))
+}
+
+/// Gets a shared list and its takeoffs by the token of its public link.
+async fn get_shared_list(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, token)): Path<(String, String)>,
+) -> Result<Json<SharedTakeoffList>, ServerError> {
+    let mut conn = pool.acquire().await?;
+    let list = helpers::get_shared_takeoff_list(&mut conn, &token)
+        .await?
+        .ok_or(ServerError::NOT_FOUND("no shared list"))?;
+
+    Ok(Json(list))
+}
+
+/// Get a list of a user.
+///
+/// # Errors
+///
+/// This function will return an error if the user has no such list.
+async fn get_list(pool: &PgPool, list_id: i32, user_id: i32) -> Result<TakeoffList, ServerError> {
+    helpers::get_takeoff_lists(pool, user_id, Some(list_id))
+        .await?
+        .pop()
+        .ok_or(ServerError::NOT_FOUND(format!("no list {list_id}")))
+}
+
+/// Check that a takeoff exists.
+async fn require_takeoff(pool: &PgPool, id: i32) -> Result<(), ServerError> {
+    match helpers::get_takeoff(pool, id).await? {
+        Some(_) => Ok(()),
+        None => Err(ServerError::NOT_FOUND(format!("no takeoff {id}"))),
+    }
+}
@@ -1,14 +1,32 @@
+mod duplicates;
+mod flights;
+mod forecasts;
 mod health;
+mod lists;
+mod quality;
+mod revisions;
+mod stations;
+mod suggestions;
 mod takeoffs;
 mod users;
 mod version;
 
+use crate::cache::CacheControl;
 use axum::Router;
 
-// RESTish
+// RESTish, not cached unless a route says otherwise
 pub fn router() -> Router {
     Router::new()
         .merge(users::router())
         .merge(takeoffs::router())
+        .merge(duplicates::router())
+        .merge(revisions::router())
+        .merge(forecasts::router())
+        .merge(stations::router())
+        .merge(suggestions::router())
+        .merge(lists::router())
+        .merge(flights::router())
+        .merge(quality::router())
         .merge(health::router())
+        .layer(CacheControl::NoStore.layer())
 }
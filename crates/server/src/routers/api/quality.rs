@@ -0,0 +1,40 @@
+use super::version::Version;
+use crate::{
+    cache::CacheControl,
+    database::helpers,
+    error::ServerError,
+    models::QualityIssue,
+    validation::{Rule, Severity},
+};
+use axum::{routing::get, Extension, Json, Router};
+use axum_extra::extract::Query;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+pub fn router() -> Router {
+    Router::new().route(
+        "/api/:version/quality_issues",
+        get(get_quality_issues).layer(CacheControl::Short.layer()),
+    )
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct GetQualityIssuesParams {
+    takeoff_id: Option<i32>,
+    rule: Option<Rule>,
+    severity: Option<Severity>,
+}
+
+/// Get quality issues flagged by `scraper validate --flag`.
+async fn get_quality_issues(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Query(params): Query<GetQualityIssuesParams>,
+) -> Result<Json<Vec<QualityIssue>>, ServerError> {
+    let issues =
+        helpers::get_quality_issues(&*pool, params.takeoff_id, params.rule, params.severity)
+            .await?;
+
+    Ok(Json(issues))
+}
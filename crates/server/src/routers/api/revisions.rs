@@ -0,0 +1,91 @@
+use super::version::Version;
+use crate::{
+    cache::{CacheControl, ResponseCache},
+    database::{auth, helpers},
+    error::ServerError,
+    models::{ChangeNote, Data, Revision, Takeoff},
+};
+use axum::{
+    extract::Path,
+    routing::{get, post},
+    Extension, Json, Router,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/api/:version/takeoffs/:id/history",
+            get(get_history).layer(CacheControl::Short.layer()),
+        )
+        .route("/api/:version/takeoffs/:id/revert", post(post_revert))
+}
+
+/// A revision to revert to, with an optional comment.
+#[derive(Debug, Deserialize)]
+struct Revert {
+    revision: i32,
+    comment: Option<String>,
+}
+
+/// Get the revisions of a takeoff, newest first.
+async fn get_history(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+) -> Result<Json<Vec<Revision>>, ServerError> {
+    let history = helpers::get_takeoff_history(&*pool, id).await?;
+    if history.is_empty() {
+        return Err(ServerError::NOT_FOUND(format!(
+            "no history of takeoff {id}"
+        )));
+    }
+
+    Ok(Json(history))
+}
+
+/// Reverts a takeoff to the snapshot of a revision, restoring it if it was deleted.
+///
+/// Snapshots don't have images, so the current image is kept.
+///
+/// Requires the `editor` role.
+async fn post_revert(
+    _version: Version,
+    pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<Revert>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::require_role((*pool).clone(), &data.session, auth::EDITOR).await?;
+
+    let Revert { revision, comment } = data.value;
+    let revision = helpers::get_revision(&*pool, revision)
+        .await?
+        .filter(|found| found.takeoff_id == id)
+        .ok_or(ServerError::NOT_FOUND(format!(
+            "no revision {revision} of takeoff {id}"
+        )))?;
+    let mut takeoff: Takeoff = serde_json::from_value(revision.snapshot)?;
+
+    let note = ChangeNote {
+        user_id: Some(user_id),
+        comment: Some(match comment {
+            Some(comment) => format!("Reverted to revision {}: {comment}", revision.id),
+            None => format!("Reverted to revision {}", revision.id),
+        }),
+    };
+
+    let mut tx = pool.begin().await?;
+    match helpers::get_takeoff(&mut *tx, id).await? {
+        Some(current) => {
+            takeoff.image = current.image;
+            helpers::update_takeoff(&mut *tx, &takeoff, &note).await?;
+        }
+        None => helpers::restore_takeoff(&mut *tx, &takeoff, &note).await?,
+    }
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use super::version::Version;
+use crate::{
+    cache::CacheControl,
+    database::helpers,
+    error::ServerError,
+    models::{StationReading, TakeoffStation},
+    stations::RETENTION_DAYS,
+};
+use axum::{extract::Path, routing::get, Extension, Json, Router};
+use axum_extra::extract::Query;
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+pub fn router() -> Router {
+    Router::new().route(
+        "/api/:version/takeoffs/:id/stations",
+        get(get_takeoff_stations).layer(CacheControl::Short.layer()),
+    )
+}
+
+/// Default hours of recent readings.
+const DEFAULT_HOURS: i64 = 3;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct StationsParams {
+    /// Hours of recent readings, up to the retention of readings.
+    hours: i64,
+}
+
+impl Default for StationsParams {
+    fn default() -> Self {
+        Self {
+            hours: DEFAULT_HOURS,
+        }
+    }
+}
+
+/// Gets the weather stations nearest to a takeoff, with their current and recent readings.
+async fn get_takeoff_stations(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Query(params): Query<StationsParams>,
+) -> Result<Json<Vec<TakeoffStation>>, ServerError> {
+    if !(1..=RETENTION_DAYS * 24).contains(&params.hours) {
+        return Err(ServerError::BAD_REQUEST(format!(
+            "hours must be between 1 and {}",
+            RETENTION_DAYS * 24
+        )));
+    }
+    helpers::get_takeoff(&*pool, id)
+        .await?
+        .ok_or(ServerError::NOT_FOUND(format!("no takeoff {id}")))?;
+
+    let stations = helpers::get_takeoff_stations(&*pool, id).await?;
+    let station_ids: Vec<i32> = stations.iter().map(|(station, _)| station.id).collect();
+    let since = Utc::now() - Duration::hours(params.hours);
+    let mut readings: HashMap<i32, Vec<StationReading>> = HashMap::new();
+    for (station_id, reading) in helpers::get_station_readings(&*pool, &station_ids, since).await? {
+        readings.entry(station_id).or_default().push(reading);
+    }
+
+    let out = stations
+        .into_iter()
+        .map(|(station, distance)| {
+            let readings = readings.remove(&station.id).unwrap_or_default();
+            TakeoffStation {
+                distance_km: distance / 1000.0,
+                current: readings.first().cloned(),
+                readings,
+                station,
+            }
+        })
+        .collect();
+
+    Ok(Json(out))
+}
@@ -0,0 +1,303 @@
+use super::version::Version;
+use crate::{
+    cache::ResponseCache,
+    database::{auth, helpers},
+    error::ServerError,
+    models::{ChangeNote, Data, Suggestion, SuggestionStatus, TakeoffChanges},
+    validation::WIND_DIRS,
+};
+use axum::{extract::Path, routing::post, Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::PgPool;
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/api/:version/takeoffs/:id/suggestions",
+            post(post_takeoff_suggestions),
+        )
+        .route("/api/:version/suggestions", post(post_suggestions))
+        .route(
+            "/api/:version/suggestions/:id/approve",
+            post(post_suggestion_approve),
+        )
+        .route(
+            "/api/:version/suggestions/:id/reject",
+            post(post_suggestion_reject),
+        )
+}
+
+/// Roles allowed to review suggestions.
+const REVIEWERS: [&str; 2] = [auth::MODERATOR, auth::EDITOR];
+
+/// Suggested changes with an optional comment.
+#[derive(Debug, Deserialize)]
+struct NewSuggestion {
+    changes: TakeoffChanges,
+    comment: Option<String>,
+}
+
+/// Status of suggestions to get.
+#[derive(Debug, Deserialize)]
+struct SuggestionsQuery {
+    #[serde(default = "pending")]
+    status: SuggestionStatus,
+}
+
+fn pending() -> SuggestionStatus {
+    SuggestionStatus::Pending
+}
+
+/// An optional comment of a reviewer.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Review {
+    comment: Option<String>,
+}
+
+/// A suggestion and the fields it changes.
+#[derive(Debug, Serialize)]
+struct ReviewSuggestion {
+    #[serde(flatten)]
+    suggestion: Suggestion,
+    diff: Vec<FieldDiff>,
+}
+
+/// A field changed by a suggestion.
+#[derive(Debug, Serialize)]
+struct FieldDiff {
+    field: String,
+    current: Value,
+    suggested: Value,
+}
+
+/// Suggests changes to a takeoff.
+///
+/// Requires a session.
+async fn post_takeoff_suggestions(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<NewSuggestion>>,
+) -> Result<Json<i32>, ServerError> {
+    let user_id = auth::check_session((*pool).clone(), &data.session).await?;
+
+    let NewSuggestion { changes, comment } = data.value;
+    validate_changes(&changes)?;
+    if helpers::get_takeoff(&*pool, id).await?.is_none() {
+        return Err(ServerError::NOT_FOUND(format!("no takeoff {id}")));
+    }
+    let suggestion_id =
+        helpers::insert_suggestion(&*pool, id, user_id, &changes, comment.as_deref()).await?;
+
+    Ok(Json(suggestion_id))
+}
+
+/// Gets suggestions with a status (`pending` by default) and their diffs, oldest first.
+///
+/// Requires the `moderator` or `editor` role.
+async fn post_suggestions(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Json(data): Json<Data<SuggestionsQuery>>,
+) -> Result<Json<Vec<ReviewSuggestion>>, ServerError> {
+    auth::require_any_role((*pool).clone(), &data.session, &REVIEWERS).await?;
+
+    let mut out = Vec::new();
+    for suggestion in helpers::get_suggestions(&*pool, data.value.status).await? {
+        let current = match helpers::get_takeoff(&*pool, suggestion.takeoff_id).await? {
+            Some(mut takeoff) => {
+                takeoff.image = None;
+                serde_json::to_value(takeoff)?
+            }
+            None => Value::Null,
+        };
+        let diff = match &suggestion.changes {
+            Value::Object(changes) => changes
+                .iter()
+                .map(|(field, suggested)| FieldDiff {
+                    field: field.clone(),
+                    current: current.get(field).cloned().unwrap_or_default(),
+                    suggested: suggested.clone(),
+                })
+                .filter(|diff| diff.current != diff.suggested)
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        out.push(ReviewSuggestion { suggestion, diff });
+    }
+
+    Ok(Json(out))
+}
+
+/// Approves a pending suggestion and applies it to the takeoff, attributed to its author.
+///
+/// Requires the `moderator` or `editor` role.
+async fn post_suggestion_approve(
+    _version: Version,
+    pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<Review>>,
+) -> Result<(), ServerError> {
+    let reviewer_id = auth::require_any_role((*pool).clone(), &data.session, &REVIEWERS).await?;
+
+    let mut tx = pool.begin().await?;
+    let suggestion = helpers::review_suggestion(
+        &mut *tx,
+        id,
+        SuggestionStatus::Approved,
+        reviewer_id,
+        data.value.comment.as_deref(),
+    )
+    .await?
+    .ok_or(ServerError::NOT_FOUND(format!(
+        "no pending suggestion {id}"
+    )))?;
+
+    let mut takeoff = helpers::get_takeoff(&mut *tx, suggestion.takeoff_id)
+        .await?
+        .ok_or(ServerError::NOT_FOUND(format!(
+            "no takeoff {}",
+            suggestion.takeoff_id
+        )))?;
+    let changes: TakeoffChanges = serde_json::from_value(suggestion.changes)?;
+    changes.apply(&mut takeoff);
+
+    let note = ChangeNote {
+        user_id: Some(suggestion.user_id),
+        comment: Some(match suggestion.comment {
+            Some(comment) => format!("Suggestion {id}: {comment}"),
+            None => format!("Suggestion {id}"),
+        }),
+    };
+    helpers::update_takeoff(&mut *tx, &takeoff, &note).await?;
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(())
+}
+
+/// Rejects a pending suggestion.
+///
+/// Requires the `moderator` or `editor` role.
+async fn post_suggestion_reject(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<Review>>,
+) -> Result<(), ServerError> {
+    let reviewer_id = auth::require_any_role((*pool).clone(), &data.session, &REVIEWERS).await?;
+
+    helpers::review_suggestion(
+        &*pool,
+        id,
+        SuggestionStatus::Rejected,
+        reviewer_id,
+        data.value.comment.as_deref(),
+    )
+    .await?
+    .ok_or(ServerError::NOT_FOUND(format!(
+        "no pending suggestion {id}"
+    )))?;
+
+    Ok(())
+}
+
+/// Check that suggested changes aren't empty and have valid values.
+fn validate_changes(changes: &TakeoffChanges) -> Result<(), ServerError> {
+    if changes.to_map().is_empty() {
+        return Err(ServerError::BAD_REQUEST("no changes"));
+    }
+    if changes
+        .name
+        .as_ref()
+        .is_some_and(|name| name.trim().is_empty())
+    {
+        return Err(ServerError::BAD_REQUEST("name can't be empty"));
+    }
+    if changes
+        .latitude
+        .is_some_and(|latitude| !(-90.0..=90.0).contains(&latitude))
+    {
+        return Err(ServerError::BAD_REQUEST("latitude out of range"));
+    }
+    if changes
+        .longitude
+        .is_some_and(|longitude| !(-180.0..=180.0).contains(&longitude))
+    {
+        return Err(ServerError::BAD_REQUEST("longitude out of range"));
+    }
+    if let Some(dir) = changes
+        .wind_dirs
+        .iter()
+        .flatten()
+        .find(|dir| !WIND_DIRS.contains(&dir.as_str()))
+    {
+        return Err(ServerError::BAD_REQUEST(format!(
+            "invalid wind direction {dir}"
+        )));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Changes with only new wind directions.
+    fn wind_dirs(dirs: &[&str]) -> TakeoffChanges {
+        TakeoffChanges {
+            wind_dirs: Some(dirs.iter().map(|dir| dir.to_string()).collect()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn validates_wind_dirs() {
+        assert!(validate_changes(&wind_dirs(&WIND_DIRS)).is_ok());
+        assert!(validate_changes(&wind_dirs(&["NNE", "WSW"])).is_ok());
+
+        let err = validate_changes(&wind_dirs(&["N", "NNX"])).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error 400 Bad Request: invalid wind direction NNX"
+        );
+    }
+
+    #[test]
+    fn validates_fields() {
+        let invalid = [
+            (TakeoffChanges::default(), "no changes"),
+            (
+                TakeoffChanges {
+                    name: Some(" ".to_owned()),
+                    ..Default::default()
+                },
+                "name can't be empty",
+            ),
+            (
+                TakeoffChanges {
+                    latitude: Some(-90.5),
+                    ..Default::default()
+                },
+                "latitude out of range",
+            ),
+            (
+                TakeoffChanges {
+                    longitude: Some(180.5),
+                    ..Default::default()
+                },
+                "longitude out of range",
+            ),
+        ];
+
+        for (changes, message) in invalid {
+            let err = validate_changes(&changes).unwrap_err();
+            assert_eq!(err.to_string(), format!("Error 400 Bad Request: {message}"));
+        }
+    }
+}
@@ -1,21 +1,62 @@
 use super::version::Version;
 use crate::{
-    database::helpers,
+    cache::{CacheControl, ResponseCache, Validators},
+    database::{auth, helpers},
     error::ServerError,
-    models::{Data, GetTakeoff, NewTakeoff},
+    formats::{cup, escape_xml, geojson, gpx, kml, wpt},
+    models::{ChangeNote, Data, GetTakeoff, NewTakeoff, SearchResult, Session, Takeoff},
 };
 use axum::{
-    routing::{get, post},
+    body::{Body, Bytes},
+    extract::OriginalUri,
+    extract::Path,
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
     Extension, Json, Router,
 };
 use axum_extra::extract::Query;
-use serde::Deserialize;
-use sqlx::PgPool;
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::io;
+use tokio::sync::mpsc;
 
 pub fn router() -> Router {
     Router::new()
         .route("/api/:version/takeoffs", get(get_takeoffs))
         .route("/api/:version/takeoffs", post(post_takeoffs))
+        .route("/api/:version/takeoffs/import", post(post_takeoffs_import))
+        .route(
+            "/api/:version/takeoffs/favorites",
+            post(post_takeoffs_favorites),
+        )
+        .route(
+            "/api/:version/takeoffs/search",
+            get(get_takeoffs_search).layer(CacheControl::Short.layer()),
+        )
+        .route("/api/:version/takeoffs/changes", get(get_takeoffs_changes))
+        .route(
+            "/api/:version/takeoffs/:id",
+            put(put_takeoff).delete(delete_takeoff),
+        )
+}
+
+/// Response format.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    #[default]
+    Json,
+    /// Newline delimited JSON, a takeoff per line.
+    NdJson,
+    GeoJson,
+    Kml,
+    Gpx,
+    Wpt,
+    Cup,
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,7 +67,16 @@ struct GetTakeoffsParams {
     limit: i64,
     region: String,
     fields: Vec<String>,
-    count: bool,
+    format: Format,
+    /// Column to sort by, descending if prefixed with `-` (e.g. `-updated_at`).
+    sort: Option<String>,
+    /// Inclusive bounds, as RFC 3339 timestamps or `YYYY-MM-DD` dates (midnight UTC).
+    created_after: Option<String>,
+    created_before: Option<String>,
+    updated_after: Option<String>,
+    updated_before: Option<String>,
+    /// Only favourites of the user, requires a session.
+    favorite: bool,
 }
 
 impl Default for GetTakeoffsParams {
@@ -37,46 +87,724 @@ impl Default for GetTakeoffsParams {
             limit: i64::MAX,
             region: "%".to_owned(),
             fields: Vec::default(),
-            count: false,
+            format: Format::default(),
+            sort: None,
+            created_after: None,
+            created_before: None,
+            updated_after: None,
+            updated_before: None,
+            favorite: false,
         }
     }
 }
 
+/// Lists takeoffs, with `ETag` and `Last-Modified` of the whole dataset.
+///
+/// Responds with `304 Not Modified` to matching conditional requests, and from the response
+/// cache if the dataset hasn't changed.
+///
+/// With `favorite=true`, only favourites of the user are listed and the request needs a session
+/// in its body, see [`favorite_takeoffs`].
 async fn get_takeoffs(
-    version: Version,
+    _version: Version,
     pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    uri: OriginalUri,
+    headers: HeaderMap,
     Query(params): Query<GetTakeoffsParams>,
-) -> Result<Json<Vec<GetTakeoff>>, ServerError> {
-    let fields = params.fields.join(", ");
-    let fields = if fields.is_empty() { "*".to_owned() } else { fields };
-
-    // TODO: bind on fields?
-    let out = if let Some(id) = params.id {
-        sqlx::query_as(&format!("SELECT {fields} FROM takeoffs WHERE id = $1"))
-            .bind(id)
-            .fetch_optional(&*pool)
-            .await?
-            .map_or(Vec::new(), |v| vec![v])
-    } else {
-        sqlx::query_as(&format!(
-            "SELECT {fields} FROM takeoffs WHERE region LIKE $1 LIMIT $2 OFFSET $3"
-        ))
-        .bind(params.region)
-        .bind(params.limit)
-        .bind((params.page - 1) * params.limit)
-        .fetch_all(&*pool)
-        .await?
+    data: Option<Json<Data<()>>>,
+) -> Result<Response, ServerError> {
+    if params.favorite {
+        let session = data.and_then(|Json(data)| data.session);
+        return favorite_takeoffs(&pool, params, &session).await;
+    }
+
+    let version = helpers::get_dataset_version(&*pool).await?;
+    let validators = Validators::new(&version);
+    if validators.not_modified(&headers) {
+        return Ok(validators.not_modified_response(CacheControl::Revalidate));
+    }
+    let key = uri.to_string();
+    if let Some(response) = cache.get(&key, version.version) {
+        return Ok(response);
+    }
+
+    let query = takeoffs_query(&params, None)?;
+    let mut response = match params.format {
+        Format::Json | Format::NdJson => stream_takeoffs(&pool, query, params.format).await?,
+        format => render_takeoffs(format, fetch_takeoffs(&pool, query).await?),
+    };
+    validators.apply(response.headers_mut());
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, CacheControl::Revalidate.value());
+
+    Ok(cache.insert(&key, version.version, response))
+}
+
+/// Lists the favourite takeoffs of the user, like the takeoffs list with `favorite=true`.
+///
+/// For clients that can't send a body with `GET` (e.g. `fetch`).
+///
+/// Requires a session.
+async fn post_takeoffs_favorites(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Query(params): Query<GetTakeoffsParams>,
+    Json(data): Json<Data<()>>,
+) -> Result<Response, ServerError> {
+    favorite_takeoffs(&pool, params, &data.session).await
+}
+
+/// List the favourite takeoffs of the user of `session`.
+///
+/// These responses are per user, so they're never cached.
+async fn favorite_takeoffs(
+    pool: &PgPool,
+    params: GetTakeoffsParams,
+    session: &Option<Session>,
+) -> Result<Response, ServerError> {
+    let user_id = auth::check_session(pool.clone(), session).await?;
+
+    let query = takeoffs_query(&params, Some(user_id))?;
+    match params.format {
+        Format::Json | Format::NdJson => stream_takeoffs(pool, query, params.format).await,
+        format => Ok(render_takeoffs(format, fetch_takeoffs(pool, query).await?)),
+    }
+}
+
+/// Content type of newline delimited JSON.
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Render takeoffs in a format that needs all of them at once.
+fn render_takeoffs(format: Format, takeoffs: Vec<GetTakeoff>) -> Response {
+    match format {
+        Format::Json | Format::NdJson => Json(takeoffs).into_response(),
+        Format::GeoJson => (
+            [(header::CONTENT_TYPE, geojson::CONTENT_TYPE)],
+            Json(geojson::to_feature_collection(takeoffs)),
+        )
+            .into_response(),
+        Format::Kml => attachment(kml::CONTENT_TYPE, "takeoffs.kml", kml::write(&takeoffs)),
+        Format::Gpx => attachment(gpx::CONTENT_TYPE, "takeoffs.gpx", gpx::write(&takeoffs)),
+        Format::Wpt => attachment(wpt::CONTENT_TYPE, "takeoffs.wpt", wpt::write(&takeoffs)),
+        Format::Cup => attachment(cup::CONTENT_TYPE, "takeoffs.cup", cup::write(&takeoffs)),
+    }
+}
+
+/// Stream takeoffs as JSON or NDJSON as they're fetched, so memory stays flat no matter
+/// how many rows there are.
+///
+/// Errors before the first row are returned, and errors after it abort the response, so it
+/// can't be mistaken for a complete one.
+async fn stream_takeoffs(
+    pool: &PgPool,
+    mut query: QueryBuilder<'static, Postgres>,
+    format: Format,
+) -> Result<Response, ServerError> {
+    let ndjson = matches!(format, Format::NdJson);
+    let (content_type, start, end) = match ndjson {
+        true => (NDJSON_CONTENT_TYPE, "", ""),
+        false => ("application/json", "[", "]"),
+    };
+
+    let (tx, mut rx) = mpsc::channel::<Result<Bytes, io::Error>>(STREAM_BUFFER);
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let mut rows = query.build_query_as::<GetTakeoff>().fetch(&pool);
+        let mut chunk = start.as_bytes().to_vec();
+        let mut first = true;
+        while let Some(row) = rows.next().await {
+            let row = row
+                .map_err(io::Error::other)
+                .and_then(|row| serde_json::to_vec(&row).map_err(io::Error::other));
+            let row = match row {
+                Ok(row) => row,
+                Err(error) => {
+                    tracing::error!("failed streaming takeoffs: {error}");
+                    let _ = tx.send(Err(error)).await;
+                    return;
+                }
+            };
+
+            if !ndjson && !first {
+                chunk.push(b',');
+            }
+            chunk.extend_from_slice(&row);
+            if ndjson {
+                chunk.push(b'\n');
+            }
+            first = false;
+
+            if tx
+                .send(Ok(Bytes::from(std::mem::take(&mut chunk))))
+                .await
+                .is_err()
+            {
+                return; // Client went away
+            }
+        }
+        chunk.extend_from_slice(end.as_bytes());
+        let _ = tx.send(Ok(Bytes::from(chunk))).await;
+    });
+
+    let first = rx.recv().await.transpose()?;
+    let rest = stream::unfold(rx, |mut rx| async move {
+        rx.recv().await.map(|chunk| (chunk, rx))
+    });
+    let body = stream::iter(first.map(Ok)).chain(rest);
+
+    Ok((
+        [(header::CONTENT_TYPE, content_type)],
+        Body::from_stream(body),
+    )
+        .into_response())
+}
+
+/// Respond with a file download.
+fn attachment(content_type: &'static str, filename: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, content_type.to_owned()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+/// Number of rows buffered ahead of the client when streaming takeoffs.
+const STREAM_BUFFER: usize = 64;
+
+/// Build a query of takeoffs matching the filters in `params`.
+///
+/// Only favourites of `favorites_of` are included if it's given.
+fn takeoffs_query(
+    params: &GetTakeoffsParams,
+    favorites_of: Option<i32>,
+) -> Result<QueryBuilder<'static, Postgres>, ServerError> {
+    let fields = select_fields(&params.fields)?;
+    let mut query: QueryBuilder<Postgres> =
+        QueryBuilder::new(format!("SELECT {fields} FROM takeoffs"));
+    if let Some(id) = params.id {
+        query.push(" WHERE id = ").push_bind(id);
+        return Ok(query);
+    }
+
+    query
+        .push(" WHERE region LIKE ")
+        .push_bind(params.region.clone());
+    if let Some(user_id) = favorites_of {
+        query
+            .push(" AND id IN (SELECT takeoff_id FROM favorites WHERE user_id = ")
+            .push_bind(user_id)
+            .push(")");
+    }
+    let filters = [
+        ("created_at >= ", &params.created_after),
+        ("created_at <= ", &params.created_before),
+        ("updated_at >= ", &params.updated_after),
+        ("updated_at <= ", &params.updated_before),
+    ];
+    for (filter, value) in filters {
+        if let Some(value) = value {
+            query
+                .push(" AND ")
+                .push(filter)
+                .push_bind(parse_date(value)?);
+        }
+    }
+
+    let (column, direction) = sort_order(params.sort.as_deref())?;
+    query.push(format!(" ORDER BY {column} {direction} NULLS LAST, id"));
+    query.push(" LIMIT ").push_bind(params.limit);
+    query
+        .push(" OFFSET ")
+        .push_bind(page_offset(params.page, params.limit)?);
+
+    Ok(query)
+}
+
+/// Get the number of rows before a page, counting pages from 1.
+///
+/// # Errors
+///
+/// This function will return an error if the offset overflows.
+fn page_offset(page: i64, limit: i64) -> Result<i64, ServerError> {
+    (page.max(1) - 1)
+        .checked_mul(limit)
+        .ok_or(ServerError::BAD_REQUEST("page out of range"))
+}
+
+/// Fetch all takeoffs of a query.
+async fn fetch_takeoffs(
+    pool: &PgPool,
+    mut query: QueryBuilder<'static, Postgres>,
+) -> Result<Vec<GetTakeoff>, ServerError> {
+    Ok(query.build_query_as().fetch_all(pool).await?)
+}
+
+/// Columns that can be selected with `fields`.
+const FIELD_COLUMNS: [&str; 18] = [
+    "id",
+    "name",
+    "description",
+    "image",
+    "region",
+    "altitude_m",
+    "height_diff_m",
+    "latitude",
+    "longitude",
+    "wind_dirs",
+    "info_url",
+    "source",
+    "source_url",
+    "created_at",
+    "created_by",
+    "updated_at",
+    "updated_by",
+    "merged_source_urls",
+];
+
+/// Get the select list of a `fields` parameter.
+///
+/// Defaults to all columns.
+fn select_fields(fields: &[String]) -> Result<String, ServerError> {
+    if let Some(field) = fields
+        .iter()
+        .find(|field| !FIELD_COLUMNS.contains(&field.as_str()))
+    {
+        return Err(ServerError::BAD_REQUEST(format!(
+            "unknown field {field}, expected one of: {}",
+            FIELD_COLUMNS.join(", ")
+        )));
+    }
+
+    match fields.is_empty() {
+        true => Ok("*".to_owned()),
+        false => Ok(fields.join(", ")),
+    }
+}
+
+/// Columns takeoffs can be sorted by.
+const SORT_COLUMNS: [&str; 6] = [
+    "id",
+    "name",
+    "altitude_m",
+    "height_diff_m",
+    "created_at",
+    "updated_at",
+];
+
+/// Get the column and direction of a `sort` parameter.
+///
+/// Defaults to ascending `id`.
+fn sort_order(sort: Option<&str>) -> Result<(&'static str, &'static str), ServerError> {
+    let sort = sort.unwrap_or("id");
+    let (name, direction) = match sort.strip_prefix('-') {
+        Some(name) => (name, "DESC"),
+        None => (sort, "ASC"),
+    };
+    let column = SORT_COLUMNS
+        .into_iter()
+        .find(|column| *column == name)
+        .ok_or(ServerError::BAD_REQUEST(format!(
+            "can't sort by {name}, expected one of: {}",
+            SORT_COLUMNS.join(", ")
+        )))?;
+
+    Ok((column, direction))
+}
+
+/// Parse an RFC 3339 timestamp, or a `YYYY-MM-DD` date as midnight UTC.
+fn parse_date(value: &str) -> Result<DateTime<Utc>, ServerError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc())
+        })
+        .map_err(|_| ServerError::BAD_REQUEST(format!("invalid date {value}")))
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ChangesParams {
+    /// Cursor of a previous response, or everything if missing.
+    since: Option<i64>,
+    fields: Vec<String>,
+}
+
+/// Takeoffs changed since a cursor.
+#[derive(Debug, Serialize)]
+struct Changes {
+    /// Cursor to get the next changes with.
+    cursor: i64,
+    /// If `takeoffs` are all takeoffs, and cached takeoffs should be replaced.
+    full: bool,
+    /// Created and updated takeoffs.
+    takeoffs: Vec<GetTakeoff>,
+    /// Ids of deleted takeoffs.
+    deleted: Vec<i32>,
+}
+
+/// Gets takeoffs created, updated or deleted since the cursor `since`, for syncing.
+///
+/// Without `since` (or with a cursor from a reset database), responds with all takeoffs.
+async fn get_takeoffs_changes(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Query(params): Query<ChangesParams>,
+) -> Result<Json<Changes>, ServerError> {
+    let mut fields = params.fields;
+    if !fields.is_empty() && !fields.iter().any(|field| field == "id") {
+        fields.push("id".to_owned());
+    }
+    let fields = select_fields(&fields)?;
+
+    // Takeoffs and changes from the same snapshot
+    let mut tx = pool.begin().await?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ")
+        .execute(&mut *tx)
+        .await?;
+    let cursor = helpers::get_sync_cursor(&mut *tx).await?;
+
+    let changes = match params.since.filter(|since| *since <= cursor) {
+        Some(since) => {
+            let ids = helpers::get_changed_takeoff_ids(&mut *tx, since, cursor).await?;
+            let takeoffs: Vec<GetTakeoff> = sqlx::query_as(&format!(
+                "SELECT {fields} FROM takeoffs WHERE id = ANY($1) ORDER BY id"
+            ))
+            .bind(&ids)
+            .fetch_all(&mut *tx)
+            .await?;
+            let deleted = ids
+                .into_iter()
+                .filter(|id| !takeoffs.iter().any(|takeoff| takeoff.id == Some(*id)))
+                .collect();
+
+            Changes {
+                cursor,
+                full: false,
+                takeoffs,
+                deleted,
+            }
+        }
+        None => Changes {
+            cursor,
+            full: true,
+            takeoffs: sqlx::query_as(&format!("SELECT {fields} FROM takeoffs ORDER BY id"))
+                .fetch_all(&mut *tx)
+                .await?,
+            deleted: Vec::new(),
+        },
     };
+    tx.commit().await?;
 
-    Ok(Json(out))
+    Ok(Json(changes))
 }
 
+/// Maximum number of search results per page.
+const MAX_SEARCH_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct SearchParams {
+    q: String,
+    page: i64,
+    limit: i64,
+}
+
+impl Default for SearchParams {
+    fn default() -> Self {
+        Self {
+            q: String::new(),
+            page: 1,
+            limit: 20,
+        }
+    }
+}
+
+/// Searches takeoffs, best match first.
+///
+/// Highlights in `name_highlight` and `snippet` are `<mark>` elements, and the rest is escaped.
+async fn get_takeoffs_search(
+    _version: Version,
+    pool: Extension<PgPool>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Vec<SearchResult>>, ServerError> {
+    let q = params.q.trim();
+    if q.is_empty() {
+        return Err(ServerError::BAD_REQUEST("missing search query q"));
+    }
+    let limit = params.limit.clamp(1, MAX_SEARCH_LIMIT);
+    let offset = page_offset(params.page, limit)?;
+
+    let mut tx = pool.begin().await?;
+    let mut results = helpers::search_takeoffs(&mut tx, q, limit, offset).await?;
+    tx.commit().await?;
+    for result in &mut results {
+        result.name_highlight = escape_highlight(&result.name_highlight);
+        result.snippet = escape_highlight(&result.snippet);
+    }
+
+    Ok(Json(results))
+}
+
+/// Escape text with `<mark>` highlights as HTML, keeping the highlights.
+fn escape_highlight(text: &str) -> String {
+    text.split("<mark>")
+        .map(|part| {
+            part.split("</mark>")
+                .map(escape_xml)
+                .collect::<Vec<_>>()
+                .join("</mark>")
+        })
+        .collect::<Vec<_>>()
+        .join("<mark>")
+}
+
+/// Creates a takeoff, attributed to the user of the session (if any).
+///
+/// Responds with `409 Conflict` if a takeoff has the same `source_url`, which only editors can
+/// update.
 async fn post_takeoffs(
-    version: Version,
+    _version: Version,
     pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
     Json(data): Json<Data<NewTakeoff>>,
 ) -> Result<(), ServerError> {
-    helpers::insert_takeoff(&*pool, &data.value).await?;
+    let user_id = match data.session {
+        Some(_) => Some(auth::check_session((*pool).clone(), &data.session).await?),
+        None => None,
+    };
+    let note = ChangeNote {
+        user_id,
+        comment: None,
+    };
+    helpers::insert_takeoff(&*pool, &data.value, &note)
+        .await
+        .map_err(|error| match error {
+            sqlx::Error::Database(error) if error.is_unique_violation() => {
+                ServerError::CONFLICT("a takeoff with this source_url exists already")
+            }
+            error => error.into(),
+        })?;
+    cache.invalidate();
+
+    Ok(())
+}
+
+/// Validates a GeoJSON feature collection and upserts every feature, or none at all.
+///
+/// Features with an `id` update that takeoff, keeping its image, and unknown ids are rejected.
+/// Other features are upserted by `source_url`.
+///
+/// Requires the `editor` role.
+async fn post_takeoffs_import(
+    _version: Version,
+    pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    Json(data): Json<Data<geojson::FeatureCollection<Map<String, Value>>>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::require_role((*pool).clone(), &data.session, auth::EDITOR).await?;
+
+    let takeoffs = geojson::to_new_takeoffs(data.value)?;
+    let note = ChangeNote {
+        user_id: Some(user_id),
+        comment: Some("Imported from GeoJSON".to_owned()),
+    };
+    let mut tx = pool.begin().await?;
+    let username = helpers::get_username(&mut *tx, user_id).await?;
+    for (id, takeoff) in takeoffs {
+        match id {
+            Some(id) => {
+                let current = helpers::get_takeoff(&mut *tx, id)
+                    .await?
+                    .ok_or(ServerError::BAD_REQUEST(format!("no takeoff {id}")))?;
+                let takeoff = updated_takeoff(current, takeoff, &username, Utc::now());
+                helpers::update_takeoff(&mut *tx, &takeoff, &note).await?;
+            }
+            None => helpers::upsert_takeoff(&mut *tx, &takeoff, &note).await?,
+        }
+    }
+    tx.commit().await?;
+    cache.invalidate();
 
     Ok(())
 }
+
+/// New fields of a takeoff, with an optional comment for its history.
+#[derive(Debug, Deserialize)]
+struct TakeoffUpdate {
+    takeoff: NewTakeoff,
+    comment: Option<String>,
+}
+
+/// An optional comment for the history of a takeoff.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ChangeComment {
+    comment: Option<String>,
+}
+
+/// Replaces the fields of a takeoff, keeping its image if `image` is missing.
+///
+/// The creation fields are kept, and the update fields are set to now and the user.
+///
+/// Requires the `editor` role.
+async fn put_takeoff(
+    _version: Version,
+    pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<TakeoffUpdate>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::require_role((*pool).clone(), &data.session, auth::EDITOR).await?;
+
+    let mut tx = pool.begin().await?;
+    let current = helpers::get_takeoff(&mut *tx, id)
+        .await?
+        .ok_or(ServerError::NOT_FOUND(format!("no takeoff {id}")))?;
+    let username = helpers::get_username(&mut *tx, user_id).await?;
+    let takeoff = updated_takeoff(current, data.value.takeoff, &username, Utc::now());
+    let note = ChangeNote {
+        user_id: Some(user_id),
+        comment: data.value.comment,
+    };
+    helpers::update_takeoff(&mut *tx, &takeoff, &note).await?;
+    tx.commit().await?;
+    cache.invalidate();
+
+    Ok(())
+}
+
+/// Replace the fields of `current` with `new`, keeping its id, creation fields, merged source
+/// URLs, and its image if `new` has none.
+///
+/// The update fields of `new` are ignored, the takeoff is updated by `username` at `now`.
+fn updated_takeoff(
+    current: Takeoff,
+    new: NewTakeoff,
+    username: &str,
+    now: DateTime<Utc>,
+) -> Takeoff {
+    Takeoff {
+        id: current.id,
+        name: new.name,
+        description: new.description,
+        image: new.image.or(current.image),
+        region: new.region,
+        altitude_m: new.altitude_m,
+        height_diff_m: new.height_diff_m,
+        latitude: new.latitude,
+        longitude: new.longitude,
+        wind_dirs: new.wind_dirs,
+        info_url: new.info_url,
+        source: new.source,
+        source_url: new.source_url,
+        created_at: current.created_at,
+        created_by: current.created_by,
+        updated_at: Some(now),
+        updated_by: Some(username.to_owned()),
+        merged_source_urls: current.merged_source_urls,
+    }
+}
+
+/// Deletes a takeoff, it can be restored from its history.
+///
+/// Requires the `editor` role.
+async fn delete_takeoff(
+    _version: Version,
+    pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    Path((_, id)): Path<(String, i32)>,
+    Json(data): Json<Data<ChangeComment>>,
+) -> Result<(), ServerError> {
+    let user_id = auth::require_role((*pool).clone(), &data.session, auth::EDITOR).await?;
+
+    let note = ChangeNote {
+        user_id: Some(user_id),
+        comment: data.value.comment,
+    };
+    if !helpers::delete_takeoff(&*pool, id, &note).await? {
+        return Err(ServerError::NOT_FOUND(format!("no takeoff {id}")));
+    }
+    cache.invalidate();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_offsets() {
+        assert_eq!(page_offset(1, i64::MAX).unwrap(), 0);
+        assert_eq!(page_offset(0, 20).unwrap(), 0);
+        assert_eq!(page_offset(-5, 20).unwrap(), 0);
+        assert_eq!(page_offset(3, 20).unwrap(), 40);
+        assert_eq!(page_offset(2, i64::MAX).unwrap(), i64::MAX);
+        assert!(page_offset(3, i64::MAX).is_err());
+        assert!(page_offset(i64::MAX, 100).is_err());
+    }
+
+    #[test]
+    fn selects_fields() {
+        let fields = |fields: &[&str]| -> Vec<String> {
+            fields.iter().map(|field| field.to_string()).collect()
+        };
+
+        assert_eq!(select_fields(&[]).unwrap(), "*");
+        assert_eq!(
+            select_fields(&fields(&["id", "name", "wind_dirs"])).unwrap(),
+            "id, name, wind_dirs"
+        );
+        assert!(select_fields(&fields(&["id", "content_hash"])).is_err());
+        assert!(select_fields(&fields(&["id FROM users --"])).is_err());
+        assert!(select_fields(&fields(&["NAME"])).is_err());
+    }
+
+    #[test]
+    fn updates_takeoffs() {
+        let current: Takeoff = serde_json::from_value(serde_json::json!({
+            "id": 7, "name": "Old", "description": "", "image": [1, 2], "region": "Vestland",
+            "altitude_m": 600, "height_diff_m": 550, "latitude": 60.6, "longitude": 6.4,
+            "wind_dirs": ["N"], "info_url": null, "source": "flightlog",
+            "source_url": "https://example.org/7", "created_at": "2020-05-01T10:00:00Z",
+            "created_by": "Kari", "updated_at": "2021-06-01T10:00:00Z", "updated_by": "Ola",
+            "merged_source_urls": ["https://example.org/8"]
+        }))
+        .unwrap();
+        let new: NewTakeoff = serde_json::from_value(serde_json::json!({
+            "name": "New", "description": "Better", "image": null, "region": "Vestland",
+            "altitude_m": 650, "height_diff_m": 600, "latitude": 60.7, "longitude": 6.5,
+            "wind_dirs": ["N", "NE"], "info_url": null, "source": "flightlog",
+            "source_url": "https://example.org/7", "created_at": "1999-01-01T00:00:00Z",
+            "created_by": "Forged", "updated_at": "1999-01-01T00:00:00Z", "updated_by": "Forged"
+        }))
+        .unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-10-19T12:00:00Z")
+            .unwrap()
+            .to_utc();
+
+        let takeoff = updated_takeoff(current, new, "editor", now);
+
+        assert_eq!(takeoff.id, 7);
+        assert_eq!(takeoff.name, "New");
+        assert_eq!(takeoff.wind_dirs, ["N", "NE"]);
+        assert_eq!(takeoff.image, Some(vec![1, 2]));
+        assert_eq!(takeoff.merged_source_urls, ["https://example.org/8"]);
+        assert_eq!(
+            takeoff.created_at.map(|time| time.to_rfc3339()).as_deref(),
+            Some("2020-05-01T10:00:00+00:00")
+        );
+        assert_eq!(takeoff.created_by.as_deref(), Some("Kari"));
+        assert_eq!(takeoff.updated_at, Some(now));
+        assert_eq!(takeoff.updated_by.as_deref(), Some("editor"));
+    }
+}
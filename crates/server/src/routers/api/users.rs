@@ -17,7 +17,7 @@ pub fn router() -> Router {
 
 /// Creates a user.
 async fn post_users(
-    version: Version,
+    _version: Version,
     pool: Extension<PgPool>,
     Json(data): Json<models::Data<models::NewUser>>,
 ) -> Result<(), ServerError> {
@@ -41,24 +41,24 @@ async fn post_users(
 
 /// Checks users credentials, creates and returns session.
 async fn post_login(
-    version: Version,
+    _version: Version,
     pool: Extension<PgPool>,
     random: Extension<Arc<Mutex<ChaCha8Rng>>>,
     Json(data): Json<models::Data<models::LoginUser>>,
 ) -> Result<Json<models::Session>, ServerError> {
-    let user_id = auth::check_credentials((&*pool).clone(), data.value).await?;
-    let session = auth::create_session((&*pool).clone(), (&*random).clone(), user_id).await?;
+    let user_id = auth::check_credentials((*pool).clone(), data.value).await?;
+    let session = auth::create_session((*pool).clone(), (*random).clone(), user_id).await?;
 
     Ok(Json(session))
 }
 
-/// Logout.
+/// Logout, deleting the session.
 async fn get_logout(
-    version: Version,
+    _version: Version,
     pool: Extension<PgPool>,
     Json(data): Json<models::Data<()>>,
 ) -> Result<(), ServerError> {
-    todo!();
+    auth::delete_session((*pool).clone(), &data.session).await?;
 
     Ok(())
 }
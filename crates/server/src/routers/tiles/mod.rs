@@ -0,0 +1,62 @@
+use crate::{
+    cache::{CacheControl, ResponseCache, Validators},
+    database::helpers,
+    error::ServerError,
+    formats::mvt,
+};
+use axum::{
+    extract::{OriginalUri, Path},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    routing::get,
+    Extension, Router,
+};
+use sqlx::PgPool;
+
+// Map tiles
+pub fn router() -> Router {
+    Router::new().route("/tiles/takeoffs/:z/:x/:y", get(get_takeoffs_tile))
+}
+
+/// Gets a vector tile of takeoffs (e.g. `/tiles/takeoffs/10/540/302.mvt`), see [`mvt`].
+///
+/// Tiles have `ETag` and `Last-Modified` of the whole dataset, and are cached until it changes.
+async fn get_takeoffs_tile(
+    pool: Extension<PgPool>,
+    cache: Extension<ResponseCache>,
+    uri: OriginalUri,
+    headers: HeaderMap,
+    Path((z, x, y)): Path<(u32, u32, String)>,
+) -> Result<Response, ServerError> {
+    let y: u32 = y
+        .strip_suffix(".mvt")
+        .and_then(|y| y.parse().ok())
+        .ok_or(ServerError::NOT_FOUND(format!("no tile {z}/{x}/{y}")))?;
+    if !mvt::valid_tile(z, x, y) {
+        return Err(ServerError::NOT_FOUND(format!("no tile {z}/{x}/{y}")));
+    }
+
+    let version = helpers::get_dataset_version(&*pool).await?;
+    let validators = Validators::new(&version);
+    if validators.not_modified(&headers) {
+        return Ok(validators.not_modified_response(CacheControl::Revalidate));
+    }
+    let key = uri.to_string();
+    if let Some(response) = cache.get(&key, version.version) {
+        return Ok(response);
+    }
+
+    let bounds = mvt::tile_bounds(z, x, y, mvt::BUFFER);
+    let takeoffs = helpers::get_takeoffs_in_bounds(&*pool, bounds).await?;
+    let mut response = (
+        [(header::CONTENT_TYPE, mvt::CONTENT_TYPE)],
+        mvt::write_takeoffs(&takeoffs, z, x, y),
+    )
+        .into_response();
+    validators.apply(response.headers_mut());
+    response
+        .headers_mut()
+        .insert(header::CACHE_CONTROL, CacheControl::Revalidate.value());
+
+    Ok(cache.insert(&key, version.version, response))
+}
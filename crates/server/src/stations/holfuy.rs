@@ -0,0 +1,266 @@
+//! Stations of [Holfuy](https://holfuy.com), see <https://api.holfuy.com>.
+//!
+//! The list of stations is public, but live readings need an API key.
+
+use super::{Config, StationProvider};
+use crate::{
+    forecast::client,
+    models::{NewStation, StationReading},
+};
+use anyhow::anyhow;
+use axum::async_trait;
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+/// Name of the provider.
+pub const NAME: &str = "holfuy";
+
+/// Base URL of the API.
+pub const DEFAULT_URL: &str = "https://api.holfuy.com";
+
+/// Format of reading dates, in UTC with the `utc` parameter.
+const DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// Holfuy station provider.
+pub struct Holfuy {
+    client: reqwest::Client,
+    url: String,
+    key: String,
+}
+
+impl Holfuy {
+    /// Create a provider, using the URL of `config` if any.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there's no API key, or if the HTTP client can't be
+    /// built.
+    pub fn new(config: &Config) -> Result<Self, anyhow::Error> {
+        Ok(Self {
+            client: client()?,
+            url: config
+                .url
+                .clone()
+                .unwrap_or(DEFAULT_URL.to_owned())
+                .trim_end_matches('/')
+                .to_owned(),
+            key: config
+                .holfuy_key
+                .clone()
+                .ok_or(anyhow!("missing HOLFUY_KEY for Holfuy readings"))?,
+        })
+    }
+}
+
+/// Station list response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StationsResponse {
+    holfuy_stations_list: Vec<StationItem>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StationItem {
+    id: i64,
+    name: String,
+    location: Location,
+}
+
+#[derive(Debug, Deserialize)]
+struct Location {
+    latitude: f64,
+    longitude: f64,
+    altitude: Option<f64>,
+}
+
+/// Live response of all stations.
+#[derive(Debug, Deserialize)]
+struct LiveResponse {
+    measurements: Vec<Measurement>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Measurement {
+    station_id: i64,
+    date_time: String,
+    wind: Option<Wind>,
+    temperature: Option<f64>,
+}
+
+/// Wind in the unit of the `su` parameter.
+#[derive(Debug, Deserialize)]
+struct Wind {
+    speed: Option<f64>,
+    gust: Option<f64>,
+    direction: Option<f64>,
+}
+
+#[async_trait]
+impl StationProvider for Holfuy {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn fetch_stations(&self) -> Result<Vec<NewStation>, anyhow::Error> {
+        let response: StationsResponse = self
+            .client
+            .get(format!("{}/stations/stations.json", self.url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let stations = response
+            .holfuy_stations_list
+            .into_iter()
+            .map(|station| NewStation {
+                provider_id: station.id.to_string(),
+                name: station.name,
+                latitude: station.location.latitude,
+                longitude: station.location.longitude,
+                altitude_m: station
+                    .location
+                    .altitude
+                    .map(|altitude| altitude.round() as i32),
+            })
+            .collect();
+
+        Ok(stations)
+    }
+
+    async fn fetch_readings(&self) -> Result<Vec<(String, StationReading)>, anyhow::Error> {
+        let response: LiveResponse = self
+            .client
+            .get(format!("{}/live/", self.url))
+            .query(&[
+                ("s", "all"),
+                ("pw", &self.key),
+                ("m", "JSON"),
+                ("tu", "C"),
+                ("su", "m/s"),
+                ("utc", ""),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        // Measurements with an invalid date are skipped
+        let readings = response
+            .measurements
+            .into_iter()
+            .filter_map(|measurement| {
+                let time = NaiveDateTime::parse_from_str(&measurement.date_time, DATE_FORMAT)
+                    .ok()?
+                    .and_utc();
+                let wind = measurement.wind;
+                let reading = StationReading {
+                    time,
+                    wind_speed_ms: wind.as_ref().and_then(|wind| wind.speed),
+                    wind_direction_deg: wind.as_ref().and_then(|wind| wind.direction),
+                    wind_gusts_ms: wind.as_ref().and_then(|wind| wind.gust),
+                    temperature_c: measurement.temperature,
+                };
+                Some((measurement.station_id.to_string(), reading))
+            })
+            .collect();
+
+        Ok(readings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use mockito::Matcher;
+
+    /// Provider fetching from a mock server with the recorded responses.
+    async fn mock() -> (mockito::ServerGuard, Holfuy) {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/stations/stations.json")
+            .with_header("content-type", "application/json")
+            .with_body(include_str!("../../tests/fixtures/holfuy/stations.json"))
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/live/")
+            .match_query(Matcher::AllOf(vec![
+                Matcher::UrlEncoded("s".into(), "all".into()),
+                Matcher::UrlEncoded("pw".into(), "key".into()),
+                Matcher::UrlEncoded("su".into(), "m/s".into()),
+            ]))
+            .with_header("content-type", "application/json")
+            .with_body(include_str!("../../tests/fixtures/holfuy/live.json"))
+            .create_async()
+            .await;
+
+        let provider = Holfuy::new(&Config {
+            url: Some(format!("{}/", server.url())),
+            holfuy_key: Some("key".to_owned()),
+        })
+        .unwrap();
+
+        (server, provider)
+    }
+
+    #[test]
+    fn requires_key() {
+        assert!(Holfuy::new(&Config::default()).is_err());
+    }
+
+    #[tokio::test]
+    async fn fetches_stations() {
+        let (_server, provider) = mock().await;
+        let stations = provider.fetch_stations().await.unwrap();
+
+        let ids: Vec<&str> = stations.iter().map(|s| s.provider_id.as_str()).collect();
+        assert_eq!(ids, ["101", "214", "1533"]);
+        assert_eq!(stations[0].name, "Hanguren");
+        assert_eq!(stations[0].latitude, 60.6451);
+        assert_eq!(stations[0].longitude, 6.4012);
+        assert_eq!(stations[0].altitude_m, Some(650));
+        assert_eq!(stations[1].altitude_m, Some(180));
+        assert_eq!(stations[2].altitude_m, None);
+    }
+
+    #[tokio::test]
+    async fn fetches_readings() {
+        let (_server, provider) = mock().await;
+        let readings = provider.fetch_readings().await.unwrap();
+
+        // The reading of 1533 has an invalid date
+        let ids: Vec<&str> = readings.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["101", "214"]);
+
+        let (_, reading) = &readings[0];
+        assert_eq!(
+            reading.time,
+            Utc.with_ymd_and_hms(2025, 7, 15, 12, 40, 0).unwrap()
+        );
+        assert_eq!(reading.wind_speed_ms, Some(3.2));
+        assert_eq!(reading.wind_direction_deg, Some(45.0));
+        assert_eq!(reading.wind_gusts_ms, Some(5.1));
+        assert_eq!(reading.temperature_c, Some(9.5));
+
+        // Without wind
+        let (_, reading) = &readings[1];
+        assert_eq!(reading.wind_speed_ms, None);
+        assert_eq!(reading.wind_direction_deg, None);
+        assert_eq!(reading.wind_gusts_ms, None);
+        assert_eq!(reading.temperature_c, Some(11.0));
+    }
+
+    #[tokio::test]
+    async fn rejects_wrong_key() {
+        let (_server, mut provider) = mock().await;
+        provider.key = "wrong".to_owned();
+
+        // Unmatched requests get 501 from the mock server
+        assert!(provider.fetch_readings().await.is_err());
+    }
+}
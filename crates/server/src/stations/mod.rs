@@ -0,0 +1,245 @@
+//! Live weather stations near takeoffs.
+//!
+//! Each provider implements [`StationProvider`], and is registered in [`NAMES`] and [`create`].
+//! [`spawn`] polls the providers in the background: stations are listed again every
+//! [`STATIONS_MAX_AGE_HOURS`] and linked to the takeoffs nearest to them, and readings are saved
+//! every [`POLL_INTERVAL_MINUTES`] and kept for [`RETENTION_DAYS`].
+
+pub mod holfuy;
+
+use crate::{
+    database::helpers,
+    models::{NewStation, StationReading},
+    validation::geo,
+};
+use anyhow::anyhow;
+use axum::async_trait;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+/// Names of all providers.
+pub const NAMES: [&str; 1] = [holfuy::NAME];
+
+/// Minutes between polls of readings.
+pub const POLL_INTERVAL_MINUTES: u64 = 5;
+/// Hours before the stations of a provider are listed again.
+pub const STATIONS_MAX_AGE_HOURS: i64 = 24;
+/// Days readings are kept.
+pub const RETENTION_DAYS: i64 = 7;
+
+/// Maximum distance in meters between a takeoff and its linked stations.
+pub const MAX_DISTANCE_M: f64 = 30_000.0;
+/// Maximum number of stations linked to a takeoff, closest first.
+pub const MAX_LINKED: usize = 3;
+
+/// A weather service with live wind readings of stations.
+#[async_trait]
+pub trait StationProvider: Send + Sync {
+    /// Unique name, saved with stations.
+    fn name(&self) -> &'static str;
+
+    /// List all stations of the provider.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails or the response is invalid.
+    async fn fetch_stations(&self) -> Result<Vec<NewStation>, anyhow::Error>;
+
+    /// Fetch the latest reading of every station.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the request fails or the response is invalid.
+    ///
+    /// # Returns
+    ///
+    /// Pairs of provider ids of stations and readings.
+    async fn fetch_readings(&self) -> Result<Vec<(String, StationReading)>, anyhow::Error>;
+}
+
+/// Configuration shared by providers.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    /// Optional URL replacing the base URL of the provider (e.g. a local mock server).
+    pub url: Option<String>,
+    /// Optional API key of Holfuy, required for readings.
+    pub holfuy_key: Option<String>,
+}
+
+impl Config {
+    /// Read the configuration from environment variables.
+    ///
+    /// * `STATIONS_URL` - Optional URL replacing the base URL of the provider.
+    /// * `HOLFUY_KEY` - Optional API key of Holfuy.
+    pub fn from_env() -> Self {
+        Self {
+            url: std::env::var("STATIONS_URL").ok(),
+            holfuy_key: std::env::var("HOLFUY_KEY").ok(),
+        }
+    }
+}
+
+/// Create a provider.
+///
+/// # Arguments
+///
+/// * `name` - One of [`NAMES`].
+/// * `config` - Configuration for the provider.
+///
+/// # Errors
+///
+/// This function will return an error if the provider is unknown or can't be created.
+pub fn create(name: &str, config: &Config) -> Result<Arc<dyn StationProvider>, anyhow::Error> {
+    match name {
+        holfuy::NAME => Ok(Arc::new(holfuy::Holfuy::new(config)?)),
+        _ => Err(anyhow!(
+            "unknown station provider {name}, expected one of: {}",
+            NAMES.join(", ")
+        )),
+    }
+}
+
+/// Poll providers in the background, every [`POLL_INTERVAL_MINUTES`].
+///
+/// Errors are logged, and the provider is polled again next time.
+pub fn spawn(pool: PgPool, providers: Vec<Arc<dyn StationProvider>>) {
+    tokio::spawn(async move {
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(POLL_INTERVAL_MINUTES * 60));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            for provider in &providers {
+                if let Err(error) = poll(&pool, provider.as_ref()).await {
+                    tracing::warn!("failed polling stations of {}: {error:#}", provider.name());
+                }
+            }
+
+            let before = Utc::now() - Duration::days(RETENTION_DAYS);
+            match helpers::delete_station_readings_before(&pool, before).await {
+                Ok(deleted) if deleted > 0 => tracing::debug!("deleted {deleted} old readings"),
+                Ok(_) => {}
+                Err(error) => tracing::warn!("failed deleting old readings: {error}"),
+            }
+        }
+    });
+}
+
+/// Poll a provider once.
+///
+/// Stations are listed again if they're older than [`STATIONS_MAX_AGE_HOURS`], and all takeoffs
+/// are linked again. Otherwise only takeoffs without stations are linked (e.g. new takeoffs).
+///
+/// # Errors
+///
+/// This function will return an error if fetching or a database query fails, or if the provider
+/// lists no stations.
+pub async fn poll(pool: &PgPool, provider: &dyn StationProvider) -> Result<(), anyhow::Error> {
+    let name = provider.name();
+    let updated_at = helpers::get_stations_updated_at(pool, name).await?;
+    let stale = updated_at.map_or(true, |updated_at| {
+        Utc::now() - updated_at >= Duration::hours(STATIONS_MAX_AGE_HOURS)
+    });
+
+    if stale {
+        let stations = provider.fetch_stations().await?;
+        // Most likely a broken response, which would delete all stations
+        if stations.is_empty() {
+            return Err(anyhow!("{name} listed no stations"));
+        }
+        let mut tx = pool.begin().await?;
+        helpers::replace_stations(&mut tx, name, &stations).await?;
+        tx.commit().await?;
+        tracing::info!("listed {} stations of {name}", stations.len());
+        link_stations(pool, None).await?;
+    } else {
+        link_stations(pool, Some(helpers::get_unlinked_takeoff_ids(pool).await?)).await?;
+    }
+
+    let ids: HashMap<String, i32> = helpers::get_stations(pool)
+        .await?
+        .into_iter()
+        .filter(|station| station.provider == name)
+        .map(|station| (station.provider_id, station.id))
+        .collect();
+    // Readings of unknown stations are skipped until they're listed
+    let readings: Vec<(i32, StationReading)> = provider
+        .fetch_readings()
+        .await?
+        .into_iter()
+        .filter_map(|(provider_id, reading)| Some((*ids.get(&provider_id)?, reading)))
+        .collect();
+    let inserted = helpers::insert_station_readings(pool, &readings).await?;
+    tracing::debug!("saved {inserted} new readings of {name}");
+
+    Ok(())
+}
+
+/// Link takeoffs to the closest [`MAX_LINKED`] stations within [`MAX_DISTANCE_M`].
+///
+/// # Arguments
+///
+/// * `pool` - Database pool.
+/// * `takeoff_ids` - Optional ids of the takeoffs to link, all takeoffs if missing.
+///
+/// # Errors
+///
+/// This function will return an error if a database query fails.
+pub async fn link_stations(
+    pool: &PgPool,
+    takeoff_ids: Option<HashSet<i32>>,
+) -> Result<(), sqlx::Error> {
+    if takeoff_ids.as_ref().is_some_and(HashSet::is_empty) {
+        return Ok(());
+    }
+
+    // Sorted by latitude, so only stations in a band around each takeoff are compared
+    let mut stations = helpers::get_stations(pool).await?;
+    stations.sort_by(|a, b| a.latitude.total_cmp(&b.latitude));
+    let takeoffs = helpers::get_takeoffs_in_bounds(pool, (-180.0, -90.0, 180.0, 90.0)).await?;
+    let lat_span = geo::meters_to_latitude(MAX_DISTANCE_M);
+
+    let mut ids = Vec::new();
+    let mut links = Vec::new();
+    for takeoff in takeoffs {
+        let (Some(id), Some(latitude), Some(longitude)) =
+            (takeoff.id, takeoff.latitude, takeoff.longitude)
+        else {
+            continue;
+        };
+        if takeoff_ids.as_ref().is_some_and(|ids| !ids.contains(&id)) {
+            continue;
+        }
+
+        let start = stations.partition_point(|station| station.latitude < latitude - lat_span);
+        let mut nearest: Vec<(i32, f64)> = stations[start..]
+            .iter()
+            .take_while(|station| station.latitude <= latitude + lat_span)
+            .map(|station| {
+                let distance =
+                    geo::haversine(latitude, longitude, station.latitude, station.longitude);
+                (station.id, distance)
+            })
+            .filter(|(_, distance)| *distance <= MAX_DISTANCE_M)
+            .collect();
+        nearest.sort_by(|a, b| a.1.total_cmp(&b.1));
+        nearest.truncate(MAX_LINKED);
+
+        ids.push(id);
+        links.extend(
+            nearest
+                .into_iter()
+                .map(|(station_id, distance)| (id, station_id, distance)),
+        );
+    }
+
+    let mut tx = pool.begin().await?;
+    helpers::replace_takeoff_stations(&mut tx, &ids, &links).await?;
+    tx.commit().await?;
+
+    Ok(())
+}
@@ -0,0 +1,377 @@
+//! Find and merge duplicate takeoffs.
+//!
+//! Candidates are takeoffs close to each other with similar names.
+
+use super::{nearby_pairs, normalize, valid_coordinates, WIND_DIRS};
+use crate::models::{GetTakeoff, Takeoff};
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Default distance in meters candidates can be apart.
+pub const DEFAULT_MAX_DISTANCE: f64 = 500.0;
+/// Default minimum name similarity of candidates, from 0 to 1.
+pub const DEFAULT_MIN_SIMILARITY: f64 = 0.6;
+
+/// Options for finding candidates.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Distance in meters candidates can be apart.
+    pub max_distance: f64,
+    /// Minimum name similarity of candidates, from 0 to 1.
+    pub min_similarity: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            max_distance: DEFAULT_MAX_DISTANCE,
+            min_similarity: DEFAULT_MIN_SIMILARITY,
+        }
+    }
+}
+
+/// A pair of takeoffs that might be duplicates.
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    /// Id of the first takeoff.
+    pub a: i32,
+    /// Id of the second takeoff.
+    pub b: i32,
+    /// Name of the first takeoff.
+    pub a_name: String,
+    /// Name of the second takeoff.
+    pub b_name: String,
+    /// Distance between the takeoffs in meters.
+    pub distance_m: f64,
+    /// Similarity of the names, from 0 to 1.
+    pub similarity: f64,
+    /// Combined score of distance and similarity, from 0 to 1.
+    pub score: f64,
+}
+
+/// Find candidate duplicates.
+///
+/// # Arguments
+///
+/// * `takeoffs` - Takeoffs with ids, names and coordinates.
+/// * `config` - Distance and similarity thresholds.
+/// * `dismissed` - Pairs of ids (smallest first) that aren't duplicates.
+///
+/// # Returns
+///
+/// Candidates with the smallest id first, best score first.
+pub fn find_candidates(
+    takeoffs: &[GetTakeoff],
+    config: &Config,
+    dismissed: &HashSet<(i32, i32)>,
+) -> Vec<Candidate> {
+    let mut out: Vec<Candidate> = nearby_pairs(takeoffs, config.max_distance)
+        .into_iter()
+        .filter_map(|(i, j, distance_m)| {
+            let (a, b) = match (takeoffs[i].id?, takeoffs[j].id?) {
+                (a, b) if a < b => ((a, &takeoffs[i]), (b, &takeoffs[j])),
+                (a, b) => ((b, &takeoffs[j]), (a, &takeoffs[i])),
+            };
+            if dismissed.contains(&(a.0, b.0)) {
+                return None;
+            }
+
+            let a_name = a.1.name.clone().unwrap_or_default();
+            let b_name = b.1.name.clone().unwrap_or_default();
+            let similarity = name_similarity(&a_name, &b_name);
+            if similarity < config.min_similarity {
+                return None;
+            }
+
+            let closeness = 1.0 - distance_m / config.max_distance.max(f64::EPSILON);
+            Some(Candidate {
+                a: a.0,
+                b: b.0,
+                a_name,
+                b_name,
+                distance_m,
+                similarity,
+                score: (similarity + closeness) / 2.0,
+            })
+        })
+        .collect();
+
+    out.sort_by(|x, y| {
+        y.score
+            .total_cmp(&x.score)
+            .then((x.a, x.b).cmp(&(y.a, y.b)))
+    });
+    out
+}
+
+/// Get the similarity of two names, from 0 to 1.
+///
+/// The best of the Sørensen-Dice coefficient of the names, and the share of words in the
+/// shorter name that are in the other (so `Hanguren` and `Voss - Hanguren` are similar).
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let words = |name: &str| -> Vec<String> {
+        normalize(name)
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .map(str::to_owned)
+            .collect()
+    };
+    let (a, b) = (words(a), words(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let dice = strsim::sorensen_dice(&a.join(" "), &b.join(" "));
+    let (shorter, longer) = match a.len() <= b.len() {
+        true => (&a, &b),
+        false => (&b, &a),
+    };
+    let common = shorter.iter().filter(|word| longer.contains(word)).count();
+    let overlap = common as f64 / shorter.len() as f64;
+
+    dice.max(overlap)
+}
+
+/// Merge `remove` into `keep`, keeping the best fields of both.
+///
+/// * Text, image, altitudes and links of `keep` are kept, unless they're missing.
+/// * The longest description and all wind directions are kept.
+/// * The earliest creation and latest update are kept.
+/// * The source URLs of `remove` are added to the merged source URLs.
+pub fn merge(keep: Takeoff, remove: Takeoff) -> Takeoff {
+    let text = |keep: String, remove: String| match keep.trim().is_empty() {
+        true => remove,
+        false => keep,
+    };
+
+    let (latitude, longitude) = match valid_coordinates(keep.latitude, keep.longitude)
+        && (keep.latitude, keep.longitude) != (0.0, 0.0)
+    {
+        true => (keep.latitude, keep.longitude),
+        false => (remove.latitude, remove.longitude),
+    };
+
+    let description = match remove.description.trim().len() > keep.description.trim().len() {
+        true => remove.description,
+        false => keep.description,
+    };
+
+    // Known directions in compass order, then anything else
+    let all_dirs: Vec<String> = keep.wind_dirs.into_iter().chain(remove.wind_dirs).collect();
+    let mut wind_dirs: Vec<String> = WIND_DIRS
+        .iter()
+        .filter(|dir| all_dirs.iter().any(|d| d == *dir))
+        .map(|dir| dir.to_string())
+        .collect();
+    for dir in all_dirs {
+        if !wind_dirs.contains(&dir) {
+            wind_dirs.push(dir);
+        }
+    }
+
+    let (created_at, created_by) = match (keep.created_at, remove.created_at) {
+        (Some(a), Some(b)) if b < a => (remove.created_at, remove.created_by),
+        (None, Some(_)) => (remove.created_at, remove.created_by),
+        _ => (keep.created_at, keep.created_by),
+    };
+    let (updated_at, updated_by) = match (keep.updated_at, remove.updated_at) {
+        (Some(a), Some(b)) if b > a => (remove.updated_at, remove.updated_by),
+        (None, Some(_)) => (remove.updated_at, remove.updated_by),
+        _ => (keep.updated_at, keep.updated_by),
+    };
+
+    let mut merged_source_urls = keep.merged_source_urls;
+    for url in remove
+        .source_url
+        .into_iter()
+        .chain(remove.merged_source_urls)
+    {
+        if Some(&url) != keep.source_url.as_ref() && !merged_source_urls.contains(&url) {
+            merged_source_urls.push(url);
+        }
+    }
+
+    Takeoff {
+        id: keep.id,
+        name: text(keep.name, remove.name),
+        description,
+        image: keep.image.or(remove.image),
+        region: text(keep.region, remove.region),
+        altitude_m: keep.altitude_m.or(remove.altitude_m),
+        height_diff_m: keep.height_diff_m.or(remove.height_diff_m),
+        latitude,
+        longitude,
+        wind_dirs,
+        info_url: keep.info_url.or(remove.info_url),
+        source: keep.source,
+        source_url: keep.source_url,
+        created_at,
+        created_by,
+        updated_at,
+        updated_by,
+        merged_source_urls,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    /// A takeoff with a name and coordinates.
+    fn located(id: i32, name: &str, latitude: f64, longitude: f64) -> GetTakeoff {
+        GetTakeoff {
+            id: Some(id),
+            name: Some(name.to_owned()),
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            ..Default::default()
+        }
+    }
+
+    /// A complete takeoff.
+    fn takeoff(id: i32, name: &str) -> Takeoff {
+        Takeoff {
+            id,
+            name: name.to_owned(),
+            description: String::new(),
+            image: None,
+            region: String::new(),
+            altitude_m: None,
+            height_diff_m: None,
+            latitude: 60.64,
+            longitude: 6.41,
+            wind_dirs: Vec::new(),
+            info_url: None,
+            source: Some("flightlog".to_owned()),
+            source_url: Some(format!("https://flightlog.org/?start_id={id}")),
+            created_at: None,
+            created_by: None,
+            updated_at: None,
+            updated_by: None,
+            merged_source_urls: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn similarity() {
+        assert_eq!(name_similarity("Hanguren", "hanguren "), 1.0);
+        assert_eq!(name_similarity("Hanguren", "Voss - Hanguren"), 1.0);
+        assert_eq!(name_similarity("Hanguren", ""), 0.0);
+        assert_eq!(name_similarity("--", "Hanguren"), 0.0);
+        assert!(name_similarity("Hanguren", "Hangurén") > DEFAULT_MIN_SIMILARITY);
+        assert!(name_similarity("Hanguren", "Aksla") < DEFAULT_MIN_SIMILARITY);
+
+        let (a, b) = ("Voss Hanguren Nord", "Hanguren Sør");
+        assert_eq!(name_similarity(a, b), name_similarity(b, a));
+    }
+
+    #[test]
+    fn candidates() {
+        // About 45 m, 90 m and 1.1 km apart
+        let takeoffs = [
+            located(7, "Hanguren", 60.64, 6.41),
+            located(3, "Voss - Hanguren", 60.6404, 6.41),
+            located(5, "Aksla", 60.6402, 6.41),
+            located(9, "Hanguren", 60.6408, 6.41),
+            located(2, "Hanguren", 60.65, 6.41),
+            GetTakeoff {
+                id: None,
+                ..located(0, "Hanguren", 60.64, 6.41)
+            },
+        ];
+        let candidates = find_candidates(&takeoffs, &Config::default(), &HashSet::new());
+        let pairs: Vec<(i32, i32)> = candidates.iter().map(|c| (c.a, c.b)).collect();
+
+        assert_eq!(pairs, [(3, 7), (3, 9), (7, 9)]);
+        assert_eq!(candidates[0].a_name, "Voss - Hanguren");
+        assert_eq!(candidates[0].b_name, "Hanguren");
+        assert_eq!(candidates[0].similarity, 1.0);
+        assert!((candidates[0].distance_m - 44.5).abs() < 1.0);
+        assert!(candidates[0].score > candidates[1].score);
+
+        let dismissed = HashSet::from([(3, 7), (7, 9)]);
+        let candidates = find_candidates(&takeoffs, &Config::default(), &dismissed);
+        assert_eq!(candidates.len(), 1);
+        assert_eq!((candidates[0].a, candidates[0].b), (3, 9));
+
+        let strict = Config {
+            max_distance: 50.0,
+            min_similarity: 0.6,
+        };
+        assert_eq!(
+            find_candidates(&takeoffs, &strict, &HashSet::new()).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn merges() {
+        let date = |day| Some(Utc.with_ymd_and_hms(2020, 1, day, 0, 0, 0).unwrap());
+        let keep = Takeoff {
+            description: "Short".to_owned(),
+            altitude_m: Some(660),
+            latitude: 0.0,
+            longitude: 0.0,
+            wind_dirs: vec!["E".to_owned(), "N".to_owned()],
+            created_at: date(5),
+            created_by: Some("Kari".to_owned()),
+            updated_at: date(6),
+            updated_by: Some("Kari".to_owned()),
+            merged_source_urls: vec!["https://flightlog.org/?start_id=3".to_owned()],
+            ..takeoff(1, "Hanguren")
+        };
+        let remove = Takeoff {
+            description: "A longer description".to_owned(),
+            image: Some(vec![1, 2, 3]),
+            region: "Vestland".to_owned(),
+            altitude_m: Some(700),
+            height_diff_m: Some(600),
+            latitude: 60.64,
+            longitude: 6.41,
+            wind_dirs: vec!["NNE".to_owned(), "N".to_owned(), "Bad".to_owned()],
+            created_at: date(1),
+            created_by: Some("Ola".to_owned()),
+            updated_at: date(2),
+            updated_by: Some("Ola".to_owned()),
+            merged_source_urls: vec![
+                "https://flightlog.org/?start_id=1".to_owned(),
+                "https://flightlog.org/?start_id=4".to_owned(),
+            ],
+            ..takeoff(2, "")
+        };
+        let merged = merge(keep, remove);
+
+        assert_eq!(merged.id, 1);
+        assert_eq!(merged.name, "Hanguren");
+        assert_eq!(merged.description, "A longer description");
+        assert_eq!(merged.image, Some(vec![1, 2, 3]));
+        assert_eq!(merged.region, "Vestland");
+        assert_eq!(
+            (merged.altitude_m, merged.height_diff_m),
+            (Some(660), Some(600))
+        );
+        assert_eq!((merged.latitude, merged.longitude), (60.64, 6.41));
+        assert_eq!(merged.wind_dirs, ["N", "NNE", "E", "Bad"]);
+        assert_eq!(
+            (merged.created_at, merged.created_by.as_deref()),
+            (date(1), Some("Ola"))
+        );
+        assert_eq!(
+            (merged.updated_at, merged.updated_by.as_deref()),
+            (date(6), Some("Kari"))
+        );
+        assert_eq!(
+            merged.source_url.as_deref(),
+            Some("https://flightlog.org/?start_id=1")
+        );
+        assert_eq!(
+            merged.merged_source_urls,
+            [
+                "https://flightlog.org/?start_id=3",
+                "https://flightlog.org/?start_id=2",
+                "https://flightlog.org/?start_id=4",
+            ]
+        );
+    }
+}
@@ -0,0 +1,22 @@
+//! Geographic helpers.
+
+/// Mean radius of the earth in meters.
+pub const EARTH_RADIUS: f64 = 6_371_000.0;
+
+/// Get the great-circle distance between two points with the haversine formula.
+///
+/// Coordinates are in decimal degrees, and the distance is in meters.
+pub fn haversine(lat_a: f64, lon_a: f64, lat_b: f64, lon_b: f64) -> f64 {
+    let (lat_a, lat_b) = (lat_a.to_radians(), lat_b.to_radians());
+    let d_lat = lat_b - lat_a;
+    let d_lon = (lon_b - lon_a).to_radians();
+
+    let a = (d_lat / 2.0).sin().powi(2) + lat_a.cos() * lat_b.cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS * a.sqrt().min(1.0).asin()
+}
+
+/// Get the latitude span in degrees of a distance in meters.
+pub fn meters_to_latitude(meters: f64) -> f64 {
+    (meters / EARTH_RADIUS).to_degrees()
+}
@@ -0,0 +1,640 @@
+//! Validate takeoffs.
+//!
+//! Rules check single takeoffs (ranges, missing fields, wind directions and suspicious text),
+//! and takeoffs against each other (duplicate names and locations).
+
+pub mod duplicates;
+pub mod geo;
+
+use crate::models::GetTakeoff;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Wind directions a takeoff can have, the 16 compass points clockwise from north.
+pub const WIND_DIRS: [&str; 16] = [
+    "N", "NNE", "NE", "ENE", "E", "ESE", "SE", "SSE", "S", "SSW", "SW", "WSW", "W", "WNW", "NW",
+    "NNW",
+];
+/// Lowest plausible altitude in meters (the shore of the Dead Sea).
+pub const MIN_ALTITUDE: i32 = -430;
+/// Highest plausible altitude in meters (the top of Mount Everest).
+pub const MAX_ALTITUDE: i32 = 8849;
+/// Highest plausible height difference in meters.
+pub const MAX_HEIGHT_DIFF: i32 = 5000;
+/// Default distance in meters takeoffs must be apart to not be duplicates.
+pub const DEFAULT_DUPLICATE_DISTANCE: f64 = 100.0;
+
+/// Validation rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// Latitude or longitude is missing.
+    MissingCoordinates,
+    /// Latitude or longitude is out of range.
+    CoordinatesOutOfRange,
+    /// Coordinates are (0, 0), usually a placeholder.
+    NullIsland,
+    /// Name is empty.
+    MissingName,
+    /// Region is empty.
+    MissingRegion,
+    /// Description is empty.
+    MissingDescription,
+    /// Altitude is missing.
+    MissingAltitude,
+    /// Altitude is implausible.
+    AltitudeOutOfRange,
+    /// Height difference is implausible.
+    HeightDiffOutOfRange,
+    /// A wind direction isn't one of [`WIND_DIRS`].
+    InvalidWindDir,
+    /// A wind direction is repeated.
+    DuplicateWindDir,
+    /// Text has HTML, entities, control characters or surrounding whitespace.
+    SuspiciousText,
+    /// Another takeoff in the same region has the same name.
+    DuplicateName,
+    /// Another takeoff is closer than the duplicate distance.
+    DuplicateLocation,
+}
+
+impl Rule {
+    /// Get the rule as stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Rule::MissingCoordinates => "missing_coordinates",
+            Rule::CoordinatesOutOfRange => "coordinates_out_of_range",
+            Rule::NullIsland => "null_island",
+            Rule::MissingName => "missing_name",
+            Rule::MissingRegion => "missing_region",
+            Rule::MissingDescription => "missing_description",
+            Rule::MissingAltitude => "missing_altitude",
+            Rule::AltitudeOutOfRange => "altitude_out_of_range",
+            Rule::HeightDiffOutOfRange => "height_diff_out_of_range",
+            Rule::InvalidWindDir => "invalid_wind_dir",
+            Rule::DuplicateWindDir => "duplicate_wind_dir",
+            Rule::SuspiciousText => "suspicious_text",
+            Rule::DuplicateName => "duplicate_name",
+            Rule::DuplicateLocation => "duplicate_location",
+        }
+    }
+
+    /// Get the severity of issues found by the rule.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Rule::MissingCoordinates
+            | Rule::CoordinatesOutOfRange
+            | Rule::NullIsland
+            | Rule::MissingName
+            | Rule::InvalidWindDir => Severity::Error,
+            _ => Severity::Warning,
+        }
+    }
+}
+
+/// Severity of an issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// The takeoff is wrong.
+    Error,
+    /// The takeoff might be wrong or incomplete.
+    Warning,
+}
+
+impl Severity {
+    /// Get the severity as stored in the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// An issue with a takeoff.
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    /// Takeoff id, if the takeoff is in the database.
+    pub takeoff_id: Option<i32>,
+    /// Optional source URL of the takeoff.
+    pub source_url: Option<String>,
+    /// The broken rule.
+    pub rule: Rule,
+    /// Severity of the rule.
+    pub severity: Severity,
+    /// Description of the issue.
+    pub message: String,
+    /// Id of the other takeoff for duplicates.
+    pub related_id: Option<i32>,
+}
+
+impl Issue {
+    /// Create an issue with a takeoff.
+    fn new(takeoff: &GetTakeoff, rule: Rule, message: String) -> Self {
+        Self {
+            takeoff_id: takeoff.id,
+            source_url: takeoff.source_url.clone(),
+            rule,
+            severity: rule.severity(),
+            message,
+            related_id: None,
+        }
+    }
+}
+
+/// Validation options.
+#[derive(Debug, Clone, Copy)]
+pub struct Config {
+    /// Distance in meters takeoffs must be apart to not be duplicates.
+    pub duplicate_distance: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            duplicate_distance: DEFAULT_DUPLICATE_DISTANCE,
+        }
+    }
+}
+
+/// Result of validating takeoffs.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// Number of validated takeoffs.
+    pub checked: usize,
+    /// Number of issues with [`Severity::Error`].
+    pub errors: usize,
+    /// Number of issues with [`Severity::Warning`].
+    pub warnings: usize,
+    /// Number of issues per rule.
+    pub rules: BTreeMap<Rule, usize>,
+    /// All issues.
+    pub issues: Vec<Issue>,
+}
+
+/// Validate takeoffs against all rules.
+///
+/// Fields that weren't selected (`None`) are treated as missing.
+pub fn validate(takeoffs: &[GetTakeoff], config: &Config) -> Report {
+    let mut issues: Vec<Issue> = takeoffs.iter().flat_map(check_takeoff).collect();
+    issues.extend(check_duplicate_names(takeoffs));
+    issues.extend(check_duplicate_locations(
+        takeoffs,
+        config.duplicate_distance,
+    ));
+
+    let mut rules = BTreeMap::new();
+    for issue in &issues {
+        *rules.entry(issue.rule).or_insert(0) += 1;
+    }
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+
+    Report {
+        checked: takeoffs.len(),
+        errors,
+        warnings: issues.len() - errors,
+        rules,
+        issues,
+    }
+}
+
+/// Check a single takeoff.
+pub fn check_takeoff(takeoff: &GetTakeoff) -> Vec<Issue> {
+    let mut out = Vec::new();
+    let mut issue = |rule, message: String| out.push(Issue::new(takeoff, rule, message));
+
+    // Coordinates
+    match (takeoff.latitude, takeoff.longitude) {
+        (Some(latitude), Some(longitude)) => {
+            if !valid_coordinates(latitude, longitude) {
+                issue(
+                    Rule::CoordinatesOutOfRange,
+                    format!("coordinates ({latitude}, {longitude}) out of range"),
+                );
+            } else if latitude.abs() < 1e-6 && longitude.abs() < 1e-6 {
+                issue(Rule::NullIsland, "coordinates are (0, 0)".to_owned());
+            }
+        }
+        _ => issue(Rule::MissingCoordinates, "missing coordinates".to_owned()),
+    }
+
+    // Missing fields
+    let blank = |text: &Option<String>| text.as_deref().map_or(true, |text| text.trim().is_empty());
+    if blank(&takeoff.name) {
+        issue(Rule::MissingName, "missing name".to_owned());
+    }
+    if blank(&takeoff.region) {
+        issue(Rule::MissingRegion, "missing region".to_owned());
+    }
+    if blank(&takeoff.description) {
+        issue(Rule::MissingDescription, "missing description".to_owned());
+    }
+
+    // Altitudes
+    match takeoff.altitude_m {
+        Some(altitude) if !(MIN_ALTITUDE..=MAX_ALTITUDE).contains(&altitude) => issue(
+            Rule::AltitudeOutOfRange,
+            format!("altitude {altitude} m is implausible"),
+        ),
+        Some(_) => {}
+        None => issue(Rule::MissingAltitude, "missing altitude".to_owned()),
+    }
+    if let Some(height_diff) = takeoff.height_diff_m {
+        let landing = takeoff.altitude_m.map(|altitude| altitude - height_diff);
+        if !(0..=MAX_HEIGHT_DIFF).contains(&height_diff) {
+            issue(
+                Rule::HeightDiffOutOfRange,
+                format!("height difference {height_diff} m is implausible"),
+            );
+        } else if let Some(landing) = landing.filter(|landing| *landing < MIN_ALTITUDE) {
+            issue(
+                Rule::HeightDiffOutOfRange,
+                format!("height difference {height_diff} m puts the landing at {landing} m"),
+            );
+        }
+    }
+
+    // Wind directions
+    let wind_dirs = takeoff.wind_dirs.as_deref().unwrap_or_default();
+    for (i, dir) in wind_dirs.iter().enumerate() {
+        if !WIND_DIRS.contains(&dir.as_str()) {
+            issue(
+                Rule::InvalidWindDir,
+                format!("invalid wind direction {dir:?}"),
+            );
+        } else if wind_dirs[..i].contains(dir) {
+            issue(
+                Rule::DuplicateWindDir,
+                format!("wind direction {dir} is repeated"),
+            );
+        }
+    }
+
+    // Text
+    let texts = [
+        ("name", &takeoff.name),
+        ("region", &takeoff.region),
+        ("description", &takeoff.description),
+    ];
+    for (field, text) in texts {
+        if let Some(reason) = text.as_deref().and_then(suspicious_text) {
+            issue(Rule::SuspiciousText, format!("{field} {reason}"));
+        }
+    }
+
+    out
+}
+
+/// Find takeoffs with the same name in the same region.
+///
+/// Names are compared case-insensitively. The issue is on every takeoff after the first.
+pub fn check_duplicate_names(takeoffs: &[GetTakeoff]) -> Vec<Issue> {
+    let mut out = Vec::new();
+    let mut seen: HashMap<(String, String), &GetTakeoff> = HashMap::new();
+
+    for takeoff in takeoffs {
+        let Some(name) = takeoff
+            .name
+            .as_deref()
+            .map(normalize)
+            .filter(|n| !n.is_empty())
+        else {
+            continue;
+        };
+        let region = takeoff.region.as_deref().map(normalize).unwrap_or_default();
+
+        match seen.get(&(region.clone(), name.clone())) {
+            Some(first) => out.push(Issue {
+                related_id: first.id,
+                ..Issue::new(
+                    takeoff,
+                    Rule::DuplicateName,
+                    format!("same name as {}", describe(first)),
+                )
+            }),
+            None => {
+                seen.insert((region, name), takeoff);
+            }
+        }
+    }
+
+    out
+}
+
+/// Find takeoffs closer than `distance` meters to each other.
+///
+/// The issue is on the later takeoff of each pair.
+pub fn check_duplicate_locations(takeoffs: &[GetTakeoff], distance: f64) -> Vec<Issue> {
+    let mut pairs = nearby_pairs(takeoffs, distance);
+    pairs.sort_by_key(|(first, second, _)| (*second, *first));
+
+    pairs
+        .into_iter()
+        .map(|(first, second, meters)| Issue {
+            related_id: takeoffs[first].id,
+            ..Issue::new(
+                &takeoffs[second],
+                Rule::DuplicateLocation,
+                format!("{meters:.0} m from {}", describe(&takeoffs[first])),
+            )
+        })
+        .collect()
+}
+
+/// Find pairs of takeoffs closer than `distance` meters to each other.
+///
+/// Takeoffs with missing, invalid or (0, 0) coordinates are skipped.
+///
+/// # Returns
+///
+/// The indices of each pair in ascending order, and the distance between them in meters.
+pub fn nearby_pairs(takeoffs: &[GetTakeoff], distance: f64) -> Vec<(usize, usize, f64)> {
+    // Sort by latitude, so only takeoffs within the latitude span of `distance` are compared
+    let mut located: Vec<(usize, f64, f64)> = takeoffs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, takeoff)| Some((i, takeoff.latitude?, takeoff.longitude?)))
+        .filter(|(_, lat, lon)| valid_coordinates(*lat, *lon) && (*lat, *lon) != (0.0, 0.0))
+        .collect();
+    located.sort_by(|a, b| a.1.total_cmp(&b.1));
+    let span = geo::meters_to_latitude(distance);
+
+    let mut out = Vec::new();
+    for (a, &(i, lat_a, lon_a)) in located.iter().enumerate() {
+        for &(j, lat_b, lon_b) in located[a + 1..].iter() {
+            if lat_b - lat_a > span {
+                break;
+            }
+            let meters = geo::haversine(lat_a, lon_a, lat_b, lon_b);
+            if meters <= distance {
+                out.push((i.min(j), i.max(j), meters));
+            }
+        }
+    }
+
+    out
+}
+
+/// Check that coordinates are finite and in range.
+fn valid_coordinates(latitude: f64, longitude: f64) -> bool {
+    (-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)
+}
+
+/// Get the reason text looks scraped wrong, if it does.
+fn suspicious_text(text: &str) -> Option<&'static str> {
+    let chars: Vec<char> = text.chars().collect();
+
+    if text != text.trim() {
+        return Some("has surrounding whitespace");
+    }
+    if text.contains('\u{FFFD}') {
+        return Some("has replacement characters");
+    }
+    if text
+        .chars()
+        .any(|c| c.is_control() && !matches!(c, '\n' | '\r' | '\t'))
+    {
+        return Some("has control characters");
+    }
+    if chars
+        .windows(2)
+        .any(|w| w[0] == '<' && (w[1].is_ascii_alphabetic() || w[1] == '/' || w[1] == '!'))
+    {
+        return Some("has HTML tags");
+    }
+
+    // Entities like `&amp;` or `&#39;`
+    let has_entity = text.match_indices('&').any(|(i, _)| {
+        let rest = &text[i + 1..];
+        let end = rest.find(';').unwrap_or(0);
+        let entity = &rest[..end];
+        (1..=10).contains(&entity.len())
+            && entity
+                .strip_prefix('#')
+                .unwrap_or(entity)
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric())
+    });
+    if has_entity {
+        return Some("has HTML entities");
+    }
+
+    None
+}
+
+/// Normalize text for comparison.
+pub(crate) fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Describe a takeoff in messages.
+fn describe(takeoff: &GetTakeoff) -> String {
+    let name = takeoff.name.as_deref().unwrap_or_default();
+    match (takeoff.id, &takeoff.source_url) {
+        (Some(id), _) => format!("{name} (#{id})"),
+        (None, Some(url)) => format!("{name} ({url})"),
+        (None, None) => name.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A takeoff without issues.
+    fn takeoff(id: i32, name: &str, latitude: f64, longitude: f64) -> GetTakeoff {
+        GetTakeoff {
+            id: Some(id),
+            name: Some(name.to_owned()),
+            description: Some("Grass ramp facing the fjord.".to_owned()),
+            region: Some("Vestland".to_owned()),
+            altitude_m: Some(660),
+            height_diff_m: Some(600),
+            latitude: Some(latitude),
+            longitude: Some(longitude),
+            wind_dirs: Some(vec!["NE".to_owned(), "ENE".to_owned()]),
+            ..Default::default()
+        }
+    }
+
+    /// Rules of issues, in order.
+    fn rules(issues: &[Issue]) -> Vec<Rule> {
+        issues.iter().map(|issue| issue.rule).collect()
+    }
+
+    #[test]
+    fn valid_takeoff() {
+        assert!(check_takeoff(&takeoff(1, "Hanguren", 60.64, 6.41)).is_empty());
+    }
+
+    #[test]
+    fn missing_fields() {
+        let issues = check_takeoff(&GetTakeoff::default());
+
+        assert_eq!(
+            rules(&issues),
+            [
+                Rule::MissingCoordinates,
+                Rule::MissingName,
+                Rule::MissingRegion,
+                Rule::MissingDescription,
+                Rule::MissingAltitude,
+            ]
+        );
+        assert_eq!(issues[0].severity, Severity::Error);
+        assert_eq!(issues[2].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn coordinates() {
+        let out_of_range = check_takeoff(&takeoff(1, "A", 91.0, 6.0));
+        assert_eq!(rules(&out_of_range), [Rule::CoordinatesOutOfRange]);
+        assert_eq!(out_of_range[0].message, "coordinates (91, 6) out of range");
+
+        let nan = check_takeoff(&takeoff(1, "A", f64::NAN, 6.0));
+        assert_eq!(rules(&nan), [Rule::CoordinatesOutOfRange]);
+
+        let null_island = check_takeoff(&takeoff(1, "A", 0.0, 0.0));
+        assert_eq!(rules(&null_island), [Rule::NullIsland]);
+    }
+
+    #[test]
+    fn altitudes() {
+        let altitude = GetTakeoff {
+            altitude_m: Some(MAX_ALTITUDE + 1),
+            ..takeoff(1, "A", 60.0, 6.0)
+        };
+        assert_eq!(rules(&check_takeoff(&altitude)), [Rule::AltitudeOutOfRange]);
+
+        let height_diff = GetTakeoff {
+            height_diff_m: Some(-10),
+            ..takeoff(1, "A", 60.0, 6.0)
+        };
+        assert_eq!(
+            rules(&check_takeoff(&height_diff)),
+            [Rule::HeightDiffOutOfRange]
+        );
+
+        let landing = GetTakeoff {
+            altitude_m: Some(100),
+            height_diff_m: Some(600),
+            ..takeoff(1, "A", 60.0, 6.0)
+        };
+        let issues = check_takeoff(&landing);
+        assert_eq!(rules(&issues), [Rule::HeightDiffOutOfRange]);
+        assert_eq!(
+            issues[0].message,
+            "height difference 600 m puts the landing at -500 m"
+        );
+    }
+
+    #[test]
+    fn wind_dirs() {
+        let dirs = ["NNW", "N", "NNE", "X", "N", "ne"];
+        let takeoff = GetTakeoff {
+            wind_dirs: Some(dirs.map(str::to_owned).to_vec()),
+            ..takeoff(1, "A", 60.0, 6.0)
+        };
+        let issues = check_takeoff(&takeoff);
+
+        assert_eq!(
+            rules(&issues),
+            [
+                Rule::InvalidWindDir,
+                Rule::DuplicateWindDir,
+                Rule::InvalidWindDir
+            ]
+        );
+        assert_eq!(issues[0].message, "invalid wind direction \"X\"");
+        assert_eq!(issues[1].message, "wind direction N is repeated");
+        assert_eq!(issues[2].message, "invalid wind direction \"ne\"");
+    }
+
+    #[test]
+    fn suspicious_texts() {
+        let cases = [
+            (" Hanguren", "name has surrounding whitespace"),
+            ("Hang<b>uren</b>", "name has HTML tags"),
+            ("Fl&oslash;yen", "name has HTML entities"),
+            ("Fl\u{FFFD}yen", "name has replacement characters"),
+            ("Hang\u{7}uren", "name has control characters"),
+        ];
+
+        for (name, message) in cases {
+            let issues = check_takeoff(&takeoff(1, name, 60.0, 6.0));
+            assert_eq!(rules(&issues), [Rule::SuspiciousText], "{name}");
+            assert_eq!(issues[0].message, message);
+        }
+        assert!(check_takeoff(&takeoff(1, "Rock & Roll <3", 60.0, 6.0)).is_empty());
+    }
+
+    #[test]
+    fn duplicate_names() {
+        let takeoffs = [
+            takeoff(1, "Hanguren", 60.64, 6.41),
+            takeoff(2, "Aksla", 62.47, 6.15),
+            takeoff(3, "  HANGUREN ", 60.0, 6.0),
+            GetTakeoff {
+                region: Some("Innlandet".to_owned()),
+                ..takeoff(4, "Hanguren", 61.0, 9.0)
+            },
+            takeoff(5, "", 60.0, 6.0),
+            takeoff(6, "", 60.0, 6.0),
+        ];
+        let issues = check_duplicate_names(&takeoffs);
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, Rule::DuplicateName);
+        assert_eq!(issues[0].takeoff_id, Some(3));
+        assert_eq!(issues[0].related_id, Some(1));
+        assert_eq!(issues[0].message, "same name as Hanguren (#1)");
+    }
+
+    #[test]
+    fn duplicate_locations() {
+        // About 45 m and 1.1 km north of the first
+        let takeoffs = [
+            takeoff(1, "Hanguren", 60.64, 6.41),
+            takeoff(2, "Far", 60.65, 6.41),
+            takeoff(3, "Near", 60.6404, 6.41),
+            takeoff(4, "Null", 0.0, 0.0),
+            takeoff(5, "Null too", 0.0, 0.0),
+            GetTakeoff {
+                latitude: None,
+                ..takeoff(6, "Missing", 60.64, 6.41)
+            },
+        ];
+        let issues = check_duplicate_locations(&takeoffs, DEFAULT_DUPLICATE_DISTANCE);
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, Rule::DuplicateLocation);
+        assert_eq!(issues[0].takeoff_id, Some(3));
+        assert_eq!(issues[0].related_id, Some(1));
+        assert_eq!(issues[0].message, "44 m from Hanguren (#1)");
+
+        assert_eq!(check_duplicate_locations(&takeoffs, 2000.0).len(), 3);
+        assert!(check_duplicate_locations(&takeoffs, 10.0).is_empty());
+    }
+
+    #[test]
+    fn report() {
+        let takeoffs = [
+            takeoff(1, "Hanguren", 60.64, 6.41),
+            takeoff(2, "Hanguren", 60.6404, 6.41),
+            GetTakeoff::default(),
+        ];
+        let report = validate(&takeoffs, &Config::default());
+
+        assert_eq!(report.checked, 3);
+        assert_eq!((report.errors, report.warnings), (2, 5));
+        assert_eq!(report.rules[&Rule::DuplicateName], 1);
+        assert_eq!(report.rules[&Rule::DuplicateLocation], 1);
+    }
+}
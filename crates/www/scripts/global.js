@@ -53,72 +53,128 @@ async function fetch_takeoff(id) {
 }
 
 /**
- * Fetch all takeoffs - locally if available, remotely if not.
+ * Search takeoffs by name, region and description.
+ * 
+ * @param {String} q - Search query.
+ * @param {Number} [limit] - Optional number of results to fetch (max 100).
+ * @returns {Promise<Array<Object>>} A list of results, best match first, with HTML `name_highlight` and `snippet`.
+ */
+async function search_takeoffs(q, limit) {
+    try {
+        const url = new URL(window.location.origin);
+        url.pathname = "/api/v0/takeoffs/search";
+
+        url.searchParams.append("q", q);
+        if (limit !== undefined) url.searchParams.append("limit", limit);
+
+        const response = await fetch(url);
+        const out = response.json();
+
+        return out;
+    } catch (error) {
+        throw error;
+    }
+}
+
+/**
+ * Fetch takeoffs changed since a cursor.
+ * 
+ * @param {Number} [since] - Optional cursor of previous changes, fetches all takeoffs if missing.
+ * @param {Array<String>} [fields] Optional list of columns to fetch.
+ * @returns {Promise<Object>} The next `cursor`, changed `takeoffs`, ids of `deleted` takeoffs, and if the takeoffs are `full`.
+ */
+async function fetch_takeoffs_changes(since, fields) {
+    try {
+        const url = new URL(window.location.origin);
+        url.pathname = "/api/v0/takeoffs/changes";
+
+        if (since !== undefined) url.searchParams.append("since", since);
+        fields?.forEach((field) => url.searchParams.append("fields", field));
+
+        const response = await fetch(url);
+        const out = response.json();
+
+        return out;
+    } catch (error) {
+        throw error;
+    }
+}
+
+/**
+ * Fetch all takeoffs - locally if available, syncing changes since the last fetch.
  * 
  * @param {Array<String>} [fields] Optional list of columns to fetch (doesn't support `image`).
  * @returns {Promise<Array<Object>>} A list of takeoffs as objects.
  */
 async function fetch_all_takeoffs_prefer_local(fields) {
-    let out = [];
-
     // Check fields
     if (fields === undefined) fields = [];
     if (fields.includes("image")) throw new Error("unsupported field");
     if (!fields.includes("id")) fields.push("id");
 
-    // Calculate hash of remotely stored takeoffs
-    const hash = await fetch_takeoffs(undefined, undefined, undefined, ["id"])
-        .then((res) => res.map((v) => v.id).join(''))
-        .then((s => cyrb53(s).toString()));
+    const cached = JSON.parse(window.localStorage.getItem("takeoffs_cache"));
+    const covered = cached !== null && fields.every((field) => cached.fields.includes(field));
 
-    // Remote fetch and update local storage function
-    const get_remote = async () => {
-        const takeoffs = await fetch_takeoffs(undefined, undefined, undefined, fields);
-        window.localStorage.setItem("takeoffs", JSON.stringify(takeoffs));
-        window.localStorage.setItem("hash", cyrb53(takeoffs.map((v) => v.id).join('')).toString());
+    let out;
+    if (covered) {
+        // Apply changes to cached takeoffs
+        const changes = await fetch_takeoffs_changes(cached.cursor, cached.fields);
+        const takeoffs = new Map(changes.full ? [] : cached.takeoffs.map((v) => [v.id, v]));
+        changes.deleted.forEach((id) => takeoffs.delete(id));
+        changes.takeoffs.forEach((v) => takeoffs.set(v.id, v));
 
-        return takeoffs;
-    };
-
-    // Fetch remotely if hash is different, get local if not
-    if (hash !== window.localStorage.getItem("hash")) {
-        out = await get_remote();
+        out = { cursor: changes.cursor, fields: cached.fields, takeoffs: [...takeoffs.values()] };
+        out.takeoffs.sort((a, b) => a.id - b.id);
     } else {
-        out = JSON.parse(window.localStorage.getItem("takeoffs"));
-
-        // Fetch remotely if missing any fields
-        l1: for (let takeoff of out) {
-            l2: for (let field of fields) {
-                if (takeoff[field] === null) {
-                    out = await get_remote();
-                    break l1;
-                }
-            }
-        }
+        // Fetch all takeoffs, with the fields of the cache too
+        const all_fields = [...new Set([...(cached?.fields ?? []), ...fields])];
+        const changes = await fetch_takeoffs_changes(undefined, all_fields);
+
+        out = { cursor: changes.cursor, fields: all_fields, takeoffs: changes.takeoffs };
     }
+    window.localStorage.setItem("takeoffs_cache", JSON.stringify(out));
+    window.localStorage.removeItem("takeoffs");
+    window.localStorage.removeItem("hash");
+
+    return out.takeoffs;
+}
+
+/**
+ * Fetch a vector tile of takeoffs.
+ * 
+ * @param {Number} z - Zoom level.
+ * @param {Number} x - Tile column.
+ * @param {Number} y - Tile row.
+ * @returns {Promise<ArrayBuffer>} A Mapbox Vector Tile.
+ */
+async function fetch_takeoffs_tile(z, x, y) {
+    try {
+        const response = await fetch(`/tiles/takeoffs/${z}/${x}/${y}.mvt`);
+        if (!response.ok) {
+            throw new Error(`failed fetching tile ${z}/${x}/${y}: ${response.status}`);
+        }
 
-    return out;
+        return response.arrayBuffer();
+    } catch (error) {
+        throw error;
+    }
 }
 
 /**
- * Hash a string.
+ * Fetch statistics of the flights logged from a takeoff.
  * 
- * @param {String} str - A string.
- * @param {String} seed - A seed.
- * @returns A hash.
- * @copyright https://stackoverflow.com/a/52171480
+ * @param {Number} id - Takeoff id.
+ * @returns {Promise<Object>} The number of `flights` and `pilots`, `average_duration_min` and `best_flight`.
  */
-function cyrb53(str, seed = 0) {
-    let h1 = 0xdeadbeef ^ seed, h2 = 0x41c6ce57 ^ seed;
-    for(let i = 0, ch; i < str.length; i++) {
-        ch = str.charCodeAt(i);
-        h1 = Math.imul(h1 ^ ch, 2654435761);
-        h2 = Math.imul(h2 ^ ch, 1597334677);
+async function fetch_takeoff_flight_stats(id) {
+    try {
+        const response = await fetch(`/api/v0/takeoffs/${id}/flights/stats`);
+        if (!response.ok) {
+            throw new Error(`failed fetching flight stats of takeoff ${id}: ${response.status}`);
+        }
+
+        return response.json();
+    } catch (error) {
+        throw error;
     }
-    h1  = Math.imul(h1 ^ (h1 >>> 16), 2246822507);
-    h1 ^= Math.imul(h2 ^ (h2 >>> 13), 3266489909);
-    h2  = Math.imul(h2 ^ (h2 >>> 16), 2246822507);
-    h2 ^= Math.imul(h1 ^ (h1 >>> 13), 3266489909);
-  
-    return 4294967296 * (2097151 & h2) + (h1 >>> 0);
-};
+}
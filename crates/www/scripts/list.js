@@ -74,16 +74,11 @@ function handle_sorting(data) {
     const required_elements = [e_search, e_name_header, e_region_header, e_location_header];
     if (required_elements.includes(null)) throw new Error("missing HTML elements");
 
-    // Search sort
-    e_search.addEventListener("input", async (e) => {
-        for (let [takeoff, e_takeoff] of data) {
-            // Text match
-            const formatText = (text) => text.toLowerCase().replaceAll(' ', '');
-            const checkMatch = (text, search) => formatText(text).includes(formatText(search));
-            const match = checkMatch(takeoff.name + takeoff.description + takeoff.region, e.target.value);
-
-            e_takeoff.setAttribute("match", match);  
-        }
+    // Search sort (after typing stops)
+    let search_timeout;
+    e_search.addEventListener("input", (e) => {
+        clearTimeout(search_timeout);
+        search_timeout = setTimeout(() => search_sort(data, e.target.value).catch(console.error), 250);
     });
 
     // Name, description, region and location sort
@@ -96,6 +91,44 @@ function handle_sorting(data) {
     }
 }
 
+/** Id of the latest search. */
+let search_id = 0;
+
+/**
+ * Show takeoffs matching `query`, best match first, with matches highlighted.
+ * 
+ * @param {Array<Array<Object>>} data - Takeoff data and their nodes.
+ * @param {String} query - Search query, all takeoffs are shown if empty.
+ */
+async function search_sort(data, query) {
+    // Ignore results of earlier searches that finish late
+    const id = ++search_id;
+    const results = query.trim() === "" ? null : await search_takeoffs(query, 100);
+    if (id !== search_id) return;
+
+    const ranks = new Map(results?.map((result, i) => [result.id, [i, result]]));
+    data.forEach(([takeoff, e_takeoff], i) => {
+        const e_name = e_takeoff.getElementsByClassName("name").item(0);
+        const e_description = e_takeoff.getElementsByClassName("description").item(0);
+        if (e_name === null || e_description === null) throw new Error("missing HTML elements");
+
+        const match = ranks.get(takeoff.id);
+        if (match !== undefined) {
+            // Highlights are `<mark>` elements, the rest is escaped by the server
+            const [rank, result] = match;
+            e_name.innerHTML = result.name_highlight;
+            e_description.innerHTML = result.snippet;
+            e_takeoff.style.order = rank;
+        } else {
+            e_name.innerText = takeoff.name;
+            e_description.innerText = takeoff.description;
+            e_takeoff.style.order = i;
+        }
+
+        e_takeoff.setAttribute("match", results === null || match !== undefined);
+    });
+}
+
 /**
  * Alphabetically sort the `data` list based on the "order" attribute on `element`. 
  * 
@@ -37,25 +37,194 @@ async function init_map() {
         attribution: '&copy; <a href="http://www.openstreetmap.org/copyright">OpenStreetMap</a>'
     }).addTo(map);
 
-    // Fetch takeoffs and create markers
-    const markers = L.markerClusterGroup();
-    const takeoffs = await fetch_all_takeoffs_prefer_local(["name", "latitude", "longitude", "wind_dirs"]);
-    for (let takeoff of takeoffs) {
-        const icon = L.divIcon({
-            className: "marker-icon",
-            html: icon_for(takeoff),
-            iconSize: L.point(24, 24),
+    // Load takeoffs in vector tiles, clustered at low zoom levels
+    const takeoffs = new TakeoffLayer();
+    takeoffs.addTo(map);
+}
+
+/**
+ * Layer of takeoff markers, loaded from vector tiles.
+ */
+const TakeoffLayer = L.GridLayer.extend({
+    initialize(options) {
+        L.GridLayer.prototype.initialize.call(this, options);
+        this._markers = new Map();
+        this.on("tileunload", (e) => {
+            const key = tile_key(e.coords);
+            this._markers.get(key)?.remove();
+            this._markers.delete(key);
         });
-        const marker = L.marker([takeoff.latitude, takeoff.longitude], {
-            keyboard: false,
-            icon: icon,
+    },
+
+    createTile(coords, done) {
+        const tile = document.createElement("div");
+
+        fetch_takeoffs_tile(coords.z, coords.x, coords.y)
+            .then((buffer) => {
+                // Unloaded while fetching
+                if (!tile.isConnected) return done(null, tile);
+
+                const markers = L.layerGroup();
+                for (let feature of decode_tile(buffer)) {
+                    // Takeoffs in the buffer are in the neighbouring tile too
+                    if (feature.x < 0 || feature.y < 0 || feature.x >= feature.extent || feature.y >= feature.extent) continue;
+
+                    const point = L.point(coords.x + feature.x / feature.extent, coords.y + feature.y / feature.extent)
+                        .multiplyBy(this.getTileSize().x);
+                    const latlng = this._map.unproject(point, coords.z);
+                    markers.addLayer(marker_for(feature, latlng, this._map, coords.z));
+                }
+                markers.addTo(this._map);
+                this._markers.set(tile_key(coords), markers);
+
+                done(null, tile);
+            })
+            .catch((error) => {
+                console.error(error);
+                done(error, tile);
+            });
+
+        return tile;
+    },
+});
+
+function tile_key(coords) {
+    return `${coords.z}/${coords.x}/${coords.y}`;
+}
+
+function marker_for(feature, latlng, map, zoom) {
+    // Cluster of takeoffs, zooms in on click
+    if (feature.properties.point_count !== undefined) {
+        const count = feature.properties.point_count;
+        const size = count < 10 ? "small" : count < 100 ? "medium" : "large";
+        const icon = L.divIcon({
+            className: `marker-cluster marker-cluster-${size}`,
+            html: `<div><span>${count}</span></div>`,
+            iconSize: L.point(40, 40),
         });
+        const marker = L.marker(latlng, { keyboard: false, icon: icon });
+        marker.on("click", () => map.setView(latlng, zoom + 2));
+
+        return marker;
+    }
+
+    const takeoff = {
+        id: feature.id,
+        name: feature.properties.name,
+        wind_dirs: feature.properties.wind_dirs.split(",").filter((dir) => dir !== ""),
+    };
+    const icon = L.divIcon({
+        className: "marker-icon",
+        html: icon_for(takeoff),
+        iconSize: L.point(24, 24),
+    });
+    const marker = L.marker(latlng, {
+        keyboard: false,
+        icon: icon,
+    });
+    marker.bindPopup(`<a href="/takeoffs?id=${takeoff.id}" target="_blank">${takeoff.name}</a>`);
+
+    return marker;
+}
+
+/**
+ * Decode point features of a Mapbox Vector Tile.
+ * 
+ * @param {ArrayBuffer} buffer - A vector tile.
+ * @returns {Array<Object>} Features with `id`, `properties`, and `x` and `y` out of `extent`.
+ */
+function decode_tile(buffer) {
+    const bytes = new Uint8Array(buffer);
+    const text = new TextDecoder();
+    const string = (field) => text.decode(bytes.subarray(field.start, field.end));
+    const features = [];
+
+    for (let layer of pbf_fields(bytes, 0, bytes.length)) {
+        if (layer.field !== 3) continue;
+
+        const keys = [], values = [], raw = [];
+        let extent = 4096;
+        for (let field of pbf_fields(bytes, layer.start, layer.end)) {
+            if (field.field === 2) raw.push(field);
+            else if (field.field === 3) keys.push(string(field));
+            else if (field.field === 4) {
+                const value = pbf_fields(bytes, field.start, field.end).next().value;
+                values.push(value.field === 1 ? string(value) : value.value);
+            }
+            else if (field.field === 5) extent = field.value;
+        }
 
-        marker.bindPopup(`<a href="/takeoffs?id=${takeoff.id}" target="_blank">${takeoff.name}</a>`);
-        markers.addLayer(marker);
+        for (let field of raw) {
+            const feature = { id: undefined, properties: {}, x: 0, y: 0, extent: extent };
+            let tags = [], geometry = [];
+            for (let part of pbf_fields(bytes, field.start, field.end)) {
+                if (part.field === 1) feature.id = part.value;
+                else if (part.field === 2) tags = pbf_packed(bytes, part.start, part.end);
+                else if (part.field === 4) geometry = pbf_packed(bytes, part.start, part.end);
+            }
+
+            for (let i = 0; i + 1 < tags.length; i += 2) {
+                feature.properties[keys[tags[i]]] = values[tags[i + 1]];
+            }
+            // A single `MoveTo` with zigzag encoded coordinates
+            const unzigzag = (n) => (n >>> 1) ^ -(n & 1);
+            feature.x = unzigzag(geometry[1]);
+            feature.y = unzigzag(geometry[2]);
+
+            features.push(feature);
+        }
     }
 
-    map.addLayer(markers);
+    return features;
+}
+
+/**
+ * Read protobuf fields, with `value` for varints and `start` and `end` for length-delimited fields.
+ */
+function* pbf_fields(bytes, start, end) {
+    const reader = { bytes: bytes, pos: start };
+    while (reader.pos < end) {
+        const key = pbf_varint(reader);
+        const field = Math.floor(key / 8), wire = key % 8;
+        if (wire === 0) {
+            yield { field: field, value: pbf_varint(reader) };
+        } else if (wire === 2) {
+            const length = pbf_varint(reader);
+            yield { field: field, start: reader.pos, end: reader.pos + length };
+            reader.pos += length;
+        } else if (wire === 1) {
+            reader.pos += 8;
+        } else if (wire === 5) {
+            reader.pos += 4;
+        } else {
+            throw new Error(`unsupported wire type ${wire}`);
+        }
+    }
+}
+
+/**
+ * Read packed protobuf varints.
+ */
+function pbf_packed(bytes, start, end) {
+    const reader = { bytes: bytes, pos: start };
+    const out = [];
+    while (reader.pos < end) out.push(pbf_varint(reader));
+
+    return out;
+}
+
+/**
+ * Read a protobuf varint.
+ */
+function pbf_varint(reader) {
+    let value = 0, shift = 0, byte;
+    do {
+        byte = reader.bytes[reader.pos++];
+        value += (byte & 0x7f) * 2 ** shift;
+        shift += 7;
+    } while (byte & 0x80);
+
+    return value;
 }
 
 function icon_for(takeoff) {
@@ -8,6 +8,7 @@ window.onload = () => {
         if (id === null) throw new Error("missing id parameter");
     
         fetch_takeoff(id).then(display_takeoff);
+        fetch_takeoff_flight_stats(id).then(display_flight_stats).catch(console.error);
     } catch (error) {
         console.error(error);
     }
@@ -49,10 +50,10 @@ async function display_takeoff(takeoff) {
     e_name.innerText = takeoff.name;
     e_region.innerText = takeoff.region;
     e_description.innerText = takeoff.description;
-    e_altitude.innerText = takeoff.altitude;
-    e_altitude_diff.innerText = takeoff.altitude_diff;
-    e_updated.innerText = takeoff.updated;
-    e_created.innerText = takeoff.created;
+    e_altitude.innerText = takeoff.altitude_m;
+    e_altitude_diff.innerText = takeoff.height_diff_m;
+    e_updated.innerText = format_dated(takeoff.updated_at, takeoff.updated_by);
+    e_created.innerText = format_dated(takeoff.created_at, takeoff.created_by);
     e_source_url.setAttribute("href", takeoff.source_url);
     
     if (e_info_url !== null) {
@@ -97,7 +98,58 @@ async function display_takeoff(takeoff) {
     e_windy_height.addEventListener("change", synchronize_windy_slider);
 
     // Set closest initial height for height slider
-    const initial_number_height = number_heights.reduce((prev, curr) => (Math.abs(curr - takeoff.altitude) < Math.abs(prev - takeoff.altitude) ? curr : prev));
+    const initial_number_height = number_heights.reduce((prev, curr) => (Math.abs(curr - takeoff.altitude_m) < Math.abs(prev - takeoff.altitude_m) ? curr : prev));
     e_windy_height.value = number_heights.indexOf(initial_number_height);
     synchronize_windy_slider();
 }
+
+/**
+ * Fill HTML elements with flight statistics, if any flights are logged.
+ * 
+ * @param {Object} stats - Flight statistics of the takeoff.
+ */
+function display_flight_stats(stats) {
+    const e_flight_stats = document.getElementById("flight-stats");
+    const e_flights_logged = document.getElementById("flights-logged");
+    const e_average_duration = document.getElementById("average-duration");
+    const e_best_flight = document.getElementById("best-flight");
+
+    if ([e_flight_stats, e_flights_logged, e_average_duration, e_best_flight].includes(null)) {
+        throw new Error("missing HTML elements");
+    }
+    if (stats.flights === 0) return;
+
+    const best = stats.best_flight;
+    const best_distance = best.distance_km !== null ? `${best.distance_km.toFixed(1)} km, ` : "";
+
+    e_flights_logged.innerText = stats.flights;
+    e_average_duration.innerText = format_duration(stats.average_duration_min);
+    e_best_flight.innerText = `${best_distance}${format_duration(best.duration_min)} (${best.date})`;
+    e_flight_stats.removeAttribute("hidden");
+}
+
+/**
+ * Format a duration (e.g. `1 t 25 min`).
+ * 
+ * @param {Number} minutes - Duration in minutes.
+ * @returns {String} The hours and minutes, leaving out hours under one.
+ */
+function format_duration(minutes) {
+    const rounded = Math.round(minutes);
+    const hours = Math.floor(rounded / 60);
+
+    return hours > 0 ? `${hours} t ${rounded % 60} min` : `${rounded} min`;
+}
+
+/**
+ * Format a date and name (e.g. `2019-03-02 Kari`).
+ * 
+ * @param {String|null} date - RFC 3339 date.
+ * @param {String|null} name - Name.
+ * @returns {String} The day and name, leaving out any that are missing.
+ */
+function format_dated(date, name) {
+    const day = date ? new Date(date).toISOString().slice(0, 10) : null;
+
+    return [day, name].filter((v) => v).join(" ");
+}